@@ -1,6 +1,46 @@
+mod args;
+mod build;
+mod error;
+mod fetch;
+mod fetched;
+mod graph;
 mod new;
+mod official;
+mod plan;
 mod run;
+mod srcinfo;
 
-#[derive(Debug)]
+pub use args::{CommandPolicyArgs, ManifestArgs};
+pub use build::BuildArgs;
+pub use error::AppError;
+pub use fetch::FetchArgs;
+pub use graph::{GraphArgs, GraphFormat};
+pub use plan::PlanArgs;
+pub use srcinfo::SrcinfoArgs;
+
+use clap::{Parser, Subcommand};
+
+/// Build a pacman repository from a manifest of PKGBUILD sources.
+#[derive(Debug, Parser)]
+#[command(name = "build-pacman-repo", version)]
+#[non_exhaustive]
+pub struct App {
+    #[command(subcommand)]
+    pub command: AppCommand,
+}
+
+/// Subcommands of [`App`].
+#[derive(Debug, Subcommand)]
 #[non_exhaustive]
-pub struct App {}
+pub enum AppCommand {
+    /// Load the manifest and print the resolved sources.
+    Plan(PlanArgs),
+    /// Clone or update the git sources, check out their refs, and lock their commits.
+    Fetch(FetchArgs),
+    /// Regenerate the missing or stale `.SRCINFO` of the local sources in the build container.
+    Srcinfo(SrcinfoArgs),
+    /// Print the dependency graph of the fetched sources.
+    Graph(GraphArgs),
+    /// Build the fetched sources in containers, in dependency order.
+    Build(BuildArgs),
+}
@@ -0,0 +1,103 @@
+use super::AppError;
+use crate::{
+    build_plan::BuildPlan,
+    lockfile::Lockfile,
+    manifest::Manifest,
+    template::{CommandPolicy, TemplateContext},
+};
+use clap::Args;
+use std::path::PathBuf;
+
+/// Arguments to load the manifest file.
+#[derive(Debug, Args)]
+#[non_exhaustive]
+pub struct ManifestArgs {
+    /// Path to the manifest file.
+    #[arg(long, default_value = "build-pacman-repo.hjson")]
+    pub manifest: PathBuf,
+    /// Override the URL of the AUR.
+    #[arg(long, value_name = "URL")]
+    pub aur_url: Option<String>,
+    #[command(flatten)]
+    pub command_policy: CommandPolicyArgs,
+}
+
+impl ManifestArgs {
+    /// Load the manifest file into a [`BuildPlan`], whose AUR packages are resolved with the
+    /// pkgbases that the `fetch` subcommand recorded in the lockfile.
+    pub fn load(&self) -> Result<BuildPlan, AppError> {
+        let mut plan = self.load_unresolved()?;
+        if plan.aur_groups.is_empty() {
+            return Ok(plan);
+        }
+        let lockfile = Lockfile::read(&Lockfile::path(&plan.manifest_path))
+            .map_err(AppError::Lockfile)?
+            .unwrap_or_default();
+        plan.resolve_aur(&lockfile.aur)
+            .map_err(AppError::ResolveAur)?;
+        Ok(plan)
+    }
+
+    /// Load the manifest file into a [`BuildPlan`] without resolving its AUR packages.
+    ///
+    /// In dry-run mode, the commands are printed and [`AppError::CommandDryRun`] is returned,
+    /// since the plan would be made of their placeholder values.
+    pub fn load_unresolved(&self) -> Result<BuildPlan, AppError> {
+        let path = &self.manifest;
+        let mut manifest = Manifest::read(path).map_err(AppError::ReadManifest)?;
+        if let Some(aur_url) = &self.aur_url {
+            manifest.aur_url = Some(aur_url.clone());
+        }
+        let policy = self
+            .command_policy
+            .policy()
+            .restrict(manifest.command_policy.clone());
+        let dry_run = policy.dry_run;
+        let context = TemplateContext::new(Manifest::dir(path)).with_policy(policy);
+        let plan = manifest.into_build_plan(path, &context);
+        for command in context.dry_run_commands() {
+            eprintln!("Would run command: {command:?}");
+        }
+        let plan = plan.map_err(AppError::LoadManifest)?;
+        if dry_run {
+            return Err(AppError::CommandDryRun);
+        }
+        Ok(plan)
+    }
+}
+
+/// Arguments to restrict the commands run by the `cmd:` template queries.
+///
+/// These restrictions are combined with the `command-policy` of the manifest.
+#[derive(Debug, Args)]
+#[non_exhaustive]
+pub struct CommandPolicyArgs {
+    /// Forbid all `cmd:` template queries.
+    #[arg(long)]
+    pub no_cmd: bool,
+    /// Executable that `cmd:` template queries are allowed to invoke (repeatable).
+    #[arg(long = "cmd-allow", value_name = "EXECUTABLE")]
+    pub cmd_allow: Vec<String>,
+    /// Maximum number of seconds each command is allowed to run.
+    #[arg(long, value_name = "SECONDS")]
+    pub cmd_timeout: Option<u64>,
+    /// Maximum number of bytes each command is allowed to write to stdout.
+    #[arg(long, value_name = "BYTES")]
+    pub cmd_max_output: Option<usize>,
+    /// Print the commands instead of running them, then stop without doing anything else.
+    #[arg(long)]
+    pub cmd_dry_run: bool,
+}
+
+impl CommandPolicyArgs {
+    /// Create a [`CommandPolicy`] from the arguments.
+    pub fn policy(&self) -> CommandPolicy {
+        CommandPolicy {
+            disabled: self.no_cmd,
+            allowed_executables: (!self.cmd_allow.is_empty()).then(|| self.cmd_allow.clone()),
+            timeout: self.cmd_timeout,
+            max_output_size: self.cmd_max_output,
+            dry_run: self.cmd_dry_run,
+        }
+    }
+}
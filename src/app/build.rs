@@ -0,0 +1,155 @@
+use super::{fetched::locate_fetched, official::load_official, AppError, ManifestArgs};
+use crate::{
+    build::{BuildDecision, Builder, ImagePlan},
+    build_plan::BuildPlan,
+    graph::DependencyGraph,
+    repo_db::{Package, RepoDb},
+    sign::Signer,
+    sync_db::SyncPackages,
+};
+use clap::Args;
+use std::path::PathBuf;
+
+/// Arguments of the `build` subcommand.
+#[derive(Debug, Args)]
+#[non_exhaustive]
+pub struct BuildArgs {
+    #[command(flatten)]
+    pub manifest: ManifestArgs,
+    /// Rebuild a pkgbase even if the repository has its packages at the same version.
+    #[arg(long, value_name = "PKGBASE")]
+    pub force: Vec<String>,
+}
+
+impl BuildArgs {
+    /// Build the fetched sources that are newer than the repository in dependency order, print
+    /// the paths to the packages, and add them to the repository database.
+    ///
+    /// With a signing key, the packages and the database are signed, then all their signatures
+    /// are verified.
+    pub fn run(self) -> Result<(), AppError> {
+        let plan = self.manifest.load()?;
+        let sources = locate_fetched(&plan)?;
+        let srcinfos: Vec<_> = sources
+            .iter()
+            .map(|source| source.srcinfo.clone())
+            .collect();
+        if let Some(unknown) = self
+            .force
+            .iter()
+            .find(|base| !srcinfos.iter().any(|srcinfo| srcinfo.base == **base))
+        {
+            return Err(AppError::UnknownPackage(unknown.clone()));
+        }
+        let graph = DependencyGraph::new(&srcinfos);
+        let order = graph.build_order().map_err(AppError::Cycle)?;
+        // The dependencies between the sources are checked before the sync databases are
+        // downloaded, the official packages are only needed for the others.
+        graph
+            .check_constraints(&srcinfos, &SyncPackages::default())
+            .map_err(AppError::Unsatisfied)?;
+        let official = load_official(&plan, &srcinfos)?;
+        graph
+            .check_constraints(&srcinfos, &official)
+            .map_err(AppError::Unsatisfied)?;
+        let images = ImagePlan::new(&srcinfos, &graph);
+
+        let signer = plan
+            .signing_key
+            .clone()
+            .map(|key| Signer::new(key).with_home(plan.gpg_home.clone()));
+        let mut repo_db =
+            RepoDb::read(&plan.package_dir, &plan.repo_name).map_err(AppError::RepoDb)?;
+        let decisions: Vec<_> = srcinfos
+            .iter()
+            .map(|srcinfo| {
+                let forced = self.force.contains(&srcinfo.base);
+                BuildDecision::new(srcinfo, &repo_db, &plan.package_dir, forced)
+            })
+            .collect();
+        let mut built = vec![Vec::new(); sources.len()];
+        for (index, srcinfo) in srcinfos.iter().enumerate() {
+            if decisions[index].needs_build() {
+                continue;
+            }
+            eprintln!("Skipping {}: {}", srcinfo.base, decisions[index]);
+            built[index] = srcinfo
+                .names()
+                .filter_map(|name| repo_db.get(name))
+                .map(|entry| plan.package_dir.join(&entry.file_name))
+                .collect();
+        }
+        let order: Vec<_> = order
+            .into_iter()
+            .filter(|&index| decisions[index].needs_build())
+            .collect();
+        if order.is_empty() {
+            return verify_signatures(signer.as_ref(), &repo_db, &plan);
+        }
+
+        let builder = Builder::new(&plan);
+        eprintln!("Preparing {} image layers", images.layers.len());
+        builder.build_layers(&images).map_err(AppError::Build)?;
+        let total = order.len();
+        for (count, index) in order.into_iter().enumerate() {
+            let source = &sources[index];
+            let base = &source.srcinfo.base;
+            eprintln!(
+                "[{}/{total}] Building {base}: {}",
+                count + 1,
+                decisions[index]
+            );
+            let layer = &images.layers[images.node_layers[index]];
+            let local_packages: Vec<_> = graph
+                .dependencies(index)
+                .into_iter()
+                .flat_map(|dependency| built[dependency].iter().cloned())
+                .collect();
+            let packages = builder
+                .build(base, &source.dir, layer, &local_packages)
+                .map_err(AppError::Build)?;
+            for package in &packages {
+                println!("{}", package.display());
+                if let Some(signer) = &signer {
+                    signer.sign(package).map_err(AppError::Sign)?;
+                }
+                let package = Package::read(package).map_err(AppError::RepoDb)?;
+                repo_db.add(&package);
+            }
+            repo_db
+                .write(&plan.package_dir, &plan.repo_name)
+                .map_err(AppError::RepoDb)?;
+            if let Some(signer) = &signer {
+                for (path, link) in RepoDb::links(&plan.package_dir, &plan.repo_name) {
+                    signer.sign_linked(&path, &link).map_err(AppError::Sign)?;
+                }
+            }
+            built[index] = packages;
+        }
+        verify_signatures(signer.as_ref(), &repo_db, &plan)
+    }
+}
+
+/// Verify the signatures of the packages in the repository database and of the database itself.
+fn verify_signatures(
+    signer: Option<&Signer>,
+    repo_db: &RepoDb,
+    plan: &BuildPlan,
+) -> Result<(), AppError> {
+    let Some(signer) = signer else {
+        return Ok(());
+    };
+    let package_dir = &plan.package_dir;
+    let packages = repo_db
+        .entries
+        .values()
+        .map(|entry| package_dir.join(&entry.file_name));
+    let databases = RepoDb::links(package_dir, &plan.repo_name)
+        .into_iter()
+        .map(|(path, _)| path)
+        .filter(|path| path.exists());
+    let paths: Vec<PathBuf> = packages.chain(databases).collect();
+    signer
+        .verify_all(paths.iter().map(PathBuf::as_path))
+        .map_err(AppError::Verify)
+}
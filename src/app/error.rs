@@ -0,0 +1,76 @@
+use crate::{
+    aur::AurError,
+    build::BuildError,
+    build_plan::ResolveAurError,
+    container::ContainerError,
+    fetch::FetchError,
+    graph::{DependencyCycleError, UnsatisfiedDependenciesError},
+    lockfile::LockfileError,
+    manifest::{ContextLoadManifestError, ReadManifestError},
+    repo_db::RepoDbError,
+    resolve::ResolveError,
+    sign::{SignError, VerifyError},
+    srcinfo::{ReadSrcinfoError, SrcinfoMismatchError},
+    sync_db::LoadSyncDbError,
+    template::TemplateContext,
+};
+use derive_more::{Display, Error};
+use std::{io, path::PathBuf};
+
+/// Error when a subcommand of [`App`](super::App) fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum AppError {
+    #[display("{_0}")]
+    ReadManifest(ReadManifestError),
+    #[display("{_0}")]
+    LoadManifest(ContextLoadManifestError<TemplateContext>),
+    #[display("Stopped after printing the commands of the dry run")]
+    CommandDryRun,
+    #[display("{_0}")]
+    Aur(AurError),
+    #[display("{_0}")]
+    ResolveAur(ResolveAurError),
+    #[display("{_0}")]
+    Fetch(FetchError),
+    #[display("Failed to fetch {failed} of {total} sources")]
+    FetchFailed { failed: usize, total: usize },
+    #[display("{_0}")]
+    Lockfile(LockfileError),
+    #[display("{_0}")]
+    Srcinfo(ReadSrcinfoError),
+    #[display("{base}: {} is missing, generate it with the `srcinfo` subcommand", path.display())]
+    MissingSrcinfo { base: String, path: PathBuf },
+    #[display("{_0}")]
+    SrcinfoMismatch(SrcinfoMismatchError),
+    #[display("Failed to write {}: {error}", path.display())]
+    WriteSrcinfo {
+        path: PathBuf,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("Outdated .SRCINFO: {}", _0.join(", "))]
+    OutdatedSrcinfo(#[error(not(source))] Vec<String>),
+    #[display("{_0}")]
+    Container(ContainerError),
+    #[display("{_0}")]
+    Cycle(DependencyCycleError),
+    #[display("{_0}")]
+    Unsatisfied(UnsatisfiedDependenciesError),
+    #[display("{_0}")]
+    Build(BuildError),
+    #[display("{_0}")]
+    RepoDb(RepoDbError),
+    #[display("{_0}")]
+    Sign(SignError),
+    #[display("{_0}")]
+    Verify(VerifyError),
+    #[display("No source builds {_0:?}")]
+    UnknownPackage(#[error(not(source))] String),
+    #[display("{_0}")]
+    SyncDb(LoadSyncDbError),
+    #[display("{_0}")]
+    Resolve(ResolveError),
+    #[display("Failed to serialize the output: {_0}")]
+    Serialize(serde_hjson::Error),
+}
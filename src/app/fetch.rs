@@ -0,0 +1,254 @@
+use super::{fetched::read_srcinfo, official::load_official, AppError, ManifestArgs};
+use crate::{
+    aur::AurClient,
+    build_plan::BuildPlan,
+    fetch::{FetchError, FetchProgress, FetchedPkgBuild, Fetcher},
+    lockfile::{LockMode, Lockfile, LockfileError},
+    pkgbuild_desc::PkgBuildDesc,
+    resolve::resolve_aur_dependencies,
+    srcinfo::Srcinfo,
+};
+use clap::Args;
+use std::{
+    collections::BTreeMap,
+    num::NonZeroUsize,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// Arguments of the `fetch` subcommand.
+#[derive(Debug, Args)]
+#[non_exhaustive]
+pub struct FetchArgs {
+    #[command(flatten)]
+    pub manifest: ManifestArgs,
+    /// Only fetch the commits in the lockfile, fail if it disagrees with the manifest.
+    #[arg(long, conflicts_with = "update")]
+    pub locked: bool,
+    /// Ignore the locked commits of the given pkgbases, or of all sources if none is given.
+    #[arg(long, value_name = "PKGBASE", num_args = 0..)]
+    pub update: Option<Vec<String>>,
+    /// Maximum number of sources to fetch at once [default: number of CPUs].
+    #[arg(long, short)]
+    pub jobs: Option<NonZeroUsize>,
+}
+
+/// State shared by the successive rounds of fetching.
+struct Session {
+    fetcher: Fetcher,
+    jobs: NonZeroUsize,
+    lockfile: Lockfile,
+    new_lockfile: Lockfile,
+    fetched: Vec<FetchedPkgBuild>,
+    errors: Vec<FetchError>,
+    total: usize,
+}
+
+impl FetchArgs {
+    /// Fetch all sources and their missing dependencies, print the directory of each PKGBUILD,
+    /// and update the lockfile.
+    pub fn run(self) -> Result<(), AppError> {
+        let mut plan = self.manifest.load_unresolved()?;
+        let mode = match self.update {
+            _ if self.locked => LockMode::Locked,
+            None => LockMode::Reuse,
+            Some(bases) if bases.is_empty() => LockMode::UpdateAll,
+            Some(bases) => LockMode::Update(bases),
+        };
+        let lockfile_path = Lockfile::path(&plan.manifest_path);
+        let lockfile =
+            Lockfile::load(&lockfile_path, &mode, &plan.sources).map_err(AppError::Lockfile)?;
+        let aur_pkgbases = aur_pkgbases(&plan, &lockfile, &mode)?;
+        plan.resolve_aur(&aur_pkgbases)
+            .map_err(AppError::ResolveAur)?;
+        if mode == LockMode::Locked {
+            // The sources of the AUR packages were unknown when the lockfile was loaded.
+            lockfile.check_locked(&plan.sources).map_err(|error| {
+                AppError::Lockfile(LockfileError::Mismatch {
+                    path: lockfile_path.clone(),
+                    error,
+                })
+            })?;
+        }
+
+        let jobs = self
+            .jobs
+            .or_else(|| thread::available_parallelism().ok())
+            .unwrap_or(NonZeroUsize::MIN);
+        let mut session = Session {
+            fetcher: Fetcher::new(plan.pkgbuild_dir.clone()),
+            jobs,
+            lockfile,
+            new_lockfile: Lockfile {
+                aur: aur_pkgbases,
+                ..Lockfile::default()
+            },
+            fetched: Vec::new(),
+            errors: Vec::new(),
+            total: 0,
+        };
+        session.fetch(&plan.sources);
+
+        // Dependencies can only be resolved once the `.SRCINFO` of every source is available.
+        let resolved = if session.errors.is_empty() {
+            read_srcinfos(&session.fetched).and_then(|srcinfos| resolve(&plan, &srcinfos))
+        } else {
+            Ok(Vec::new())
+        };
+        let mut sources = plan.sources.clone();
+        let first_added = session.fetched.len();
+        if let Ok(added) = &resolved {
+            for source in added {
+                eprintln!("Adding dependency {}", source.package().base());
+            }
+            session.fetch(added);
+            sources.extend(added.iter().cloned());
+        } else {
+            // Keep the locked commits of the dependencies that could not be resolved this time.
+            for (base, locked) in &session.lockfile.sources {
+                if !session.new_lockfile.sources.contains_key(base) {
+                    session
+                        .new_lockfile
+                        .sources
+                        .insert(base.clone(), locked.clone());
+                }
+            }
+        }
+
+        if mode == LockMode::Locked {
+            if resolved.is_ok() && session.errors.is_empty() {
+                session.lockfile.verify(&sources).map_err(|error| {
+                    AppError::Lockfile(LockfileError::Mismatch {
+                        path: lockfile_path.clone(),
+                        error,
+                    })
+                })?;
+            }
+        } else {
+            session
+                .new_lockfile
+                .write(&lockfile_path)
+                .map_err(AppError::Lockfile)?;
+        }
+
+        resolved?;
+        if session.errors.is_empty() {
+            read_srcinfos(&session.fetched[first_added..])?;
+            return Ok(());
+        }
+        for error in &session.errors {
+            eprintln!("error: {error}");
+        }
+        Err(AppError::FetchFailed {
+            failed: session.errors.len(),
+            total: session.total,
+        })
+    }
+}
+
+impl Session {
+    /// Fetch sources with their locked commits, and lock the fetched commits.
+    fn fetch(&mut self, sources: &[PkgBuildDesc]) {
+        let pinned: Vec<_> = sources
+            .iter()
+            .map(|source| self.lockfile.pin(source))
+            .collect();
+        let total = pinned.len();
+        let finished = AtomicUsize::new(0);
+        let results = self.fetcher.fetch_all(&pinned, self.jobs, |progress| {
+            if let FetchProgress::Finished { source, result } = progress {
+                let count = finished.fetch_add(1, Ordering::Relaxed) + 1;
+                let base = source.package().base();
+                let status = if result.is_ok() { "Fetched" } else { "Failed" };
+                eprintln!("[{count}/{total}] {status} {base}");
+            }
+        });
+        self.total += total;
+
+        for (source, result) in sources.iter().zip(results) {
+            let fetched = match result {
+                Ok(fetched) => fetched,
+                Err(error) => {
+                    // Keep the previously locked commit of a source that failed to fetch.
+                    if let Some(locked) = self.lockfile.sources.get(source.package().base()) {
+                        self.new_lockfile
+                            .sources
+                            .insert(source.package().base().to_string(), locked.clone());
+                    }
+                    self.errors.push(error);
+                    continue;
+                }
+            };
+            let base = fetched.package.base();
+            let dir = fetched.dir.display();
+            match (source, &fetched.commit) {
+                (PkgBuildDesc::Git(source), Some(commit)) => {
+                    println!("{base}: {dir} ({commit})");
+                    self.new_lockfile
+                        .insert(source, commit.clone(), fetched.shared_with.clone());
+                }
+                _ => println!("{base}: {dir}"),
+            }
+            self.fetched.push(fetched);
+        }
+    }
+}
+
+/// Read the `.SRCINFO` of the fetched PKGBUILDs and check them against the manifest.
+fn read_srcinfos(fetched: &[FetchedPkgBuild]) -> Result<Vec<Srcinfo>, AppError> {
+    let mut srcinfos = Vec::with_capacity(fetched.len());
+    for fetched in fetched {
+        let srcinfo = read_srcinfo(fetched)?;
+        srcinfo
+            .check(&fetched.package)
+            .map_err(AppError::SrcinfoMismatch)?;
+        let undeclared = srcinfo.undeclared_names(&fetched.package);
+        if !undeclared.is_empty() {
+            let base = &srcinfo.base;
+            let names = undeclared.join(", ");
+            eprintln!("warning: {base}: .SRCINFO also builds {names}");
+        }
+        srcinfos.push(srcinfo);
+    }
+    Ok(srcinfos)
+}
+
+/// Get the pkgbase of each AUR package of the plan, indexed by package name.
+///
+/// The pkgbases in the lockfile are reused, the others are looked up in the AUR unless the
+/// lockfile is required to be up to date.
+fn aur_pkgbases(
+    plan: &BuildPlan,
+    lockfile: &Lockfile,
+    mode: &LockMode,
+) -> Result<BTreeMap<String, String>, AppError> {
+    let mut pkgbases = BTreeMap::new();
+    let mut unknown = Vec::new();
+    for name in plan.aur_names() {
+        match lockfile.aur.get(name) {
+            Some(base) => {
+                pkgbases.insert(name.to_string(), base.clone());
+            }
+            None => unknown.push(name.to_string()),
+        }
+    }
+    if unknown.is_empty() || *mode == LockMode::Locked {
+        return Ok(pkgbases);
+    }
+    let packages = AurClient::new(plan.aur_url.clone())
+        .info(&unknown)
+        .map_err(AppError::Aur)?;
+    for package in packages {
+        pkgbases.insert(package.name, package.package_base);
+    }
+    Ok(pkgbases)
+}
+
+/// Find the AUR dependencies of the fetched sources that are missing from the plan.
+fn resolve(plan: &BuildPlan, srcinfos: &[Srcinfo]) -> Result<Vec<PkgBuildDesc>, AppError> {
+    let official = load_official(plan, srcinfos)?;
+    let aur = AurClient::new(plan.aur_url.clone());
+    resolve_aur_dependencies(srcinfos, &official, &aur, plan.missing_dependencies)
+        .map_err(AppError::Resolve)
+        .map(|added| added.into_iter().map(PkgBuildDesc::Git).collect())
+}
@@ -0,0 +1,122 @@
+use super::AppError;
+use crate::{
+    build_plan::BuildPlan,
+    fetch::{FetchedPkgBuild, Fetcher},
+    lockfile::Lockfile,
+    pkgbuild_desc::{GitPkgBuildDesc, PkgBuildDesc},
+    pkgbuild_name::PkgBuildName,
+    srcinfo::{ReadSrcinfoError, Srcinfo},
+};
+use std::{io, path::PathBuf};
+
+/// PKGBUILD directory that was fetched by the `fetch` subcommand, alongside its `.SRCINFO`.
+#[derive(Debug)]
+pub(super) struct FetchedSource {
+    pub dir: PathBuf,
+    pub srcinfo: Srcinfo,
+}
+
+/// Locate the PKGBUILDs of the plan and of the dependencies that `fetch` added to the lockfile.
+pub(super) fn locate_fetched(plan: &BuildPlan) -> Result<Vec<FetchedSource>, AppError> {
+    let fetcher = Fetcher::new(plan.pkgbuild_dir.clone());
+    let lockfile = Lockfile::read(&Lockfile::path(&plan.manifest_path))
+        .map_err(AppError::Lockfile)?
+        .unwrap_or_default();
+
+    let shared_with = |base: &str| {
+        lockfile
+            .sources
+            .get(base)
+            .and_then(|locked| locked.shared_with.as_deref())
+    };
+
+    let mut sources = Vec::new();
+    for source in &plan.sources {
+        let fetched = fetcher
+            .locate_shared(source, shared_with(source.package().base()))
+            .map_err(AppError::Fetch)?;
+        let srcinfo = read_srcinfo(&fetched)?;
+        srcinfo
+            .check(&fetched.package)
+            .map_err(AppError::SrcinfoMismatch)?;
+        sources.push(FetchedSource {
+            dir: fetched.dir,
+            srcinfo,
+        });
+    }
+
+    let dependencies = lockfile.sources.iter().filter(|(base, _)| {
+        !plan
+            .sources
+            .iter()
+            .any(|source| source.package().base() == *base)
+    });
+    for (base, locked) in dependencies {
+        let source = GitPkgBuildDesc {
+            package: PkgBuildName::single(base.clone()),
+            git_url: locked.git_url.clone(),
+            git_depth: None,
+            git_ref: Some(locked.commit.clone()),
+            sub_dir: locked.sub_dir.clone(),
+        };
+        let fetched = fetcher
+            .locate_shared(&PkgBuildDesc::Git(source), locked.shared_with.as_deref())
+            .map_err(AppError::Fetch)?;
+        let srcinfo = read_srcinfo(&fetched)?;
+        sources.push(FetchedSource {
+            dir: fetched.dir,
+            srcinfo,
+        });
+    }
+    Ok(sources)
+}
+
+/// Read the `.SRCINFO` of a fetched PKGBUILD.
+///
+/// Local sources are not expected to have one until the `srcinfo` subcommand generates it.
+pub(super) fn read_srcinfo(fetched: &FetchedPkgBuild) -> Result<Srcinfo, AppError> {
+    match Srcinfo::read(&fetched.dir) {
+        Ok(srcinfo) => Ok(srcinfo),
+        Err(ReadSrcinfoError::Io { path, error })
+            if fetched.commit.is_none() && error.kind() == io::ErrorKind::NotFound =>
+        {
+            Err(AppError::MissingSrcinfo {
+                base: fetched.package.base().to_string(),
+                path,
+            })
+        }
+        Err(error) => Err(AppError::Srcinfo(error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    #[test]
+    fn missing_srcinfo_of_local_source() {
+        let dir = env::temp_dir().join(format!("pacman-repo-builder-fetched-{}", process::id(),));
+        fs::create_dir_all(&dir).unwrap();
+        let mut fetched = FetchedPkgBuild {
+            package: PkgBuildName::single("foo".to_string()),
+            dir: dir.clone(),
+            commit: None,
+            shared_with: None,
+        };
+        match read_srcinfo(&fetched).unwrap_err() {
+            AppError::MissingSrcinfo { base, path } => {
+                assert_eq!(base, "foo");
+                assert_eq!(path, dir.join(".SRCINFO"));
+            }
+            error => panic!("unexpected error: {error}"),
+        }
+
+        fetched.commit = Some("0123abc".to_string());
+        assert!(matches!(
+            read_srcinfo(&fetched).unwrap_err(),
+            AppError::Srcinfo(ReadSrcinfoError::Io { .. }),
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+use super::{fetched::locate_fetched, official::load_official, AppError, ManifestArgs};
+use crate::graph::{DependencyGraph, ExportOptions, ExportedGraph};
+use clap::{Args, ValueEnum};
+use pipe_trait::Pipe;
+
+/// Arguments of the `graph` subcommand.
+#[derive(Debug, Args)]
+#[non_exhaustive]
+pub struct GraphArgs {
+    #[command(flatten)]
+    pub manifest: ManifestArgs,
+    /// Output format.
+    #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+    pub format: GraphFormat,
+    /// Include the dependencies from the official repositories.
+    #[arg(long)]
+    pub official: bool,
+    /// Only show the pkgbases, not the packages of split PKGBUILDs.
+    #[arg(long)]
+    pub collapse_split: bool,
+    /// Highlight a pkgbase or package and everything that depends on it.
+    #[arg(long, value_name = "PACKAGE")]
+    pub highlight: Option<String>,
+}
+
+/// Output format of the `graph` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[non_exhaustive]
+pub enum GraphFormat {
+    /// Graphviz DOT language.
+    Dot,
+    /// JSON object of nodes and edges.
+    Json,
+    /// Mermaid flowchart.
+    Mermaid,
+}
+
+impl GraphArgs {
+    /// Print the dependency graph of the fetched sources.
+    pub fn run(self) -> Result<(), AppError> {
+        let plan = self.manifest.load()?;
+        let srcinfos: Vec<_> = locate_fetched(&plan)?
+            .into_iter()
+            .map(|fetched| fetched.srcinfo)
+            .collect();
+        let graph = DependencyGraph::new(&srcinfos);
+
+        let highlight = match &self.highlight {
+            None => None,
+            Some(name) => srcinfos
+                .iter()
+                .position(|srcinfo| srcinfo.base == *name || srcinfo.names().any(|own| own == name))
+                .ok_or_else(|| AppError::UnknownPackage(name.clone()))?
+                .pipe(Some),
+        };
+        let official = match self.official {
+            true => load_official(&plan, &srcinfos)?.pipe(Some),
+            false => None,
+        };
+        let options = ExportOptions {
+            collapse_split: self.collapse_split,
+            official: official.as_ref(),
+            highlight,
+        };
+
+        let exported = ExportedGraph::new(&srcinfos, &graph, options);
+        let output = match self.format {
+            GraphFormat::Dot => exported.to_dot(),
+            GraphFormat::Json => exported.to_json(),
+            GraphFormat::Mermaid => exported.to_mermaid(),
+        };
+        println!("{}", output.trim_end());
+        Ok(())
+    }
+}
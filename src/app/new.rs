@@ -1,7 +1,8 @@
 use super::App;
+use clap::Parser;
 
 impl App {
     pub fn from_env() -> Self {
-        App {}
+        App::parse()
     }
 }
@@ -0,0 +1,33 @@
+use super::AppError;
+use crate::{
+    build_plan::BuildPlan, container::ContainerManager, srcinfo::Srcinfo, sync_db::SyncPackages,
+};
+
+/// Sub directory of the container directory to download the sync databases into.
+///
+/// A pkgbase cannot start with a dot, so this never clashes with the build context of a pkgbase.
+const SYNC_DB_DIR: &str = ".sync";
+
+/// Load the packages of the official repositories, unless the sources provide all their
+/// dependencies.
+pub(super) fn load_official(
+    plan: &BuildPlan,
+    srcinfos: &[Srcinfo],
+) -> Result<SyncPackages, AppError> {
+    if !SyncPackages::are_needed_by(srcinfos) {
+        return Ok(SyncPackages::default());
+    }
+    match &plan.sync_db_dir {
+        Some(dir) => SyncPackages::load(dir, &plan.official_repositories),
+        None => {
+            eprintln!("Downloading the sync databases in {}", plan.base_image);
+            SyncPackages::download(
+                &ContainerManager::new(plan.container_manager.clone()),
+                &plan.base_image,
+                &plan.container_dir.join(SYNC_DB_DIR),
+                &plan.official_repositories,
+            )
+        }
+    }
+    .map_err(AppError::SyncDb)
+}
@@ -0,0 +1,20 @@
+use super::{AppError, ManifestArgs};
+use clap::Args;
+
+/// Arguments of the `plan` subcommand.
+#[derive(Debug, Args)]
+#[non_exhaustive]
+pub struct PlanArgs {
+    #[command(flatten)]
+    pub manifest: ManifestArgs,
+}
+
+impl PlanArgs {
+    /// Load the manifest and print the resolved sources as HJSON.
+    pub fn run(self) -> Result<(), AppError> {
+        let plan = self.manifest.load()?;
+        let output = serde_hjson::to_string(&plan.sources).map_err(AppError::Serialize)?;
+        println!("{output}");
+        Ok(())
+    }
+}
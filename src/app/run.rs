@@ -1,9 +1,22 @@
-use super::App;
+use super::{App, AppCommand};
 use std::process::ExitCode;
 
 impl App {
     #[must_use]
     pub fn run(self) -> ExitCode {
-        todo!()
+        let result = match self.command {
+            AppCommand::Plan(args) => args.run(),
+            AppCommand::Fetch(args) => args.run(),
+            AppCommand::Srcinfo(args) => args.run(),
+            AppCommand::Graph(args) => args.run(),
+            AppCommand::Build(args) => args.run(),
+        };
+        match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("error: {error}");
+                ExitCode::FAILURE
+            }
+        }
     }
 }
@@ -0,0 +1,185 @@
+use super::{AppError, ManifestArgs};
+use crate::{
+    container::ContainerManager,
+    pkgbuild_desc::{LocalPkgBuildDesc, PkgBuildDesc},
+    srcinfo::{ReadSrcinfoError, Srcinfo, SrcinfoStatus, SRCINFO_FILE_NAME},
+};
+use clap::Args;
+use std::{fs, io, path::Path};
+
+/// Arguments of the `srcinfo` subcommand.
+#[derive(Debug, Args)]
+#[non_exhaustive]
+pub struct SrcinfoArgs {
+    #[command(flatten)]
+    pub manifest: ManifestArgs,
+    /// Fail if any `.SRCINFO` is missing or differs from the generated one, instead of writing it.
+    ///
+    /// Every `.SRCINFO` is regenerated, as modification times are unreliable in fresh clones.
+    #[arg(long)]
+    pub check: bool,
+}
+
+impl SrcinfoArgs {
+    /// Regenerate the missing or stale `.SRCINFO` of the local sources in the build container.
+    pub fn run(self) -> Result<(), AppError> {
+        let plan = self.manifest.load_unresolved()?;
+        let container = ContainerManager::new(plan.container_manager.clone());
+        let mut outdated = Vec::new();
+        for source in &plan.sources {
+            let PkgBuildDesc::Local(LocalPkgBuildDesc { dir, .. }) = source else {
+                continue;
+            };
+            let dir = Path::new(dir);
+            let base = source.package().base();
+            if !self.check
+                && Srcinfo::status(dir).map_err(AppError::Srcinfo)? == SrcinfoStatus::Fresh
+            {
+                continue;
+            }
+
+            eprintln!("Generating .SRCINFO of {base}");
+            let text = Srcinfo::generate(&container, &plan.base_image, dir)
+                .map_err(AppError::Container)?;
+            let path = dir.join(SRCINFO_FILE_NAME);
+            if let Err(error) = Srcinfo::parse(&text) {
+                return Err(AppError::Srcinfo(ReadSrcinfoError::Parse { path, error }));
+            }
+
+            if !self.check {
+                fs::write(&path, text).map_err(|error| AppError::WriteSrcinfo {
+                    path: path.clone(),
+                    error,
+                })?;
+                println!("{base}: {}", path.display());
+                continue;
+            }
+            let current = match fs::read_to_string(&path) {
+                Ok(current) => Some(current),
+                Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+                Err(error) => return Err(AppError::Srcinfo(ReadSrcinfoError::Io { path, error })),
+            };
+            match current {
+                None => eprintln!("{base}: {} is missing", path.display()),
+                Some(current) if current != text => {
+                    eprintln!("{base}: {} is outdated", path.display())
+                }
+                Some(_) => continue,
+            }
+            outdated.push(base.to_string());
+        }
+
+        if outdated.is_empty() {
+            return Ok(());
+        }
+        Err(AppError::OutdatedSrcinfo(outdated))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{
+        app::{App, AppCommand},
+        container::fake::{calls, fake_container_manager},
+    };
+    use clap::Parser;
+    use std::{env, fs::File, process, time::SystemTime};
+
+    const SRCINFO: &str =
+        "pkgbase = foo\n\tpkgver = 1.0\n\tpkgrel = 1\n\tarch = any\n\npkgname = foo\n";
+
+    /// Write a manifest with a local source `foo`, whose container manager logs its arguments
+    /// and prints the content of `generated` as the output of `makepkg --printsrcinfo`.
+    fn setup(root: &Path) -> std::path::PathBuf {
+        let _ = fs::remove_dir_all(root);
+        fs::create_dir_all(root.join("foo")).unwrap();
+        fs::write(root.join("foo/PKGBUILD"), "pkgname=foo\n").unwrap();
+        fs::write(root.join("generated"), SRCINFO).unwrap();
+        let generated = format!("cat '{}'", root.join("generated").display());
+        let container = fake_container_manager(root, &generated);
+        let manifest = root.join("build-pacman-repo.hjson");
+        let content = format!(
+            "container-manager: {script}\ncontainer-file: Containerfile\nbase-image: arch:latest\nrepo-name: repo\nsources: [\n  {{\n    name: foo\n    dir: foo\n  }}\n]\n",
+            script = container.program,
+        );
+        fs::write(&manifest, content).unwrap();
+        manifest
+    }
+
+    fn run(manifest: &Path, check: bool) -> Result<(), AppError> {
+        let manifest = manifest.to_str().unwrap();
+        let args = ["build-pacman-repo", "srcinfo", "--manifest", manifest];
+        let check = check.then_some("--check");
+        match App::parse_from(args.into_iter().chain(check)).command {
+            AppCommand::Srcinfo(args) => args.run(),
+            command => panic!("unexpected command {command:?}"),
+        }
+    }
+
+    #[test]
+    fn generate_missing_and_stale_srcinfo() {
+        let root = env::temp_dir().join(format!("pacman-repo-builder-srcinfo-{}", process::id()));
+        let manifest = setup(&root);
+        let dir = fs::canonicalize(root.join("foo")).unwrap();
+
+        run(&manifest, false).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join(SRCINFO_FILE_NAME)).unwrap(),
+            SRCINFO
+        );
+        assert_eq!(
+            calls(&root),
+            [format!(
+                "run --rm --user nobody --volume {}:/pkgbuild:ro --workdir /pkgbuild arch:latest makepkg --printsrcinfo",
+                dir.display(),
+            )],
+        );
+
+        run(&manifest, false).unwrap();
+        assert_eq!(calls(&root).len(), 1, "a fresh .SRCINFO is kept");
+
+        let updated = SRCINFO.replace("pkgrel = 1", "pkgrel = 2");
+        fs::write(root.join("generated"), &updated).unwrap();
+        File::options()
+            .write(true)
+            .open(dir.join("PKGBUILD"))
+            .unwrap()
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(10))
+            .unwrap();
+        run(&manifest, false).unwrap();
+        assert_eq!(calls(&root).len(), 2, "a stale .SRCINFO is regenerated");
+        assert_eq!(
+            fs::read_to_string(dir.join(SRCINFO_FILE_NAME)).unwrap(),
+            updated
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn check_reports_missing_and_outdated_srcinfo() {
+        let root = env::temp_dir().join(format!(
+            "pacman-repo-builder-srcinfo-check-{}",
+            process::id(),
+        ));
+        let manifest = setup(&root);
+        let path = root.join("foo").join(SRCINFO_FILE_NAME);
+        let outdated = |result: Result<(), AppError>| match result {
+            Err(AppError::OutdatedSrcinfo(bases)) => bases,
+            result => panic!("unexpected result {result:?}"),
+        };
+
+        assert_eq!(outdated(run(&manifest, true)), ["foo"]);
+        assert!(!path.exists(), "--check writes nothing");
+
+        fs::write(&path, SRCINFO.replace("pkgver = 1.0", "pkgver = 0.9")).unwrap();
+        assert_eq!(outdated(run(&manifest, true)), ["foo"]);
+
+        fs::write(&path, SRCINFO).unwrap();
+        run(&manifest, true).unwrap();
+        assert_eq!(calls(&root).len(), 3, "--check always regenerates");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{pkgbuild_desc::GitPkgBuildDesc, pkgbuild_name::PkgBuildName};
+use derive_more::{Display, Error};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Default value of [`AurClient::base_url`].
+pub const DEFAULT_AUR_URL: &str = "https://aur.archlinux.org";
+
+/// Maximum number of packages to query in a single request, to keep the URL short.
+const MAX_NAMES_PER_REQUEST: usize = 100;
+
+/// Maximum duration of each request.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Client of the AUR RPC interface.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AurClient {
+    /// URL of the AUR without the trailing slash, such as `https://aur.archlinux.org`.
+    pub base_url: String,
+    /// HTTP agent to send the requests.
+    agent: ureq::Agent,
+}
+
+/// Metadata of an AUR package, as returned by the RPC `info` endpoint.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+#[serde(rename_all = "PascalCase")]
+pub struct AurPackage {
+    /// Name of the package.
+    pub name: String,
+    /// Base of the PKGBUILD that builds the package.
+    pub package_base: String,
+    /// Version of the package.
+    pub version: String,
+    /// Run-time dependencies of the package.
+    #[serde(default)]
+    pub depends: Vec<String>,
+    /// Build-time dependencies of the package.
+    #[serde(default)]
+    pub make_depends: Vec<String>,
+    /// Dependencies to run the tests of the package.
+    #[serde(default)]
+    pub check_depends: Vec<String>,
+    /// Virtual packages provided by the package.
+    #[serde(default)]
+    pub provides: Vec<String>,
+}
+
+/// Response of the AUR RPC interface.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+struct RpcResponse {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    results: Vec<AurPackage>,
+}
+
+/// Error when querying the AUR fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum AurError {
+    #[display("Failed to request {url}: {error}")]
+    Request {
+        url: String,
+        #[error(source)]
+        error: ureq::Error,
+    },
+    #[display("AUR RPC returned an error: {_0}")]
+    Rpc(#[error(not(source))] String),
+    #[display("Packages not found in the AUR: {}", _0.join(", "))]
+    NotFound(#[error(not(source))] Vec<String>),
+}
+
+impl Default for AurClient {
+    fn default() -> Self {
+        AurClient::new(DEFAULT_AUR_URL.to_string())
+    }
+}
+
+impl AurClient {
+    /// Create a client of the AUR at `base_url`.
+    pub fn new(base_url: String) -> Self {
+        let agent = ureq::Agent::config_builder()
+            .timeout_global(Some(TIMEOUT))
+            .build()
+            .into();
+        AurClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent,
+        }
+    }
+
+    /// Get the URL of the git repository of a pkgbase.
+    pub fn git_url(&self, pkgbase: &str) -> String {
+        format!("{}/{pkgbase}.git", self.base_url)
+    }
+
+    /// Create git descriptions of AUR packages in order, merging the packages that share a pkgbase.
+    ///
+    /// Each package is given as a pair of its name and its pkgbase.
+    pub fn git_descs<'a, Packages>(
+        &self,
+        packages: Packages,
+        git_depth: Option<u64>,
+    ) -> Vec<GitPkgBuildDesc>
+    where
+        Packages: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut bases: Vec<(&str, Vec<String>)> = Vec::new();
+        for (name, base) in packages {
+            match bases.iter_mut().find(|(existing, _)| *existing == base) {
+                Some((_, names)) => names.push(name.to_string()),
+                None => bases.push((base, vec![name.to_string()])),
+            }
+        }
+        bases
+            .into_iter()
+            .map(|(base, names)| {
+                let package = match <[String; 1]>::try_from(names) {
+                    Ok([name]) if name == base => PkgBuildName::single(name),
+                    Ok([name]) => PkgBuildName::split(base.to_string(), vec![name]),
+                    Err(names) => PkgBuildName::split(base.to_string(), names),
+                };
+                GitPkgBuildDesc {
+                    package,
+                    git_url: self.git_url(base),
+                    git_depth,
+                    git_ref: None,
+                    sub_dir: None,
+                }
+            })
+            .collect()
+    }
+
+    /// Query the metadata of the packages that exist among `names`.
+    ///
+    /// The results are in no particular order.
+    pub fn lookup(&self, names: &[String]) -> Result<Vec<AurPackage>, AurError> {
+        let mut packages = Vec::with_capacity(names.len());
+        for chunk in names.chunks(MAX_NAMES_PER_REQUEST) {
+            packages.extend(self.request_info(chunk)?);
+        }
+        Ok(packages)
+    }
+
+    /// Query the metadata of packages by their names.
+    ///
+    /// Fail if any of the packages does not exist. The results are in the same order as `names`.
+    pub fn info(&self, names: &[String]) -> Result<Vec<AurPackage>, AurError> {
+        let mut packages = self.lookup(names)?;
+        let missing: Vec<_> = names
+            .iter()
+            .filter(|name| !packages.iter().any(|package| &package.name == *name))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(AurError::NotFound(missing));
+        }
+        packages.sort_by_key(|package| names.iter().position(|name| *name == package.name));
+        Ok(packages)
+    }
+
+    /// Send a single request to the `info` endpoint.
+    fn request_info(&self, names: &[String]) -> Result<Vec<AurPackage>, AurError> {
+        let url = format!("{}/rpc/v5/info", self.base_url);
+        let request_error = |error| AurError::Request {
+            url: url.clone(),
+            error,
+        };
+        let response: RpcResponse = self
+            .agent
+            .get(&url)
+            .query_pairs(names.iter().map(|name| ("arg[]", name.as_str())))
+            .call()
+            .map_err(request_error)?
+            .body_mut()
+            .read_json()
+            .map_err(request_error)?;
+        if response.kind == "error" {
+            let message = response
+                .error
+                .unwrap_or_else(|| "unknown error".to_string());
+            return Err(AurError::Rpc(message));
+        }
+        Ok(response.results)
+    }
+}
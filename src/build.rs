@@ -0,0 +1,503 @@
+mod decision;
+mod layers;
+
+pub use decision::BuildDecision;
+pub use layers::{ImageLayer, ImagePlan};
+
+use crate::{
+    build_plan::BuildPlan,
+    container::{ContainerError, ContainerManager},
+    fetch::is_single_normal_component,
+    file_base_name::FileBaseName,
+    repo_name::RepoName,
+};
+use derive_more::{Display, Error};
+use sha2::{Digest, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Unprivileged user that runs `makepkg` in the build containers.
+const BUILDER_USER: &str = "builder";
+
+/// Directory of the PKGBUILD in the build containers.
+const CONTAINER_PKGBUILD_DIR: &str = "/home/builder/pkgbuild";
+
+/// Directory that `makepkg` writes the built packages to in the build containers.
+const CONTAINER_PACKAGE_DIR: &str = "/home/builder/packages";
+
+/// Directory of the local repository of the dependencies built earlier in the build containers.
+const CONTAINER_REPO_DIR: &str = "/var/lib/build-pacman-repo/repo";
+
+/// Sub directory of [`Builder::container_dir`] with the build contexts of the layers.
+///
+/// A pkgbase cannot start with a dot, so this never clashes with the build context of a pkgbase.
+const LAYER_CONTEXT_DIR: &str = ".layers";
+
+/// Builder of the packages of a [build plan](BuildPlan) in containers.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Builder {
+    /// The container manager that builds the images and runs the containers.
+    pub container: ContainerManager,
+    /// The name of the container file in each build context.
+    pub container_file: FileBaseName,
+    /// Container image with `base-devel` from which the packages are built.
+    pub base_image: String,
+    /// Prefix of the names of the images that the builder builds, such as
+    /// `build-pacman-repo/0123456789ab`, so that the images of different manifests never clash.
+    pub image_namespace: String,
+    /// Directory to create the build contexts in, one sub directory per `pkgbase` and per layer.
+    pub container_dir: PathBuf,
+    /// Directory to copy the built packages into.
+    pub package_dir: PathBuf,
+    /// Name of the local repository that provides the dependencies built earlier.
+    pub repo_name: RepoName,
+}
+
+/// Error when [`Builder::build`] or [`Builder::build_layers`] fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum BuildError {
+    #[display("{base}: The pkgbase cannot be used as a directory name")]
+    InvalidBase {
+        #[error(not(source))]
+        base: String,
+    },
+    #[display("{target}: Failed to prepare {}: {error}", path.display())]
+    Io {
+        target: String,
+        path: PathBuf,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("{target}: {error}")]
+    Container {
+        target: String,
+        #[error(source)]
+        error: ContainerError,
+    },
+    #[display("{base}: makepkg produced no packages")]
+    NoPackages {
+        #[error(not(source))]
+        base: String,
+    },
+}
+
+impl Builder {
+    /// Create a builder with the container settings and the directories of a plan.
+    pub fn new(plan: &BuildPlan) -> Self {
+        Builder {
+            container: ContainerManager::new(plan.container_manager.clone()),
+            container_file: plan.container_file.clone(),
+            base_image: plan.base_image.clone(),
+            image_namespace: image_namespace(&plan.manifest_path),
+            container_dir: plan.container_dir.clone(),
+            package_dir: plan.package_dir.clone(),
+            repo_name: plan.repo_name.clone(),
+        }
+    }
+
+    /// Replace [`Builder::container`].
+    pub fn with_container(mut self, container: ContainerManager) -> Self {
+        self.container = container;
+        self
+    }
+
+    /// Build the images of the layers of `plan`, each one after its parent.
+    pub fn build_layers(&self, plan: &ImagePlan) -> Result<(), BuildError> {
+        for layer in &plan.layers {
+            let io_error = |path: &Path| {
+                let path = path.to_path_buf();
+                move |error| BuildError::Io {
+                    target: layer.name.clone(),
+                    path,
+                    error,
+                }
+            };
+            let context = self.container_dir.join(LAYER_CONTEXT_DIR).join(&layer.name);
+            fs::create_dir_all(&context).map_err(io_error(&context))?;
+            let from = match layer.parent {
+                Some(parent) => self.image(&plan.layers[parent].name),
+                None => self.base_image.clone(),
+            };
+            let content = self.layer_container_file_content(&from, layer);
+            self.build_image(&context, &content, &self.image(&layer.name))
+                .map_err(|error| match error {
+                    ImageError::Io(path, error) => io_error(&path)(error),
+                    ImageError::Container(error) => BuildError::Container {
+                        target: layer.name.clone(),
+                        error,
+                    },
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Build the PKGBUILD in `pkgbuild_dir` from the image of `layer` and copy the packages into
+    /// the package directory.
+    ///
+    /// The image of `layer` must have been built by [`Builder::build_layers`]. The packages in
+    /// `local_packages`, usually those of the dependencies built earlier, are served to pacman
+    /// in the build container as a local repository named after [`Builder::repo_name`].
+    ///
+    /// Return the paths to the copied packages.
+    pub fn build(
+        &self,
+        base: &str,
+        pkgbuild_dir: &Path,
+        layer: &ImageLayer,
+        local_packages: &[PathBuf],
+    ) -> Result<Vec<PathBuf>, BuildError> {
+        if !is_single_normal_component(base) {
+            return Err(BuildError::InvalidBase {
+                base: base.to_string(),
+            });
+        }
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+            move |error| BuildError::Io {
+                target: base.to_string(),
+                path,
+                error,
+            }
+        };
+        let container_error = |error| BuildError::Container {
+            target: base.to_string(),
+            error,
+        };
+
+        let context = self.container_dir.join(base);
+        if context.exists() {
+            fs::remove_dir_all(&context).map_err(io_error(&context))?;
+        }
+        let context_pkgbuild = context.join("pkgbuild");
+        copy_dir(pkgbuild_dir, &context_pkgbuild).map_err(io_error(&context_pkgbuild))?;
+        if !local_packages.is_empty() {
+            let context_repo = context.join("repo");
+            fs::create_dir_all(&context_repo).map_err(io_error(&context_repo))?;
+            for package in local_packages {
+                let Some(name) = package.file_name() else {
+                    continue;
+                };
+                let target = context_repo.join(name);
+                fs::copy(package, &target).map_err(io_error(&target))?;
+            }
+        }
+        let image = self.image(&pkgbase_image(base));
+        let content =
+            self.container_file_content(&self.image(&layer.name), !local_packages.is_empty());
+        self.build_image(&context, &content, &image)
+            .map_err(|error| match error {
+                ImageError::Io(path, error) => io_error(&path)(error),
+                ImageError::Container(error) => container_error(error),
+            })?;
+
+        let container = format!("{image}-{}", process::id()).replace('/', "-");
+        let output = context.join("packages");
+        fs::create_dir_all(&output).map_err(io_error(&output))?;
+        let built = self
+            .container
+            .exec([
+                "run",
+                "--name",
+                &container,
+                &image,
+                "makepkg",
+                "--syncdeps",
+                "--noconfirm",
+            ])
+            .and_then(|()| {
+                let source = format!("{container}:{CONTAINER_PACKAGE_DIR}/.");
+                self.container
+                    .exec(["cp".as_ref(), source.as_ref(), output.as_os_str()])
+            });
+        let removed = self.container.run(["rm", "--force", &container]);
+        built.and(removed).map_err(container_error)?;
+
+        fs::create_dir_all(&self.package_dir).map_err(io_error(&self.package_dir))?;
+        let mut packages = Vec::new();
+        for entry in fs::read_dir(&output).map_err(io_error(&output))? {
+            let entry = entry.map_err(io_error(&output))?;
+            let name = entry.file_name();
+            if !name.to_string_lossy().contains(".pkg.tar") {
+                continue;
+            }
+            let target = self.package_dir.join(&name);
+            fs::copy(entry.path(), &target).map_err(io_error(&target))?;
+            packages.push(target);
+        }
+        if packages.is_empty() {
+            return Err(BuildError::NoPackages {
+                base: base.to_string(),
+            });
+        }
+        packages.sort();
+        Ok(packages)
+    }
+
+    /// Get the full name of the image `name` in [`Builder::image_namespace`].
+    fn image(&self, name: &str) -> String {
+        format!("{}/{name}", self.image_namespace)
+    }
+
+    /// Write `content` to the container file in `context` and build the image `tag` from it.
+    fn build_image(&self, context: &Path, content: &str, tag: &str) -> Result<(), ImageError> {
+        let container_file = context.join(&*self.container_file);
+        fs::write(&container_file, content)
+            .map_err(|error| ImageError::Io(container_file.clone(), error))?;
+        self.container
+            .exec([
+                "build".as_ref(),
+                "--file".as_ref(),
+                container_file.as_os_str(),
+                "--tag".as_ref(),
+                tag.as_ref(),
+                context.as_os_str(),
+            ])
+            .map_err(ImageError::Container)
+    }
+
+    /// Generate the container file of a layer that inherits from the image `from`.
+    ///
+    /// The root layer also upgrades the system and creates the user that runs `makepkg`.
+    fn layer_container_file_content(&self, from: &str, layer: &ImageLayer) -> String {
+        let mut lines = vec![format!("FROM {from}")];
+        if layer.parent.is_none() {
+            let sudoers = format!("{BUILDER_USER} ALL=(ALL) NOPASSWD: /usr/bin/pacman");
+            lines.extend([
+                "RUN pacman -Syu --noconfirm".to_string(),
+                format!("RUN useradd --create-home --user-group {BUILDER_USER}"),
+                format!("RUN echo '{sudoers}' > /etc/sudoers.d/{BUILDER_USER}"),
+            ]);
+        }
+        if !layer.dependencies.is_empty() {
+            lines.push(format!(
+                "RUN pacman -S --noconfirm --needed --asdeps {}",
+                layer.dependencies.join(" "),
+            ));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// Generate the container file of the build context of a pkgbase built from the layer `from`.
+    ///
+    /// If `local_repo` is true, the packages in the `repo` directory of the build context are
+    /// registered in `pacman.conf` as a local repository.
+    fn container_file_content(&self, from: &str, local_repo: bool) -> String {
+        let mut lines = vec![format!("FROM {from}")];
+        if local_repo {
+            let repo = &self.repo_name;
+            let database = format!("{CONTAINER_REPO_DIR}/{repo}.db.tar.gz");
+            let section = format!(
+                "\\n[{repo}]\\nSigLevel = Optional TrustAll\\nServer = file://{CONTAINER_REPO_DIR}\\n",
+            );
+            lines.extend([
+                format!("COPY repo {CONTAINER_REPO_DIR}"),
+                format!("RUN repo-add --quiet {database} {CONTAINER_REPO_DIR}/*.pkg.tar*"),
+                format!("RUN printf '{section}' >> /etc/pacman.conf"),
+                "RUN pacman -Sy --noconfirm".to_string(),
+            ]);
+        }
+        lines.extend([
+            format!("COPY --chown={BUILDER_USER}:{BUILDER_USER} pkgbuild {CONTAINER_PKGBUILD_DIR}"),
+            format!("USER {BUILDER_USER}"),
+            format!("RUN mkdir {CONTAINER_PACKAGE_DIR}"),
+            format!("ENV PKGDEST={CONTAINER_PACKAGE_DIR}"),
+            format!("WORKDIR {CONTAINER_PKGBUILD_DIR}"),
+        ]);
+        lines.join("\n") + "\n"
+    }
+}
+
+/// Error when [`Builder::build_image`] fails, before the target is attached.
+enum ImageError {
+    Io(PathBuf, io::Error),
+    Container(ContainerError),
+}
+
+/// Get the namespace of the images of the manifest at `manifest_path` from the hash of its
+/// canonical path.
+fn image_namespace(manifest_path: &Path) -> String {
+    let path = fs::canonicalize(manifest_path).unwrap_or_else(|_| manifest_path.to_path_buf());
+    format!(
+        "build-pacman-repo/{}",
+        short_hash(path.as_os_str().as_encoded_bytes()),
+    )
+}
+
+/// Get the name of the image that builds a pkgbase, relative to the image namespace.
+///
+/// Characters that are valid in a pkgbase but not in an image name are replaced with `-`, in
+/// which case the hash of the pkgbase is appended, so that `Foo` and `foo` do not clash.
+fn pkgbase_image(base: &str) -> String {
+    let image: String = base
+        .chars()
+        .map(|char| match char {
+            'a'..='z' | '0'..='9' | '.' | '_' | '-' => char,
+            'A'..='Z' => char.to_ascii_lowercase(),
+            _ => '-',
+        })
+        .collect();
+    match image == base {
+        true => image,
+        false => format!("{image}-{}", short_hash(base.as_bytes())),
+    }
+}
+
+/// Get the first 12 hexadecimal digits of the SHA-256 of `bytes`.
+fn short_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)[..6]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Copy a directory recursively, except for the `.git` directory.
+fn copy_dir(source: &Path, target: &Path) -> io::Result<()> {
+    fs::create_dir_all(target)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        let target = target.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            copy_symlink(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Recreate a symbolic link.
+#[cfg(unix)]
+fn copy_symlink(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, target)
+}
+
+/// Copy the file or directory that a symbolic link points to, since creating symbolic links may
+/// require privileges on this platform.
+#[cfg(not(unix))]
+fn copy_symlink(source: &Path, target: &Path) -> io::Result<()> {
+    if fs::metadata(source)?.is_dir() {
+        copy_dir(source, target)
+    } else {
+        fs::copy(source, target).map(drop)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::container::fake::{calls, fake_container_manager};
+    use std::env;
+
+    #[test]
+    fn image_names() {
+        assert_eq!(pkgbase_image("foo-bar_1.0"), "foo-bar_1.0");
+        assert_ne!(pkgbase_image("Foo"), pkgbase_image("foo"));
+        assert!(pkgbase_image("Foo").starts_with("foo-"));
+        assert_ne!(pkgbase_image("foo+"), pkgbase_image("foo@"));
+
+        let namespace = image_namespace(Path::new("/nonexistent/a/build-pacman-repo.hjson"));
+        assert!(namespace.starts_with("build-pacman-repo/"));
+        assert_eq!(namespace.len(), "build-pacman-repo/".len() + 12);
+        assert_ne!(
+            namespace,
+            image_namespace(Path::new("/nonexistent/b/build-pacman-repo.hjson")),
+        );
+    }
+
+    #[test]
+    fn build_in_container() {
+        let root = env::temp_dir().join(format!("pacman-repo-builder-build-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let pkgbuild_dir = root.join("pkgbuild");
+        fs::create_dir_all(pkgbuild_dir.join(".git")).unwrap();
+        fs::write(pkgbuild_dir.join("PKGBUILD"), "pkgname=foo\n").unwrap();
+        std::os::unix::fs::symlink("PKGBUILD", pkgbuild_dir.join("link")).unwrap();
+        let dependency = root.join("bar-1.0-1-any.pkg.tar.zst");
+        fs::write(&dependency, "").unwrap();
+
+        // Keep the container file of each image, and pretend that `makepkg` built a package.
+        let script = format!(
+            "case \"$1\" in\n    build) cp \"$3\" '{root}/built-container-file' ;;\n    \
+             cp) touch \"$3/foo-1.0-1-any.pkg.tar.zst\" \"$3/makepkg.log\" ;;\nesac",
+            root = root.display(),
+        );
+        let builder = Builder {
+            container: fake_container_manager(&root, &script),
+            container_file: FileBaseName::try_from_string("Containerfile".to_string()).unwrap(),
+            base_image: "arch:latest".to_string(),
+            image_namespace: "build-pacman-repo/ns".to_string(),
+            container_dir: root.join("container"),
+            package_dir: root.join("package"),
+            repo_name: RepoName::try_from_string("repo".to_string()).unwrap(),
+        };
+        let layer = ImageLayer {
+            name: "layer-0".to_string(),
+            parent: None,
+            dependencies: Vec::new(),
+        };
+        let packages = builder
+            .build("foo", &pkgbuild_dir, &layer, &[dependency])
+            .unwrap();
+        assert_eq!(packages, [root.join("package/foo-1.0-1-any.pkg.tar.zst")]);
+        assert!(packages[0].is_file());
+        assert!(!root.join("package/makepkg.log").exists());
+
+        let context = root.join("container/foo");
+        assert!(context.join("pkgbuild/PKGBUILD").is_file());
+        assert!(!context.join("pkgbuild/.git").exists());
+        assert_eq!(
+            fs::read_link(context.join("pkgbuild/link")).unwrap(),
+            Path::new("PKGBUILD"),
+        );
+        assert!(context.join("repo/bar-1.0-1-any.pkg.tar.zst").is_file());
+
+        let container_file = fs::read_to_string(root.join("built-container-file")).unwrap();
+        assert_eq!(
+            container_file.lines().collect::<Vec<_>>(),
+            [
+                "FROM build-pacman-repo/ns/layer-0",
+                "COPY repo /var/lib/build-pacman-repo/repo",
+                "RUN repo-add --quiet /var/lib/build-pacman-repo/repo/repo.db.tar.gz /var/lib/build-pacman-repo/repo/*.pkg.tar*",
+                r"RUN printf '\n[repo]\nSigLevel = Optional TrustAll\nServer = file:///var/lib/build-pacman-repo/repo\n' >> /etc/pacman.conf",
+                "RUN pacman -Sy --noconfirm",
+                "COPY --chown=builder:builder pkgbuild /home/builder/pkgbuild",
+                "USER builder",
+                "RUN mkdir /home/builder/packages",
+                "ENV PKGDEST=/home/builder/packages",
+                "WORKDIR /home/builder/pkgbuild",
+            ],
+        );
+
+        let container = format!("build-pacman-repo-ns-foo-{}", process::id());
+        assert_eq!(
+            calls(&root),
+            [
+                format!(
+                    "build --file {} --tag build-pacman-repo/ns/foo {}",
+                    context.join("Containerfile").display(),
+                    context.display(),
+                ),
+                format!(
+                    "run --name {container} build-pacman-repo/ns/foo makepkg --syncdeps --noconfirm"
+                ),
+                format!(
+                    "cp {container}:/home/builder/packages/. {}",
+                    context.join("packages").display(),
+                ),
+                format!("rm --force {container}"),
+            ],
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{repo_db::RepoDb, srcinfo::Srcinfo, vercmp::vercmp};
+use derive_more::Display;
+use std::{cmp::Ordering, path::Path};
+
+/// Whether a pkgbase is built or skipped, and why.
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildDecision {
+    #[display("forced")]
+    Forced,
+    #[display("{name} is not in the repository")]
+    Missing { name: String },
+    #[display("{file_name} of {name} is missing")]
+    MissingArchive { name: String, file_name: String },
+    #[display("{name} {version} is newer than {repo_version} in the repository")]
+    Outdated {
+        name: String,
+        version: String,
+        repo_version: String,
+    },
+    #[display("{version} is up to date")]
+    UpToDate { version: String },
+}
+
+impl BuildDecision {
+    /// Decide whether to build `srcinfo` by comparing the versions of its packages against
+    /// those in `repo_db`, with the help of [`vercmp`].
+    ///
+    /// The pkgbase is built if any of its packages is missing from the repository, has no
+    /// archive in `package_dir`, or is older than the `.SRCINFO`.
+    pub fn new(srcinfo: &Srcinfo, repo_db: &RepoDb, package_dir: &Path, forced: bool) -> Self {
+        if forced {
+            return BuildDecision::Forced;
+        }
+        let version = srcinfo.version.to_string();
+        for name in srcinfo.names() {
+            let Some(entry) = repo_db.get(name) else {
+                return BuildDecision::Missing {
+                    name: name.to_string(),
+                };
+            };
+            if !package_dir.join(&entry.file_name).exists() {
+                return BuildDecision::MissingArchive {
+                    name: name.to_string(),
+                    file_name: entry.file_name.clone(),
+                };
+            }
+            if vercmp(&version, &entry.version) == Ordering::Greater {
+                return BuildDecision::Outdated {
+                    name: name.to_string(),
+                    version,
+                    repo_version: entry.version.clone(),
+                };
+            }
+        }
+        BuildDecision::UpToDate { version }
+    }
+
+    /// Whether the pkgbase needs to be built.
+    pub fn needs_build(&self) -> bool {
+        !matches!(self, BuildDecision::UpToDate { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo_db::DbEntry;
+    use std::{env, fs, process};
+
+    /// Create a repository of `foo-bin` and `foo-docs` at `version`, whose archives are in `dir`.
+    fn repo_db(dir: &Path, version: &str) -> RepoDb {
+        let mut repo_db = RepoDb::default();
+        for name in ["foo-bin", "foo-docs"] {
+            let file_name = format!("{name}-{version}-any.pkg.tar.zst");
+            fs::write(dir.join(&file_name), "").unwrap();
+            let entry = DbEntry {
+                name: name.to_string(),
+                version: version.to_string(),
+                file_name,
+                desc: String::new(),
+                files: None,
+            };
+            repo_db.entries.insert(format!("{name}-{version}"), entry);
+        }
+        repo_db
+    }
+
+    #[test]
+    fn decide_from_the_repository() {
+        let dir = env::temp_dir().join(format!("pacman-repo-builder-decision-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let srcinfo = Srcinfo::parse(
+            "pkgbase = foo\n\tepoch = 1\n\tpkgver = 1.10\n\tpkgrel = 1\n\tarch = any\n\n\
+             pkgname = foo-bin\n\npkgname = foo-docs\n",
+        )
+        .unwrap();
+        let decide = |repo_db: &RepoDb, forced| BuildDecision::new(&srcinfo, repo_db, &dir, forced);
+
+        let up_to_date = repo_db(&dir, "1:1.10-1");
+        let decision = decide(&up_to_date, false);
+        assert_eq!(
+            decision,
+            BuildDecision::UpToDate {
+                version: "1:1.10-1".to_string(),
+            },
+        );
+        assert!(!decision.needs_build());
+        assert_eq!(decision.to_string(), "1:1.10-1 is up to date");
+        assert_eq!(decide(&repo_db(&dir, "1:1.10-2"), false), decision);
+        assert_eq!(decide(&up_to_date, true), BuildDecision::Forced);
+
+        let decision = decide(&repo_db(&dir, "1.11-1"), false);
+        assert_eq!(
+            decision.to_string(),
+            "foo-bin 1:1.10-1 is newer than 1.11-1 in the repository",
+        );
+        assert!(decision.needs_build());
+        assert!(matches!(
+            decide(&repo_db(&dir, "1:1.9-1"), false),
+            BuildDecision::Outdated { .. },
+        ));
+
+        let mut missing = up_to_date.clone();
+        missing.entries.remove("foo-docs-1:1.10-1");
+        assert_eq!(
+            decide(&missing, false),
+            BuildDecision::Missing {
+                name: "foo-docs".to_string(),
+            },
+        );
+
+        fs::remove_file(dir.join("foo-docs-1:1.10-1-any.pkg.tar.zst")).unwrap();
+        let decision = decide(&up_to_date, false);
+        assert_eq!(
+            decision.to_string(),
+            "foo-docs-1:1.10-1-any.pkg.tar.zst of foo-docs is missing",
+        );
+        assert!(decision.needs_build());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{graph::DependencyGraph, srcinfo::Srcinfo};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Name of the root layer. Each child layer appends its position to its parent.
+const ROOT_LAYER_NAME: &str = "layer-0";
+
+/// Tree of container images that install the repository dependencies shared by the PKGBUILDs.
+///
+/// The build image of each PKGBUILD inherits from a layer, so that the dependencies that it
+/// shares with its siblings are only installed once, and cached.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ImagePlan {
+    /// The layers, each one after its parent. The first one is the root.
+    pub layers: Vec<ImageLayer>,
+    /// Index of the layer that each node of the [`DependencyGraph`] is built from.
+    pub node_layers: Vec<usize>,
+}
+
+/// Container image in an [`ImagePlan`] that does not build anything.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ImageLayer {
+    /// Name of the layer, such as `layer-0-1`, and of its image in the
+    /// [namespace](crate::build::Builder::image_namespace) of the builder.
+    pub name: String,
+    /// Index of the layer that this one inherits from, `None` for the root.
+    pub parent: Option<usize>,
+    /// Names of the packages from the repositories that this layer installs on top of its parent.
+    pub dependencies: Vec<String>,
+}
+
+impl ImagePlan {
+    /// Group the PKGBUILDs by the repository dependencies that they share.
+    ///
+    /// Repository dependencies are those that no node of `graph` builds. The root layer installs
+    /// the dependencies shared by all the PKGBUILDs, then each child layer installs those shared
+    /// by a group of PKGBUILDs that share the most common remaining dependency.
+    pub fn new(srcinfos: &[Srcinfo], graph: &DependencyGraph) -> Self {
+        let mut remaining: Vec<BTreeSet<String>> = srcinfos
+            .iter()
+            .zip(&graph.nodes)
+            .map(|(srcinfo, node)| {
+                srcinfo
+                    .dependencies()
+                    .into_iter()
+                    .filter(|(_, dependency)| {
+                        !node
+                            .edges
+                            .iter()
+                            .any(|edge| edge.dependency == **dependency)
+                    })
+                    .map(|(_, dependency)| dependency.name.as_str())
+                    .filter(|name| !srcinfo.has_name(name))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .collect();
+
+        let members: Vec<usize> = (0..srcinfos.len()).collect();
+        let root_dependencies = match members.len() {
+            0 | 1 => BTreeSet::new(),
+            _ => intersection(&remaining, &members),
+        };
+        for dependencies in &mut remaining {
+            dependencies.retain(|name| !root_dependencies.contains(name));
+        }
+        let mut plan = ImagePlan {
+            layers: vec![ImageLayer {
+                name: ROOT_LAYER_NAME.to_string(),
+                parent: None,
+                dependencies: root_dependencies.into_iter().collect(),
+            }],
+            node_layers: vec![0; srcinfos.len()],
+        };
+        plan.split(0, members, &mut remaining);
+        plan
+    }
+
+    /// Create child layers of `layer` for the groups of `members` that share dependencies.
+    fn split(&mut self, layer: usize, mut members: Vec<usize>, remaining: &mut [BTreeSet<String>]) {
+        let mut children = 0;
+        loop {
+            let mut counts = BTreeMap::<&str, usize>::new();
+            for &member in &members {
+                for name in &remaining[member] {
+                    *counts.entry(name).or_default() += 1;
+                }
+            }
+            let most_common = counts
+                .into_iter()
+                .filter(|(_, count)| *count >= 2)
+                .max_by(|(a_name, a_count), (b_name, b_count)| {
+                    a_count.cmp(b_count).then_with(|| b_name.cmp(a_name))
+                })
+                .map(|(name, _)| name.to_string());
+            let Some(most_common) = most_common else {
+                break;
+            };
+
+            let (group, rest) = members
+                .into_iter()
+                .partition(|&member| remaining[member].contains(&most_common));
+            members = rest;
+            let shared = intersection(remaining, &group);
+            for &member in &group {
+                remaining[member].retain(|name| !shared.contains(name));
+            }
+            children += 1;
+            self.layers.push(ImageLayer {
+                name: format!("{}-{children}", self.layers[layer].name),
+                parent: Some(layer),
+                dependencies: shared.into_iter().collect(),
+            });
+            self.split(self.layers.len() - 1, group, remaining);
+        }
+        for member in members {
+            self.node_layers[member] = layer;
+        }
+    }
+}
+
+/// Get the dependencies that all of `members` have.
+fn intersection(dependencies: &[BTreeSet<String>], members: &[usize]) -> BTreeSet<String> {
+    let Some((&first, rest)) = members.split_first() else {
+        return BTreeSet::new();
+    };
+    let mut shared = dependencies[first].clone();
+    for &member in rest {
+        shared.retain(|name| dependencies[member].contains(name));
+    }
+    shared
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a `.SRCINFO` of a single package, whose base section ends with `fields`.
+    fn srcinfo(base: &str, fields: &str) -> Srcinfo {
+        Srcinfo::parse(&format!(
+            "pkgbase = {base}\n\tpkgver = 1.0\n\tpkgrel = 1\n\tarch = any\n{fields}\npkgname = {base}\n",
+        ))
+        .unwrap()
+    }
+
+    fn image_plan(srcinfos: &[Srcinfo]) -> ImagePlan {
+        ImagePlan::new(srcinfos, &DependencyGraph::new(srcinfos))
+    }
+
+    /// Describe the layers as `(name, parent, dependencies)`.
+    fn layers(plan: &ImagePlan) -> Vec<(&str, Option<usize>, Vec<&str>)> {
+        plan.layers
+            .iter()
+            .map(|layer| {
+                let dependencies = layer.dependencies.iter().map(String::as_str).collect();
+                (layer.name.as_str(), layer.parent, dependencies)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nested_layers() {
+        let plan = image_plan(&[
+            srcinfo(
+                "a",
+                "\tdepends = glibc\n\tdepends = python>=3\n\tdepends = python-foo\n\tmakedepends = cmake\n",
+            ),
+            srcinfo("b", "\tdepends = glibc\n\tdepends = python\n\tdepends = python-foo\n"),
+            srcinfo("c", "\tdepends = glibc\n\tdepends = python\n\tmakedepends = cmake\n"),
+            srcinfo("d", "\tdepends = glibc\n\tdepends = rust\n\tmakedepends = cmake\n"),
+            srcinfo("e", "\tdepends = glibc\n\tdepends = a\n\tcheckdepends = e\n"),
+        ]);
+        assert_eq!(
+            layers(&plan),
+            [
+                ("layer-0", None, vec!["glibc"]),
+                ("layer-0-1", Some(0), vec!["cmake"]),
+                ("layer-0-1-1", Some(1), vec!["python"]),
+            ],
+            "the most common dependency is grouped first, the smallest name breaks ties",
+        );
+        assert_eq!(plan.node_layers, [2, 0, 2, 1, 0]);
+    }
+
+    #[test]
+    fn sibling_layers() {
+        let plan = image_plan(&[
+            srcinfo("a", "\tdepends = x\n\tdepends = y\n"),
+            srcinfo("b", "\tmakedepends = x\n\tcheckdepends = y\n"),
+            srcinfo("c", "\tdepends = z\n\tmakedepends = w\n"),
+            srcinfo("d", "\tdepends = z\n"),
+        ]);
+        assert_eq!(
+            layers(&plan),
+            [
+                ("layer-0", None, vec![]),
+                ("layer-0-1", Some(0), vec!["x", "y"]),
+                ("layer-0-2", Some(0), vec!["z"]),
+            ],
+        );
+        assert_eq!(plan.node_layers, [1, 1, 2, 2]);
+    }
+
+    #[test]
+    fn dependencies_between_sources_are_not_installed() {
+        let plan = image_plan(&[
+            srcinfo("a", "\tdepends = b\n\tdepends = shared\n"),
+            srcinfo("b", "\tprovides = virtual\n\tdepends = shared\n"),
+            srcinfo("c", "\tdepends = virtual\n\tdepends = shared\n"),
+        ]);
+        assert_eq!(layers(&plan), [("layer-0", None, vec!["shared"])],);
+        assert_eq!(plan.node_layers, [0, 0, 0]);
+    }
+
+    #[test]
+    fn single_pkgbuild_has_an_empty_root() {
+        let plan = image_plan(&[srcinfo("a", "\tdepends = glibc\n")]);
+        assert_eq!(layers(&plan), [("layer-0", None, vec![])]);
+        assert_eq!(plan.node_layers, [0]);
+
+        let plan = image_plan(&[]);
+        assert_eq!(layers(&plan), [("layer-0", None, vec![])]);
+        assert!(plan.node_layers.is_empty());
+    }
+}
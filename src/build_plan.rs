@@ -0,0 +1,109 @@
+use crate::{
+    aur::AurClient, file_base_name::FileBaseName, pkgbuild_desc::PkgBuildDesc,
+    pkgbuild_group::AurPkgBuildGroup, repo_name::RepoName, resolve::MissingDependencyAction,
+};
+use derive_more::{Display, Error};
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// Normalized form of a [manifest](crate::manifest::Manifest) with all paths resolved.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct BuildPlan {
+    /// Path to the manifest file.
+    pub manifest_path: PathBuf,
+    /// Directory that contains the manifest file.
+    pub manifest_dir: PathBuf,
+    /// The name of or path to the CLI program to build images and run containers.
+    pub container_manager: String,
+    /// The name of the manifest file to be read by the [container manager](BuildPlan::container_manager).
+    pub container_file: FileBaseName,
+    /// Container image with `base-devel` from which the packages are built.
+    pub base_image: String,
+    /// Directory to store all the repositories that contain PKGBUILD and .SRCINFO.
+    pub pkgbuild_dir: PathBuf,
+    /// Directory to store all the directories to build container images in.
+    pub container_dir: PathBuf,
+    /// Directory to store all the built pacman package archives.
+    pub package_dir: PathBuf,
+    /// Name of the repository of the built pacman packages.
+    pub repo_name: RepoName,
+    /// Variables to be used by the templates.
+    pub variables: BTreeMap<String, String>,
+    /// URL of the AUR to query and clone the packages from.
+    pub aur_url: String,
+    /// What to do with the dependencies that are neither in the official repositories nor in the sources.
+    pub missing_dependencies: MissingDependencyAction,
+    /// Directory of the pacman sync databases of the official repositories.
+    ///
+    /// `None` means that the sync databases are downloaded in the [base image](BuildPlan::base_image).
+    pub sync_db_dir: Option<PathBuf>,
+    /// Names of the official repositories whose packages need not be built.
+    pub official_repositories: Vec<String>,
+    /// ID of the GPG key to sign the packages and the repository database with.
+    pub signing_key: Option<String>,
+    /// GPG home directory that holds the [signing key](BuildPlan::signing_key).
+    pub gpg_home: Option<PathBuf>,
+    /// Flattened list of PKGBUILD directories to build.
+    ///
+    /// The [directories](crate::pkgbuild_desc::LocalPkgBuildDesc::dir) of local sources are
+    /// resolved against the [manifest directory](BuildPlan::manifest_dir).
+    pub sources: Vec<PkgBuildDesc>,
+    /// Groups of AUR packages whose pkgbases are yet to be [resolved](BuildPlan::resolve_aur).
+    pub aur_groups: Vec<AurPkgBuildGroup>,
+}
+
+/// Error when [`BuildPlan::resolve_aur`] fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ResolveAurError {
+    #[display(
+        "Pkgbases of AUR packages are not locked, run the fetch subcommand first: {}",
+        _0.join(", ")
+    )]
+    Unknown(#[error(not(source))] Vec<String>),
+    #[display("Duplicated pkgbase {_0:?}")]
+    DuplicatedBase(#[error(not(source))] String),
+}
+
+impl BuildPlan {
+    /// Names of the packages of the unresolved [AUR groups](BuildPlan::aur_groups).
+    pub fn aur_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.aur_groups
+            .iter()
+            .flat_map(|group| &group.aur)
+            .map(String::as_str)
+    }
+
+    /// Append the sources of the [AUR groups](BuildPlan::aur_groups) to the
+    /// [sources](BuildPlan::sources), given the pkgbase of each package indexed by its name.
+    pub fn resolve_aur(
+        &mut self,
+        pkgbases: &BTreeMap<String, String>,
+    ) -> Result<(), ResolveAurError> {
+        let aur = AurClient::new(self.aur_url.clone());
+        let mut resolved = Vec::new();
+        let mut unknown = Vec::new();
+        for group in &self.aur_groups {
+            match group.resolve(pkgbases, &aur) {
+                Ok(descs) => resolved.extend(descs),
+                Err(names) => unknown.extend(names),
+            }
+        }
+        if !unknown.is_empty() {
+            return Err(ResolveAurError::Unknown(unknown));
+        }
+        for desc in resolved {
+            let base = desc.package.base();
+            if self
+                .sources
+                .iter()
+                .any(|source| source.package().base() == base)
+            {
+                return Err(ResolveAurError::DuplicatedBase(base.to_string()));
+            }
+            self.sources.push(PkgBuildDesc::Git(desc));
+        }
+        self.aur_groups.clear();
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+#[cfg(all(test, unix))]
+pub(crate) mod fake;
+
+use derive_more::{Display, Error};
+use std::{
+    ffi::OsStr,
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+};
+
+/// Default value of [`Manifest::base_image`](crate::manifest::Manifest::base_image).
+pub const DEFAULT_BASE_IMAGE: &str = "docker.io/archlinux/archlinux:base-devel";
+
+/// Path at which [`ContainerManager::run_in_dir`] mounts the directory.
+const MOUNT_POINT: &str = "/pkgbuild";
+
+/// Unprivileged user to run the commands that refuse to run as root, such as `makepkg`.
+const UNPRIVILEGED_USER: &str = "nobody";
+
+/// Wrapper of the CLI of a container manager, such as `docker` or `podman`.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ContainerManager {
+    /// The name of or path to the container manager program.
+    pub program: String,
+}
+
+/// Error when a command of the container manager fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ContainerError {
+    #[display("Failed to spawn {program:?}: {error}")]
+    Spawn {
+        program: String,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("Command `{command}` exited with {status}: {stderr}")]
+    Status {
+        command: String,
+        status: ExitStatus,
+        #[error(not(source))]
+        stderr: String,
+    },
+    #[display("Command `{command}` exited with {status}")]
+    Exit {
+        command: String,
+        #[error(not(source))]
+        status: ExitStatus,
+    },
+    #[display("The output of `{_0}` is not valid UTF-8")]
+    NotUtf8(#[error(not(source))] String),
+    #[display("Failed to resolve {}: {error}", path.display())]
+    Canonicalize {
+        path: PathBuf,
+        #[error(source)]
+        error: io::Error,
+    },
+}
+
+impl ContainerManager {
+    /// Use the container manager `program`.
+    pub fn new(program: String) -> Self {
+        ContainerManager { program }
+    }
+
+    /// Run a command of the container manager and get its stdout.
+    pub fn run<Args>(&self, args: Args) -> Result<String, ContainerError>
+    where
+        Args: IntoIterator,
+        Args::Item: AsRef<OsStr>,
+    {
+        let args: Vec<_> = args.into_iter().collect();
+        let display_command = || display_command(&self.program, &args);
+        let output = Command::new(&self.program)
+            .args(&args)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|error| ContainerError::Spawn {
+                program: self.program.clone(),
+                error,
+            })?;
+        if !output.status.success() {
+            return Err(ContainerError::Status {
+                command: display_command(),
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        String::from_utf8(output.stdout).map_err(|_| ContainerError::NotUtf8(display_command()))
+    }
+
+    /// Run a command of the container manager, letting it print to the terminal.
+    pub fn exec<Args>(&self, args: Args) -> Result<(), ContainerError>
+    where
+        Args: IntoIterator,
+        Args::Item: AsRef<OsStr>,
+    {
+        let args: Vec<_> = args.into_iter().collect();
+        let status = Command::new(&self.program)
+            .args(&args)
+            .stdin(Stdio::null())
+            .status()
+            .map_err(|error| ContainerError::Spawn {
+                program: self.program.clone(),
+                error,
+            })?;
+        if status.success() {
+            return Ok(());
+        }
+        Err(ContainerError::Exit {
+            command: display_command(&self.program, &args),
+            status,
+        })
+    }
+
+    /// Run `command` as an unprivileged user in a disposable container of `image`, with `dir`
+    /// mounted read-only as the working directory, and get its stdout.
+    pub fn run_in_dir(
+        &self,
+        image: &str,
+        dir: &Path,
+        command: &[&str],
+    ) -> Result<String, ContainerError> {
+        let dir = fs::canonicalize(dir).map_err(|error| ContainerError::Canonicalize {
+            path: dir.to_path_buf(),
+            error,
+        })?;
+        let mut volume = dir.into_os_string();
+        volume.push(format!(":{MOUNT_POINT}:ro"));
+        let args = [
+            "run".as_ref(),
+            "--rm".as_ref(),
+            "--user".as_ref(),
+            UNPRIVILEGED_USER.as_ref(),
+            "--volume".as_ref(),
+            volume.as_os_str(),
+            "--workdir".as_ref(),
+            MOUNT_POINT.as_ref(),
+            image.as_ref(),
+        ];
+        self.run(args.into_iter().chain(command.iter().map(OsStr::new)))
+    }
+}
+
+/// Join a program and its arguments into a command line to show in error messages.
+fn display_command(program: &str, args: &[impl AsRef<OsStr>]) -> String {
+    let args = args.iter().map(|arg| arg.as_ref().to_string_lossy());
+    [program.into()]
+        .into_iter()
+        .chain(args)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,20 @@
+use super::ContainerManager;
+use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+
+/// Write a stand-in of the container manager into `root`, a shell script that logs its
+/// arguments to `calls.log` in `root`, then runs `body`.
+pub(crate) fn fake_container_manager(root: &Path, body: &str) -> ContainerManager {
+    fs::create_dir_all(root).unwrap();
+    let script = root.join("container-manager");
+    let log = root.join("calls.log");
+    let content = format!("#!/bin/sh\necho \"$*\" >> '{}'\n{body}\n", log.display());
+    fs::write(&script, content).unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    ContainerManager::new(script.to_string_lossy().into_owned())
+}
+
+/// Get the arguments of each call to the container manager of [`fake_container_manager`].
+pub(crate) fn calls(root: &Path) -> Vec<String> {
+    let log = fs::read_to_string(root.join("calls.log")).unwrap_or_default();
+    log.lines().map(str::to_string).collect()
+}
@@ -0,0 +1,134 @@
+use crate::package_version::{PackageVersion, ParsePackageVersionError};
+use arch_pkg_text::value::{Dependency, DependencySpecification, DependencySpecificationOperator};
+use derive_more::{Display, Error};
+use std::{cmp::Ordering, fmt};
+
+/// Parsed dependency, such as `foo`, `foo>=1.2-3`, or `libbar.so=2-64`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DependencySpec {
+    /// Name of the package, virtual package, or soname.
+    pub name: String,
+    /// Version constraint, if any.
+    pub constraint: Option<VersionConstraint>,
+}
+
+/// Version constraint of a [`DependencySpec`], such as `>=1.2-3`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct VersionConstraint {
+    /// The comparison operator.
+    pub operator: DependencySpecificationOperator,
+    /// The version to compare against.
+    pub version: PackageVersion,
+}
+
+/// Error when parsing a [`DependencySpec`] fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ParseDependencySpecError {
+    #[display("Dependency {_0:?} has no name")]
+    EmptyName(#[error(not(source))] String),
+    #[display("Dependency {_0:?} has an invalid version constraint")]
+    InvalidOperator(#[error(not(source))] String),
+    #[display("Dependency {dependency:?} has an invalid version: {error}")]
+    InvalidVersion {
+        dependency: String,
+        #[error(source)]
+        error: ParsePackageVersionError,
+    },
+}
+
+impl VersionConstraint {
+    /// Whether `version` satisfies the constraint, compared like `alpm_pkg_vercmp`.
+    pub fn matches(&self, version: &PackageVersion) -> bool {
+        let ordering = version.vercmp(&self.version);
+        match self.operator {
+            DependencySpecificationOperator::Less => ordering == Ordering::Less,
+            DependencySpecificationOperator::LessOrEqual => ordering != Ordering::Greater,
+            DependencySpecificationOperator::Equal => ordering == Ordering::Equal,
+            DependencySpecificationOperator::GreaterOrEqual => ordering != Ordering::Less,
+            DependencySpecificationOperator::Greater => ordering == Ordering::Greater,
+        }
+    }
+}
+
+impl DependencySpec {
+    /// Parse a dependency from a `.SRCINFO` or `desc` field.
+    pub fn parse(dependency: &str) -> Result<Self, ParseDependencySpecError> {
+        let (name, specification) = Dependency(dependency).components();
+        if name.as_str().is_empty() {
+            return Err(ParseDependencySpecError::EmptyName(dependency.to_string()));
+        }
+        let constraint = match specification {
+            DependencySpecification("") => None,
+            specification => {
+                let (operator, version) = specification.components().ok_or_else(|| {
+                    ParseDependencySpecError::InvalidOperator(dependency.to_string())
+                })?;
+                let version = PackageVersion::parse(version.as_str()).map_err(|error| {
+                    ParseDependencySpecError::InvalidVersion {
+                        dependency: dependency.to_string(),
+                        error,
+                    }
+                })?;
+                Some(VersionConstraint { operator, version })
+            }
+        };
+        Ok(DependencySpec {
+            name: name.as_str().to_string(),
+            constraint,
+        })
+    }
+
+    /// Whether the package `name` at `version`, which provides `provides`, satisfies the
+    /// dependency, like `alpm_depcmp`.
+    ///
+    /// A provision only satisfies a versioned dependency if it has a version of its own, as in
+    /// `foo=1.2`.
+    pub fn is_satisfied_by(
+        &self,
+        name: &str,
+        version: &PackageVersion,
+        provides: &[DependencySpec],
+    ) -> bool {
+        if name == self.name && self.matches(Some(version)) {
+            return true;
+        }
+        provides
+            .iter()
+            .filter(|provision| provision.name == self.name)
+            .any(|provision| match &provision.constraint {
+                Some(VersionConstraint {
+                    operator: DependencySpecificationOperator::Equal,
+                    version,
+                }) => self.matches(Some(version)),
+                _ => self.matches(None),
+            })
+    }
+
+    /// Whether a package or provision of the right name at `version` satisfies the constraint.
+    fn matches(&self, version: Option<&PackageVersion>) -> bool {
+        match (&self.constraint, version) {
+            (None, _) => true,
+            (Some(constraint), Some(version)) => constraint.matches(version),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+impl fmt::Display for VersionConstraint {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}{}", self.operator, self.version)
+    }
+}
+
+impl fmt::Display for DependencySpec {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.name)?;
+        if let Some(constraint) = &self.constraint {
+            write!(formatter, "{constraint}")?;
+        }
+        Ok(())
+    }
+}
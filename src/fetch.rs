@@ -0,0 +1,261 @@
+pub mod all;
+pub mod git;
+
+pub use all::FetchProgress;
+pub use git::{Git, GitError};
+
+use crate::{
+    pkgbuild_desc::{GitPkgBuildDesc, LocalPkgBuildDesc, PkgBuildDesc},
+    pkgbuild_name::PkgBuildName,
+};
+use derive_more::{Display, Error};
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+/// Fetcher of the PKGBUILD directories of a [build plan](crate::build_plan::BuildPlan).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Fetcher {
+    /// Directory to clone the git sources into, one sub directory per `pkgbase`.
+    pub pkgbuild_dir: PathBuf,
+    /// The `git` CLI.
+    pub git: Git,
+}
+
+/// PKGBUILD directory that is available in the local filesystem.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct FetchedPkgBuild {
+    /// Name(s) and base of the packages being built by the PKGBUILD.
+    pub package: PkgBuildName,
+    /// Path to the directory that contains the PKGBUILD.
+    pub dir: PathBuf,
+    /// Hash of the checked out commit, if the source is a git repository.
+    pub commit: Option<String>,
+    /// Pkgbase of the source whose clone also contains this PKGBUILD, if the clone is shared.
+    pub shared_with: Option<String>,
+}
+
+/// Error when [`Fetcher::fetch`] fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum FetchError {
+    #[display("{base}: The pkgbase cannot be used as a directory name")]
+    InvalidBase {
+        #[error(not(source))]
+        base: String,
+    },
+    #[display("{base}: Sub directory {sub_dir:?} is outside the git repository")]
+    InvalidSubDir {
+        base: String,
+        #[error(not(source))]
+        sub_dir: String,
+    },
+    #[display("{base}: Failed to create {}: {error}", path.display())]
+    CreateDir {
+        base: String,
+        path: PathBuf,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("{base}: {error}")]
+    Git {
+        base: String,
+        #[error(source)]
+        error: GitError,
+    },
+    #[display("{base}: Failed to fetch the clone shared with {owner}")]
+    SharedClone {
+        base: String,
+        #[error(not(source))]
+        owner: String,
+    },
+    #[display("{base}: {} is not a directory", path.display())]
+    NotDirectory {
+        base: String,
+        #[error(not(source))]
+        path: PathBuf,
+    },
+    #[display("{base}: The source has not been fetched")]
+    NotFetched {
+        #[error(not(source))]
+        base: String,
+    },
+}
+
+impl Fetcher {
+    /// Create a fetcher that clones the git sources into `pkgbuild_dir`.
+    pub fn new(pkgbuild_dir: PathBuf) -> Self {
+        Fetcher {
+            pkgbuild_dir,
+            git: Git::new(),
+        }
+    }
+
+    /// Replace [`Fetcher::git`].
+    pub fn with_git(mut self, git: Git) -> Self {
+        self.git = git;
+        self
+    }
+
+    /// Make the PKGBUILD directory of a source available in the local filesystem.
+    pub fn fetch(&self, source: &PkgBuildDesc) -> Result<FetchedPkgBuild, FetchError> {
+        match source {
+            PkgBuildDesc::Local(source) => self.fetch_local(source),
+            PkgBuildDesc::Git(source) => {
+                validate_git(source)?;
+                let (repo, commit) = self.sync_git(source)?;
+                locate_git(source, &repo, commit)
+            }
+        }
+    }
+
+    /// Find the PKGBUILD directory of a source that was previously fetched, without fetching it.
+    pub fn locate(&self, source: &PkgBuildDesc) -> Result<FetchedPkgBuild, FetchError> {
+        self.locate_shared(source, None)
+    }
+
+    /// Find the PKGBUILD directory of a source that was previously fetched into the clone of
+    /// another source, as reported by [`FetchedPkgBuild::shared_with`].
+    ///
+    /// `None` means that the source was fetched into a clone of its own.
+    pub fn locate_shared(
+        &self,
+        source: &PkgBuildDesc,
+        shared_with: Option<&str>,
+    ) -> Result<FetchedPkgBuild, FetchError> {
+        match source {
+            PkgBuildDesc::Local(source) => self.fetch_local(source),
+            PkgBuildDesc::Git(source) => {
+                validate_git(source)?;
+                let base = source.package.base();
+                let owner = shared_with.unwrap_or(base);
+                if !is_single_normal_component(owner) {
+                    return Err(FetchError::InvalidBase {
+                        base: owner.to_string(),
+                    });
+                }
+                let repo = self.pkgbuild_dir.join(owner);
+                if !repo.join(".git").exists() {
+                    return Err(FetchError::NotFetched {
+                        base: base.to_string(),
+                    });
+                }
+                let commit = self
+                    .git
+                    .run(&repo, ["rev-parse", "HEAD"])
+                    .map_err(|error| FetchError::Git {
+                        base: base.to_string(),
+                        error,
+                    })?;
+                let mut fetched = locate_git(source, &repo, commit)?;
+                fetched.shared_with = shared_with.map(str::to_string);
+                Ok(fetched)
+            }
+        }
+    }
+
+    /// Local directories need no fetching.
+    fn fetch_local(&self, source: &LocalPkgBuildDesc) -> Result<FetchedPkgBuild, FetchError> {
+        let dir = PathBuf::from(&source.dir);
+        if !dir.is_dir() {
+            return Err(FetchError::NotDirectory {
+                base: source.package.base().to_string(),
+                path: dir,
+            });
+        }
+        Ok(FetchedPkgBuild {
+            package: source.package.clone(),
+            dir,
+            commit: None,
+            shared_with: None,
+        })
+    }
+
+    /// Clone or update the repository of a git source, then check out its ref.
+    ///
+    /// Return the path to the repository and the hash of the checked out commit.
+    fn sync_git(&self, source: &GitPkgBuildDesc) -> Result<(PathBuf, String), FetchError> {
+        let base = source.package.base();
+        let repo = self.pkgbuild_dir.join(base);
+        fs::create_dir_all(&repo).map_err(|error| FetchError::CreateDir {
+            base: base.to_string(),
+            path: repo.clone(),
+            error,
+        })?;
+        let commit = self
+            .git
+            .sync(
+                &repo,
+                &source.git_url,
+                source.git_depth,
+                source.git_ref.as_deref(),
+            )
+            .map_err(|error| FetchError::Git {
+                base: base.to_string(),
+                error,
+            })?;
+        Ok((repo, commit))
+    }
+}
+
+/// Check that the pkgbase and the sub directory of a git source are safe to use as paths.
+fn validate_git(source: &GitPkgBuildDesc) -> Result<(), FetchError> {
+    let base = source.package.base();
+    if !is_single_normal_component(base) {
+        return Err(FetchError::InvalidBase {
+            base: base.to_string(),
+        });
+    }
+    if let Some(sub_dir) = &source.sub_dir {
+        if !is_relative_inside(sub_dir) {
+            return Err(FetchError::InvalidSubDir {
+                base: base.to_string(),
+                sub_dir: sub_dir.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Locate the PKGBUILD directory of a git source in a synced repository.
+fn locate_git(
+    source: &GitPkgBuildDesc,
+    repo: &Path,
+    commit: String,
+) -> Result<FetchedPkgBuild, FetchError> {
+    let dir = match &source.sub_dir {
+        Some(sub_dir) => repo.join(sub_dir),
+        None => repo.to_path_buf(),
+    };
+    if !dir.is_dir() {
+        return Err(FetchError::NotDirectory {
+            base: source.package.base().to_string(),
+            path: dir,
+        });
+    }
+    Ok(FetchedPkgBuild {
+        package: source.package.clone(),
+        dir,
+        commit: Some(commit),
+        shared_with: None,
+    })
+}
+
+/// Whether a string is a plain file name.
+pub(crate) fn is_single_normal_component(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    )
+}
+
+/// Whether a relative path stays inside the directory it is relative to.
+fn is_relative_inside(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
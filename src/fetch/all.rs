@@ -0,0 +1,250 @@
+use super::{locate_git, validate_git, FetchError, FetchedPkgBuild, Fetcher};
+use crate::pkgbuild_desc::{GitPkgBuildDesc, PkgBuildDesc};
+use pipe_trait::Pipe;
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// Progress of [`Fetcher::fetch_all`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FetchProgress<'a> {
+    /// A source started being fetched.
+    Started { source: &'a PkgBuildDesc },
+    /// A source finished being fetched.
+    Finished {
+        source: &'a PkgBuildDesc,
+        result: &'a Result<FetchedPkgBuild, FetchError>,
+    },
+}
+
+/// Sources to be fetched together, with their indices in the list of sources.
+enum Task<'a> {
+    /// A local source.
+    Local(usize, &'a PkgBuildDesc),
+    /// Git sources that share the same URL, ref, and depth, and thus the same clone.
+    ///
+    /// The clone is named after the first source, the others are
+    /// [shared with](FetchedPkgBuild::shared_with) it.
+    Git(Vec<(usize, &'a GitPkgBuildDesc)>),
+}
+
+impl Fetcher {
+    /// Fetch multiple sources with at most `jobs` sources being fetched at once.
+    ///
+    /// Git sources with the same URL, ref, and depth share a single clone, which is named after
+    /// the first of them and recorded in [`FetchedPkgBuild::shared_with`]. A failed source does
+    /// not stop the others. The results are in the same order as `sources`, and `report` is called
+    /// from the worker threads as the sources progress.
+    pub fn fetch_all<Report>(
+        &self,
+        sources: &[PkgBuildDesc],
+        jobs: NonZeroUsize,
+        report: Report,
+    ) -> Vec<Result<FetchedPkgBuild, FetchError>>
+    where
+        Report: Fn(FetchProgress) + Sync,
+    {
+        let tasks = plan_tasks(sources);
+        let next_task = AtomicUsize::new(0);
+        let results = sources
+            .iter()
+            .map(|_| None)
+            .collect::<Vec<_>>()
+            .pipe(Mutex::new);
+
+        thread::scope(|scope| {
+            for _ in 0..jobs.get().min(tasks.len()) {
+                scope.spawn(|| {
+                    while let Some(task) = tasks.get(next_task.fetch_add(1, Ordering::Relaxed)) {
+                        self.run_task(
+                            sources,
+                            task,
+                            |index, result| {
+                                report(FetchProgress::Finished {
+                                    source: &sources[index],
+                                    result: &result,
+                                });
+                                results.lock().expect("lock results")[index] = Some(result);
+                            },
+                            &report,
+                        );
+                    }
+                });
+            }
+        });
+
+        results
+            .into_inner()
+            .expect("unlock results")
+            .into_iter()
+            .map(|result| result.expect("every source belongs to a task"))
+            .collect()
+    }
+
+    /// Fetch the sources of a task, passing the result of each source to `finish`.
+    fn run_task<Finish, Report>(
+        &self,
+        sources: &[PkgBuildDesc],
+        task: &Task,
+        finish: Finish,
+        report: &Report,
+    ) where
+        Finish: Fn(usize, Result<FetchedPkgBuild, FetchError>),
+        Report: Fn(FetchProgress),
+    {
+        let members = match task {
+            Task::Local(index, source) => {
+                report(FetchProgress::Started { source });
+                finish(*index, self.fetch(source));
+                return;
+            }
+            Task::Git(members) => members,
+        };
+        for (index, _) in members {
+            report(FetchProgress::Started {
+                source: &sources[*index],
+            });
+        }
+
+        let (owner_index, owner) = members[0];
+        let synced = validate_git(owner).and_then(|()| self.sync_git(owner));
+        let (repo, commit) = match synced {
+            Ok(synced) => synced,
+            Err(error) => {
+                finish(owner_index, Err(error));
+                for (index, member) in &members[1..] {
+                    let error = FetchError::SharedClone {
+                        base: member.package.base().to_string(),
+                        owner: owner.package.base().to_string(),
+                    };
+                    finish(*index, Err(error));
+                }
+                return;
+            }
+        };
+
+        for (position, &(index, member)) in members.iter().enumerate() {
+            let result = validate_git(member)
+                .and_then(|()| locate_git(member, &repo, commit.clone()))
+                .map(|fetched| FetchedPkgBuild {
+                    shared_with: (position > 0).then(|| owner.package.base().to_string()),
+                    ..fetched
+                });
+            finish(index, result);
+        }
+    }
+}
+
+/// Group the sources into tasks.
+fn plan_tasks(sources: &[PkgBuildDesc]) -> Vec<Task<'_>> {
+    let mut tasks = Vec::new();
+    let mut clones: Vec<Vec<_>> = Vec::new();
+    let mut clone_indices = HashMap::<_, usize>::new();
+    for (index, source) in sources.iter().enumerate() {
+        let git_source = match source {
+            PkgBuildDesc::Local(_) => {
+                tasks.push(Task::Local(index, source));
+                continue;
+            }
+            PkgBuildDesc::Git(git_source) => git_source,
+        };
+        let key = (
+            &git_source.git_url,
+            &git_source.git_ref,
+            git_source.git_depth,
+        );
+        match clone_indices.get(&key) {
+            Some(&clone) => clones[clone].push((index, git_source)),
+            None => {
+                clone_indices.insert(key, clones.len());
+                clones.push(vec![(index, git_source)]);
+            }
+        }
+    }
+    tasks.extend(clones.into_iter().map(Task::Git));
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkgbuild_name::PkgBuildName;
+    use std::{env, fs, path::Path, process};
+
+    /// Create a git repository with a PKGBUILD directory for each of `bases`.
+    fn monorepo(dir: &Path, bases: &[&str]) {
+        let git = Fetcher::new(dir.to_path_buf()).git;
+        fs::create_dir_all(dir).unwrap();
+        git.run(dir, ["init", "--quiet"]).unwrap();
+        for base in bases {
+            fs::create_dir_all(dir.join(base)).unwrap();
+            fs::write(dir.join(base).join("PKGBUILD"), format!("pkgname={base}\n")).unwrap();
+        }
+        git.run(dir, ["add", "."]).unwrap();
+        git.run(
+            dir,
+            [
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "--quiet",
+                "--message=init",
+            ],
+        )
+        .unwrap();
+    }
+
+    fn member(url: &Path, base: &str) -> PkgBuildDesc {
+        PkgBuildDesc::Git(GitPkgBuildDesc {
+            package: PkgBuildName::single(base.to_string()),
+            git_url: url.to_string_lossy().into_owned(),
+            git_depth: None,
+            git_ref: None,
+            sub_dir: Some(base.to_string()),
+        })
+    }
+
+    #[test]
+    fn locate_members_of_shared_clone() {
+        let root = env::temp_dir().join(format!("pacman-repo-builder-fetch-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let upstream = root.join("upstream");
+        monorepo(&upstream, &["foo", "bar"]);
+        let sources = [member(&upstream, "foo"), member(&upstream, "bar")];
+        let fetcher = Fetcher::new(root.join("pkgbuild"));
+
+        let jobs = NonZeroUsize::new(2).unwrap();
+        let fetched: Vec<_> = fetcher
+            .fetch_all(&sources, jobs, |_| {})
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(fetched[0].shared_with, None);
+        assert_eq!(fetched[1].shared_with.as_deref(), Some("foo"));
+        assert!(fetched[1].dir.join("PKGBUILD").is_file());
+
+        for (source, fetched) in sources.iter().zip(&fetched) {
+            let located = fetcher
+                .locate_shared(source, fetched.shared_with.as_deref())
+                .unwrap();
+            assert_eq!(located.dir, fetched.dir);
+            assert_eq!(located.commit, fetched.commit);
+            assert_eq!(located.shared_with, fetched.shared_with);
+        }
+        assert!(matches!(
+            fetcher.locate(&sources[1]),
+            Err(FetchError::NotFetched { base }) if base == "bar",
+        ));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
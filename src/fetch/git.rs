@@ -0,0 +1,164 @@
+use derive_more::{Display, Error};
+use std::{
+    ffi::OsStr,
+    io,
+    path::Path,
+    process::{Command, ExitStatus, Stdio},
+};
+
+/// Wrapper of the `git` CLI.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Git {
+    /// The name of or path to the `git` program.
+    pub program: String,
+}
+
+/// Error when a `git` command fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum GitError {
+    #[display("Failed to spawn {program:?}: {error}")]
+    Spawn {
+        program: String,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("Command `git {args}` exited with {status}: {stderr}")]
+    Status {
+        args: String,
+        status: ExitStatus,
+        #[error(not(source))]
+        stderr: String,
+    },
+    #[display("The output of `git {_0}` is not valid UTF-8")]
+    NotUtf8(#[error(not(source))] String),
+}
+
+impl Default for Git {
+    fn default() -> Self {
+        Git::new()
+    }
+}
+
+impl Git {
+    /// Use the `git` program from `PATH`.
+    pub fn new() -> Self {
+        Git {
+            program: "git".to_string(),
+        }
+    }
+
+    /// Replace [`Git::program`].
+    pub fn with_program(mut self, program: String) -> Self {
+        self.program = program;
+        self
+    }
+
+    /// Run a `git` command in a repository and get its trimmed stdout.
+    pub fn run<Args>(&self, repo: &Path, args: Args) -> Result<String, GitError>
+    where
+        Args: IntoIterator,
+        Args::Item: AsRef<OsStr>,
+    {
+        let args: Vec<_> = args.into_iter().collect();
+        let display_args = || {
+            args.iter()
+                .map(|arg| arg.as_ref().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let output = Command::new(&self.program)
+            .arg("-C")
+            .arg(repo)
+            .args(&args)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|error| GitError::Spawn {
+                program: self.program.clone(),
+                error,
+            })?;
+        if !output.status.success() {
+            return Err(GitError::Status {
+                args: display_args(),
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        String::from_utf8(output.stdout)
+            .map(|stdout| stdout.trim().to_string())
+            .map_err(|_| GitError::NotUtf8(display_args()))
+    }
+
+    /// Whether a repository only has part of its history.
+    pub fn is_shallow(&self, repo: &Path) -> Result<bool, GitError> {
+        self.run(repo, ["rev-parse", "--is-shallow-repository"])
+            .map(|output| output == "true")
+    }
+
+    /// Make an existing directory a clone of `url` at `git_ref`, and get the commit hash.
+    ///
+    /// An existing clone in the directory is reused: its remote is updated, the target is
+    /// fetched, and the work tree is reset to it. `git_ref` defaults to the remote `HEAD`.
+    /// When `depth` is set, only that many commits of history are fetched.
+    pub fn sync(
+        &self,
+        repo: &Path,
+        url: &str,
+        depth: Option<u64>,
+        git_ref: Option<&str>,
+    ) -> Result<String, GitError> {
+        if repo.join(".git").exists() {
+            self.run(repo, ["remote", "set-url", "--", "origin", url])?;
+        } else {
+            self.run(repo, ["init", "--quiet"])?;
+            self.run(repo, ["remote", "add", "--", "origin", url])?;
+        }
+
+        let target = git_ref.unwrap_or("HEAD");
+        let mut fetch_args = vec!["fetch".to_string(), "--quiet".to_string()];
+        match depth {
+            Some(depth) => fetch_args.push(format!("--depth={depth}")),
+            None if self.is_shallow(repo)? => fetch_args.push("--unshallow".to_string()),
+            None => {}
+        }
+        fetch_args.extend(["--".to_string(), "origin".to_string(), target.to_string()]);
+
+        let commit = match self.run(repo, &fetch_args) {
+            Ok(_) => "FETCH_HEAD",
+            Err(_) if is_commit_hash(target) => {
+                // The server may refuse to serve a commit by its hash, or the hash may be
+                // abbreviated, so fetch the whole history to find it.
+                let mut fetch_args = vec!["fetch", "--quiet", "--tags"];
+                if self.is_shallow(repo)? {
+                    fetch_args.push("--unshallow");
+                }
+                fetch_args.extend(["--", "origin", "+refs/heads/*:refs/remotes/origin/*"]);
+                self.run(repo, fetch_args)?;
+                target
+            }
+            Err(error) => return Err(error),
+        };
+
+        let commit = self.run(
+            repo,
+            [
+                "rev-parse",
+                "--verify",
+                "--end-of-options",
+                &format!("{commit}^{{commit}}"),
+            ],
+        )?;
+        self.run(
+            repo,
+            ["checkout", "--quiet", "--force", "--detach", &commit],
+        )?;
+        self.run(repo, ["clean", "--quiet", "--force", "-d"])?;
+        Ok(commit)
+    }
+}
+
+/// Whether a git ref looks like a full or abbreviated commit hash.
+fn is_commit_hash(git_ref: &str) -> bool {
+    (7..=64).contains(&git_ref.len()) && git_ref.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
@@ -0,0 +1,388 @@
+mod constraints;
+mod export;
+
+pub use constraints::{UnsatisfiedDependenciesError, UnsatisfiedDependency};
+pub use export::{ExportOptions, ExportedEdge, ExportedGraph, ExportedNode, ExportedNodeKind};
+
+use crate::{dependency_spec::DependencySpec, srcinfo::Srcinfo};
+use derive_more::{Display, Error};
+use serde::Serialize;
+use std::{collections::HashMap, fmt};
+
+/// Graph of the dependencies between PKGBUILDs, built from their `.SRCINFO`.
+///
+/// Dependencies that are not built by any of the PKGBUILDs are not part of the graph.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct DependencyGraph {
+    /// PKGBUILDs in the order they were given to [`DependencyGraph::new`].
+    pub nodes: Vec<GraphNode>,
+}
+
+/// PKGBUILD in a [`DependencyGraph`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct GraphNode {
+    /// The pkgbase of the PKGBUILD.
+    pub base: String,
+    /// Dependencies of the PKGBUILD on the other nodes.
+    pub edges: Vec<DependencyEdge>,
+}
+
+/// Dependency of a [`GraphNode`] on another.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct DependencyEdge {
+    /// Index of the node that satisfies the dependency.
+    pub target: usize,
+    /// The dependency as declared in the `.SRCINFO`, such as `foo>=1.0`.
+    pub dependency: DependencySpec,
+    /// The field of the `.SRCINFO` that declares the dependency.
+    pub kind: DependencyKind,
+}
+
+/// Field of a `.SRCINFO` that declares a dependency.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    #[display("depends")]
+    Depends,
+    #[display("makedepends")]
+    MakeDepends,
+    #[display("checkdepends")]
+    CheckDepends,
+}
+
+/// Error when the dependencies of the PKGBUILDs form a cycle.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub struct DependencyCycleError {
+    /// The pkgbases in the cycle, each depending on the next, the last depending on the first.
+    pub bases: Vec<String>,
+    /// The dependency of each pkgbase in [`bases`](DependencyCycleError::bases) on the next.
+    pub edges: Vec<(DependencyKind, String)>,
+}
+
+impl fmt::Display for DependencyCycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Dependency cycle: ")?;
+        for (base, (kind, dependency)) in self.bases.iter().zip(&self.edges) {
+            write!(f, "{base} -({kind} {dependency})-> ")?;
+        }
+        match self.bases.first() {
+            Some(first) => write!(f, "{first}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl DependencyGraph {
+    /// Link the PKGBUILDs to each other through their dependencies.
+    ///
+    /// A dependency is satisfied by the PKGBUILD that builds a package of the same name, or
+    /// else by the first PKGBUILD that provides it. Dependencies of a PKGBUILD on itself are ignored.
+    pub fn new(srcinfos: &[Srcinfo]) -> Self {
+        let mut providers = HashMap::new();
+        for (index, srcinfo) in srcinfos.iter().enumerate() {
+            for name in srcinfo.names() {
+                providers.insert(name, index);
+            }
+        }
+        for (index, srcinfo) in srcinfos.iter().enumerate() {
+            for provide in srcinfo.provisions() {
+                providers.entry(provide.name.as_str()).or_insert(index);
+            }
+        }
+
+        let nodes = srcinfos
+            .iter()
+            .enumerate()
+            .map(|(index, srcinfo)| {
+                let edges = srcinfo
+                    .dependencies()
+                    .into_iter()
+                    .filter_map(|(kind, dependency)| {
+                        let target = *providers.get(dependency.name.as_str())?;
+                        (target != index).then(|| DependencyEdge {
+                            target,
+                            dependency: dependency.clone(),
+                            kind,
+                        })
+                    })
+                    .collect();
+                GraphNode {
+                    base: srcinfo.base.clone(),
+                    edges,
+                }
+            })
+            .collect();
+        DependencyGraph { nodes }
+    }
+
+    /// Group the nodes into layers that can be built in order, the nodes of each layer only
+    /// depending on those of the previous layers.
+    ///
+    /// Each layer contains indices of [`nodes`](DependencyGraph::nodes) in ascending order.
+    pub fn layers(&self) -> Result<Vec<Vec<usize>>, DependencyCycleError> {
+        let mut remaining: Vec<usize> = self
+            .nodes
+            .iter()
+            .map(|node| distinct_targets(node).len())
+            .collect();
+        let mut dependents = vec![Vec::new(); self.nodes.len()];
+        for (index, node) in self.nodes.iter().enumerate() {
+            for target in distinct_targets(node) {
+                dependents[target].push(index);
+            }
+        }
+
+        let mut layers = Vec::new();
+        let mut layer: Vec<usize> = (0..self.nodes.len())
+            .filter(|&index| remaining[index] == 0)
+            .collect();
+        let mut placed = 0;
+        while !layer.is_empty() {
+            placed += layer.len();
+            let mut next = Vec::new();
+            for &index in &layer {
+                for &dependent in &dependents[index] {
+                    remaining[dependent] -= 1;
+                    if remaining[dependent] == 0 {
+                        next.push(dependent);
+                    }
+                }
+            }
+            next.sort_unstable();
+            layers.push(layer);
+            layer = next;
+        }
+
+        if placed == self.nodes.len() {
+            return Ok(layers);
+        }
+        let start = remaining
+            .iter()
+            .position(|&count| count > 0)
+            .expect("some nodes are left unplaced");
+        Err(self.find_cycle(start, &remaining))
+    }
+
+    /// Get the indices of the nodes that a node depends on, directly or indirectly, in ascending order.
+    pub fn dependencies(&self, index: usize) -> Vec<usize> {
+        let mut found = vec![false; self.nodes.len()];
+        let mut pending = vec![index];
+        while let Some(dependent) = pending.pop() {
+            for edge in &self.nodes[dependent].edges {
+                if edge.target != index && !found[edge.target] {
+                    found[edge.target] = true;
+                    pending.push(edge.target);
+                }
+            }
+        }
+        (0..self.nodes.len())
+            .filter(|&index| found[index])
+            .collect()
+    }
+
+    /// Get the indices of the nodes that depend on a node, directly or indirectly, in ascending order.
+    pub fn reverse_dependencies(&self, index: usize) -> Vec<usize> {
+        let mut found = vec![false; self.nodes.len()];
+        let mut pending = vec![index];
+        while let Some(target) = pending.pop() {
+            for (dependent, node) in self.nodes.iter().enumerate() {
+                let depends = node.edges.iter().any(|edge| edge.target == target);
+                if depends && dependent != index && !found[dependent] {
+                    found[dependent] = true;
+                    pending.push(dependent);
+                }
+            }
+        }
+        (0..self.nodes.len())
+            .filter(|&index| found[index])
+            .collect()
+    }
+
+    /// Get the indices of the nodes in an order such that every node comes after its dependencies.
+    pub fn build_order(&self) -> Result<Vec<usize>, DependencyCycleError> {
+        self.layers()
+            .map(|layers| layers.into_iter().flatten().collect())
+    }
+
+    /// Follow the dependencies of `start` among the unplaced nodes until one is visited twice.
+    ///
+    /// Every unplaced node has an unplaced dependency, so the walk always ends in a cycle.
+    fn find_cycle(&self, start: usize, remaining: &[usize]) -> DependencyCycleError {
+        let mut path: Vec<(usize, &DependencyEdge)> = Vec::new();
+        let mut current = start;
+        loop {
+            if let Some(position) = path.iter().position(|(index, _)| *index == current) {
+                let cycle = &path[position..];
+                return DependencyCycleError {
+                    bases: cycle
+                        .iter()
+                        .map(|(index, _)| self.nodes[*index].base.clone())
+                        .collect(),
+                    edges: cycle
+                        .iter()
+                        .map(|(_, edge)| (edge.kind, edge.dependency.to_string()))
+                        .collect(),
+                };
+            }
+            let edge = self.nodes[current]
+                .edges
+                .iter()
+                .find(|edge| remaining[edge.target] > 0)
+                .expect("an unplaced node has an unplaced dependency");
+            path.push((current, edge));
+            current = edge.target;
+        }
+    }
+}
+
+/// Get the distinct nodes that a node depends on.
+fn distinct_targets(node: &GraphNode) -> Vec<usize> {
+    let mut targets: Vec<_> = node.edges.iter().map(|edge| edge.target).collect();
+    targets.sort_unstable();
+    targets.dedup();
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a `.SRCINFO` whose base section ends with `fields`, one `pkgname` per package.
+    ///
+    /// A package may be followed by the lines of its own section, such as `"foo\n\tprovides = bar"`.
+    fn srcinfo(base: &str, fields: &str, packages: &[&str]) -> Srcinfo {
+        let packages: String = packages
+            .iter()
+            .map(|package| format!("\npkgname = {package}\n"))
+            .collect();
+        Srcinfo::parse(&format!(
+            "pkgbase = {base}\n\tpkgver = 1.0\n\tpkgrel = 1\n\tarch = any\n{fields}{packages}",
+        ))
+        .unwrap()
+    }
+
+    /// Describe the edges of a node as `(target, dependency, kind)`.
+    fn edges(graph: &DependencyGraph, index: usize) -> Vec<(usize, String, DependencyKind)> {
+        graph.nodes[index]
+            .edges
+            .iter()
+            .map(|edge| (edge.target, edge.dependency.to_string(), edge.kind))
+            .collect()
+    }
+
+    #[test]
+    fn chain() {
+        let graph = DependencyGraph::new(&[
+            srcinfo("a", "\tdepends = b>=1\n\tdepends = glibc\n", &["a"]),
+            srcinfo("b", "\tmakedepends = c\n\tmakedepends = cmake\n", &["b"]),
+            srcinfo("c", "\tdepends = glibc\n", &["c"]),
+        ]);
+        let bases: Vec<_> = graph.nodes.iter().map(|node| node.base.as_str()).collect();
+        assert_eq!(bases, ["a", "b", "c"]);
+        assert_eq!(
+            edges(&graph, 0),
+            [(1, "b>=1".to_string(), DependencyKind::Depends)],
+        );
+        assert_eq!(
+            edges(&graph, 1),
+            [(2, "c".to_string(), DependencyKind::MakeDepends)],
+        );
+        assert!(
+            edges(&graph, 2).is_empty(),
+            "official dependencies are ignored"
+        );
+
+        assert_eq!(graph.layers().unwrap(), [[2], [1], [0]]);
+        assert_eq!(graph.build_order().unwrap(), [2, 1, 0]);
+        assert_eq!(graph.dependencies(0), [1, 2]);
+        assert_eq!(graph.dependencies(1), [2]);
+        assert!(graph.dependencies(2).is_empty());
+        assert_eq!(graph.reverse_dependencies(2), [0, 1]);
+        assert_eq!(graph.reverse_dependencies(1), [0]);
+        assert!(graph.reverse_dependencies(0).is_empty());
+    }
+
+    #[test]
+    fn diamond() {
+        let graph = DependencyGraph::new(&[
+            srcinfo("top", "\tdepends = left\n\tdepends = right\n", &["top"]),
+            srcinfo("right", "\tdepends = bottom\n", &["right"]),
+            srcinfo("bottom", "", &["bottom"]),
+            srcinfo("left", "\tcheckdepends = bottom\n", &["left"]),
+        ]);
+        assert_eq!(graph.layers().unwrap(), [vec![2], vec![1, 3], vec![0]]);
+        assert_eq!(graph.build_order().unwrap(), [2, 1, 3, 0]);
+        assert_eq!(graph.dependencies(0), [1, 2, 3]);
+        assert_eq!(graph.reverse_dependencies(2), [0, 1, 3]);
+        assert!(graph.dependencies(1).iter().all(|&index| index == 2));
+    }
+
+    #[test]
+    fn split_packages_and_provisions() {
+        let graph = DependencyGraph::new(&[
+            srcinfo(
+                "foo",
+                "\tdepends = bar-lib\n\tdepends = libbar.so=1-64\n\tcheckdepends = bar-cli\n\tmakedepends = virtual\n",
+                &["foo"],
+            ),
+            srcinfo(
+                "bar",
+                "",
+                &[
+                    "bar-cli\n\tdepends = bar-lib",
+                    "bar-lib\n\tprovides = libbar.so=1-64",
+                ],
+            ),
+            srcinfo("baz", "\tprovides = bar-cli\n\tprovides = virtual\n", &["baz"]),
+            srcinfo("qux", "\tprovides = virtual\n", &["qux"]),
+        ]);
+        assert_eq!(
+            edges(&graph, 0),
+            [
+                (1, "bar-lib".to_string(), DependencyKind::Depends),
+                (1, "libbar.so=1-64".to_string(), DependencyKind::Depends),
+                (2, "virtual".to_string(), DependencyKind::MakeDepends),
+                (1, "bar-cli".to_string(), DependencyKind::CheckDepends),
+            ],
+            "a package name wins over a provision, and the first provider over the others",
+        );
+        assert!(
+            edges(&graph, 1).is_empty(),
+            "dependencies between the packages of a PKGBUILD are ignored",
+        );
+        assert_eq!(graph.layers().unwrap(), [vec![1, 2, 3], vec![0]]);
+    }
+
+    #[test]
+    fn cycle() {
+        let graph = DependencyGraph::new(&[
+            srcinfo("d", "\tdepends = a\n", &["d"]),
+            srcinfo("a", "\tdepends = b\n", &["a"]),
+            srcinfo("b", "\tmakedepends = c>=1\n", &["b"]),
+            srcinfo("c", "\tdepends = a\n", &["c"]),
+            srcinfo("e", "", &["e"]),
+        ]);
+        let error = graph.layers().unwrap_err();
+        assert_eq!(error.bases, ["a", "b", "c"]);
+        assert_eq!(
+            error.edges,
+            [
+                (DependencyKind::Depends, "b".to_string()),
+                (DependencyKind::MakeDepends, "c>=1".to_string()),
+                (DependencyKind::Depends, "a".to_string()),
+            ],
+        );
+        assert_eq!(
+            error.to_string(),
+            "Dependency cycle: a -(depends b)-> b -(makedepends c>=1)-> c -(depends a)-> a",
+        );
+        assert!(graph.build_order().is_err());
+        assert_eq!(graph.dependencies(1), [2, 3]);
+        assert_eq!(graph.reverse_dependencies(1), [0, 2, 3]);
+    }
+}
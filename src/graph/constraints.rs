@@ -0,0 +1,236 @@
+use super::{DependencyGraph, DependencyKind};
+use crate::{
+    dependency_spec::DependencySpec, package_version::PackageVersion, srcinfo::Srcinfo,
+    sync_db::SyncPackages,
+};
+use derive_more::{Display, Error};
+use std::fmt;
+
+/// Dependency of a PKGBUILD that cannot be satisfied.
+#[derive(Debug, Display)]
+#[non_exhaustive]
+pub enum UnsatisfiedDependency {
+    #[display("{base}: {kind} {dependency:?} is not satisfied by {}", candidates.join(", "))]
+    Version {
+        base: String,
+        kind: DependencyKind,
+        dependency: String,
+        /// The packages of the right name, such as `foo 1.0-1`.
+        candidates: Vec<String>,
+    },
+}
+
+/// Error when some dependencies of the PKGBUILDs cannot be satisfied.
+#[derive(Debug, Display, Error)]
+#[display("Unsatisfiable dependencies:{}", UnsatisfiedList(_0))]
+pub struct UnsatisfiedDependenciesError(#[error(not(source))] pub Vec<UnsatisfiedDependency>);
+
+impl DependencyGraph {
+    /// Check the version constraints of the dependencies of `srcinfos`.
+    ///
+    /// A dependency on another node must be satisfied by that node. Any other versioned
+    /// dependency must be satisfied by a package of `official` if one has the right name.
+    /// Dependencies that nothing provides are left to [`resolve`](crate::resolve).
+    /// With an empty `official`, only the dependencies between the nodes are checked.
+    pub fn check_constraints(
+        &self,
+        srcinfos: &[Srcinfo],
+        official: &SyncPackages,
+    ) -> Result<(), UnsatisfiedDependenciesError> {
+        let mut unsatisfied = Vec::new();
+        for (srcinfo, node) in srcinfos.iter().zip(&self.nodes) {
+            for (kind, spec) in srcinfo.dependencies() {
+                if spec.constraint.is_none() {
+                    continue;
+                }
+                let edge = node.edges.iter().find(|edge| edge.dependency == *spec);
+                let candidates = match edge {
+                    Some(edge) => source_candidates(&srcinfos[edge.target], spec),
+                    None => official_candidates(official, spec),
+                };
+                if candidates.is_empty() || candidates.iter().any(|(_, satisfied)| *satisfied) {
+                    continue;
+                }
+                let mut candidates: Vec<_> = candidates.into_iter().map(|(name, _)| name).collect();
+                candidates.sort();
+                candidates.dedup();
+                unsatisfied.push(UnsatisfiedDependency::Version {
+                    base: srcinfo.base.clone(),
+                    kind,
+                    dependency: spec.to_string(),
+                    candidates,
+                });
+            }
+        }
+        if unsatisfied.is_empty() {
+            Ok(())
+        } else {
+            Err(UnsatisfiedDependenciesError(unsatisfied))
+        }
+    }
+}
+
+/// Get the packages of a PKGBUILD that have the name of `spec`, and whether they satisfy it.
+fn source_candidates(srcinfo: &Srcinfo, spec: &DependencySpec) -> Vec<(String, bool)> {
+    let version = &srcinfo.version;
+    srcinfo
+        .packages
+        .iter()
+        .filter(|package| package.name == spec.name || provides_name(&package.provides, &spec.name))
+        .map(|package| {
+            let satisfied = spec.is_satisfied_by(&package.name, version, &package.provides);
+            (format!("{} {version}", package.name), satisfied)
+        })
+        .collect()
+}
+
+/// Get the official packages that have the name of `spec`, and whether they satisfy it.
+fn official_candidates(official: &SyncPackages, spec: &DependencySpec) -> Vec<(String, bool)> {
+    official
+        .packages
+        .iter()
+        .filter(|package| package.name == spec.name || provides_name(&package.provides, &spec.name))
+        .filter_map(|package| {
+            let version = PackageVersion::parse(&package.version).ok()?;
+            let satisfied = spec.is_satisfied_by(&package.name, &version, &package.provides);
+            Some((format!("{} {}", package.name, package.version), satisfied))
+        })
+        .collect()
+}
+
+/// Whether one of `provides` has the name `name`.
+fn provides_name(provides: &[DependencySpec], name: &str) -> bool {
+    provides.iter().any(|provision| provision.name == name)
+}
+
+/// Display one [`UnsatisfiedDependency`] per line.
+struct UnsatisfiedList<'a>(&'a [UnsatisfiedDependency]);
+
+impl fmt::Display for UnsatisfiedList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for unsatisfied in self.0 {
+            write!(f, "\n  {unsatisfied}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync_db::SyncPackage;
+
+    /// Parse a `.SRCINFO` of a single package, whose base section ends with `fields`.
+    fn srcinfo(base: &str, pkgver: &str, fields: &str) -> Srcinfo {
+        Srcinfo::parse(&format!(
+            "pkgbase = {base}\n\tpkgver = {pkgver}\n\tpkgrel = 1\n\tarch = any\n{fields}\npkgname = {base}\n",
+        ))
+        .unwrap()
+    }
+
+    fn official(packages: &[(&str, &str, &[&str])]) -> SyncPackages {
+        let packages = packages
+            .iter()
+            .map(|(name, version, provides)| SyncPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+                provides: provides
+                    .iter()
+                    .map(|provision| DependencySpec::parse(provision).unwrap())
+                    .collect(),
+            })
+            .collect();
+        SyncPackages {
+            packages,
+            ..SyncPackages::default()
+        }
+    }
+
+    /// Check the constraints and describe the unsatisfied dependencies.
+    fn check(srcinfos: &[Srcinfo], official: &SyncPackages) -> Vec<String> {
+        let graph = DependencyGraph::new(srcinfos);
+        match graph.check_constraints(srcinfos, official) {
+            Ok(()) => Vec::new(),
+            Err(UnsatisfiedDependenciesError(unsatisfied)) => {
+                unsatisfied.iter().map(ToString::to_string).collect()
+            }
+        }
+    }
+
+    #[test]
+    fn dependencies_on_sources() {
+        let bar = srcinfo("bar", "1.0", "\tprovides = libbar=2.5\n");
+        let satisfied = srcinfo(
+            "foo",
+            "1.0",
+            "\tdepends = bar>=1.0\n\tdepends = libbar>=2\n\tmakedepends = bar\n",
+        );
+        assert!(check(&[satisfied, bar.clone()], &SyncPackages::default()).is_empty());
+
+        let unsatisfied = srcinfo(
+            "foo",
+            "1.0",
+            "\tdepends = bar>=2.0\n\tmakedepends = libbar<2\n",
+        );
+        assert_eq!(
+            check(&[unsatisfied, bar], &SyncPackages::default()),
+            [
+                "foo: depends \"bar>=2.0\" is not satisfied by bar 1.0-1",
+                "foo: makedepends \"libbar<2\" is not satisfied by bar 1.0-1",
+            ],
+        );
+    }
+
+    #[test]
+    fn unversioned_provision_does_not_satisfy_versioned_dependency() {
+        let bar = srcinfo("bar", "3.0", "\tprovides = libbar\n");
+        let foo = srcinfo("foo", "1.0", "\tdepends = libbar>=1\n");
+        assert_eq!(
+            check(&[foo, bar], &SyncPackages::default()),
+            ["foo: depends \"libbar>=1\" is not satisfied by bar 3.0-1"],
+        );
+    }
+
+    #[test]
+    fn dependencies_on_official_packages() {
+        let official = official(&[
+            ("glibc", "2.40-1", &[]),
+            ("bash", "5.2-1", &["sh=5.2"]),
+            ("zsh", "5.9-1", &["sh"]),
+        ]);
+        let satisfied = srcinfo(
+            "foo",
+            "1.0",
+            "\tdepends = glibc>=2.39\n\tdepends = sh>=5\n\tdepends = missing>=1\n",
+        );
+        assert!(check(&[satisfied], &official).is_empty());
+
+        let unsatisfied = srcinfo(
+            "foo",
+            "1.0",
+            "\tcheckdepends = glibc>2.40\n\tdepends = sh>6\n",
+        );
+        assert_eq!(
+            check(std::slice::from_ref(&unsatisfied), &official),
+            [
+                "foo: depends \"sh>6\" is not satisfied by bash 5.2-1, zsh 5.9-1",
+                "foo: checkdepends \"glibc>2.40\" is not satisfied by glibc 2.40-1",
+            ],
+        );
+        assert!(
+            check(&[unsatisfied], &SyncPackages::default()).is_empty(),
+            "only the dependencies between the sources are checked without official packages",
+        );
+    }
+
+    #[test]
+    fn source_is_preferred_over_official_package() {
+        let official = official(&[("bar", "2.0-1", &[])]);
+        let bar = srcinfo("bar", "1.0", "");
+        let foo = srcinfo("foo", "1.0", "\tdepends = bar>=2\n");
+        assert_eq!(
+            check(&[foo, bar], &official),
+            ["foo: depends \"bar>=2\" is not satisfied by bar 1.0-1"],
+        );
+    }
+}
@@ -0,0 +1,510 @@
+use super::{DependencyGraph, DependencyKind};
+use crate::{srcinfo::Srcinfo, sync_db::SyncPackages};
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Options of [`ExportedGraph::new`].
+#[derive(Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct ExportOptions<'a> {
+    /// Only show the pkgbases, not the packages that they build.
+    pub collapse_split: bool,
+    /// Show the dependencies that these official packages satisfy.
+    pub official: Option<&'a SyncPackages>,
+    /// Index of the node whose reverse dependencies are highlighted.
+    pub highlight: Option<usize>,
+}
+
+/// Visual representation of a [`DependencyGraph`].
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+#[serde(rename_all = "kebab-case")]
+pub struct ExportedGraph {
+    /// The pkgbases, packages, and official dependencies.
+    pub nodes: Vec<ExportedNode>,
+    /// The dependencies of the pkgbases.
+    pub edges: Vec<ExportedEdge>,
+}
+
+/// Node of an [`ExportedGraph`].
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+#[serde(rename_all = "kebab-case")]
+pub struct ExportedNode {
+    /// Unique identifier, such as `pkgbase:foo`, `pkgname:foo-docs`, or `official:glibc`.
+    pub id: String,
+    /// Name of the pkgbase or of the package.
+    pub label: String,
+    /// What the node represents.
+    pub kind: ExportedNodeKind,
+    /// Identifier of the pkgbase node that builds this package node.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Whether the node is the highlighted one or one of its reverse dependencies.
+    pub highlighted: bool,
+}
+
+/// What an [`ExportedNode`] represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportedNodeKind {
+    /// A PKGBUILD of the plan.
+    Pkgbase,
+    /// A package built by a split PKGBUILD.
+    Pkgname,
+    /// A package from the official repositories.
+    Official,
+}
+
+/// Edge of an [`ExportedGraph`], from a pkgbase to what satisfies one of its dependencies.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+#[serde(rename_all = "kebab-case")]
+pub struct ExportedEdge {
+    /// Identifier of the dependent node.
+    pub from: String,
+    /// Identifier of the node that satisfies the dependency.
+    pub to: String,
+    /// The field of the `.SRCINFO` that declares the dependency.
+    pub kind: DependencyKind,
+    /// The dependency as written in the `.SRCINFO`.
+    pub dependency: String,
+}
+
+impl ExportedGraph {
+    /// Create the representation of the graph of `srcinfos`.
+    pub fn new(srcinfos: &[Srcinfo], graph: &DependencyGraph, options: ExportOptions) -> Self {
+        let highlighted = match options.highlight {
+            Some(index) => {
+                let mut highlighted = graph.reverse_dependencies(index);
+                highlighted.push(index);
+                highlighted
+            }
+            None => Vec::new(),
+        };
+        let is_split = |srcinfo: &Srcinfo| {
+            !options.collapse_split && srcinfo.names().ne([srcinfo.base.as_str()])
+        };
+
+        let mut nodes = Vec::new();
+        for (index, srcinfo) in srcinfos.iter().enumerate() {
+            let id = pkgbase_id(&srcinfo.base);
+            let highlighted = highlighted.contains(&index);
+            if is_split(srcinfo) {
+                nodes.extend(srcinfo.names().map(|name| ExportedNode {
+                    id: pkgname_id(name),
+                    label: name.to_string(),
+                    kind: ExportedNodeKind::Pkgname,
+                    parent: Some(id.clone()),
+                    highlighted,
+                }));
+            }
+            nodes.push(ExportedNode {
+                id,
+                label: srcinfo.base.clone(),
+                kind: ExportedNodeKind::Pkgbase,
+                parent: None,
+                highlighted,
+            });
+        }
+
+        let mut edges = Vec::new();
+        for (node, srcinfo) in graph.nodes.iter().zip(srcinfos) {
+            for edge in &node.edges {
+                let target = &srcinfos[edge.target];
+                let name = edge.dependency.name.as_str();
+                let to = if is_split(target) && target.names().any(|own| own == name) {
+                    pkgname_id(name)
+                } else {
+                    pkgbase_id(&target.base)
+                };
+                edges.push(ExportedEdge {
+                    from: pkgbase_id(&srcinfo.base),
+                    to,
+                    kind: edge.kind,
+                    dependency: edge.dependency.to_string(),
+                });
+            }
+        }
+
+        if let Some(official) = options.official {
+            for (srcinfo, node) in srcinfos.iter().zip(&graph.nodes) {
+                for (kind, dependency) in srcinfo.dependencies() {
+                    let in_graph = node.edges.iter().any(|edge| edge.dependency == *dependency);
+                    let name = dependency.name.as_str();
+                    if in_graph || !official.provides(name) {
+                        continue;
+                    }
+                    let id = official_id(name);
+                    if !nodes.iter().any(|node| node.id == id) {
+                        nodes.push(ExportedNode {
+                            id: id.clone(),
+                            label: name.to_string(),
+                            kind: ExportedNodeKind::Official,
+                            parent: None,
+                            highlighted: false,
+                        });
+                    }
+                    edges.push(ExportedEdge {
+                        from: pkgbase_id(&srcinfo.base),
+                        to: id,
+                        kind,
+                        dependency: dependency.to_string(),
+                    });
+                }
+            }
+        }
+
+        ExportedGraph { nodes, edges }
+    }
+
+    /// Render the graph in the Graphviz DOT language.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph dependencies {\n");
+        let mut clusters = 0;
+        for node in self.nodes.iter().filter(|node| node.parent.is_none()) {
+            let children: Vec<_> = self
+                .nodes
+                .iter()
+                .filter(|child| child.parent.as_ref() == Some(&node.id))
+                .collect();
+            if children.is_empty() {
+                writeln!(dot, "  {}", dot_node(node)).unwrap();
+                continue;
+            }
+            writeln!(dot, "  subgraph cluster_{clusters} {{").unwrap();
+            writeln!(dot, "    label = {};", dot_string(&node.label)).unwrap();
+            writeln!(dot, "    {}", dot_node(node)).unwrap();
+            for child in children {
+                writeln!(dot, "    {}", dot_node(child)).unwrap();
+            }
+            writeln!(dot, "  }}").unwrap();
+            clusters += 1;
+        }
+        for edge in &self.edges {
+            let style = if edge.kind == DependencyKind::Depends {
+                ""
+            } else {
+                ", style = dashed"
+            };
+            writeln!(
+                dot,
+                "  {} -> {} [label = {}{style}];",
+                dot_string(&edge.from),
+                dot_string(&edge.to),
+                dot_string(&edge.dependency),
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Render the graph as a Mermaid flowchart.
+    pub fn to_mermaid(&self) -> String {
+        // Mermaid identifiers cannot contain all the characters of the node identifiers.
+        let mermaid_id = |id: &str| {
+            let index = self.nodes.iter().position(|node| node.id == id);
+            format!("n{}", index.expect("edges only refer to existing nodes"))
+        };
+        let mermaid_node = |index: usize, node: &ExportedNode| {
+            let label = node.label.replace('"', "#quot;");
+            match node.kind {
+                ExportedNodeKind::Pkgbase => format!("n{index}[\"{label}\"]"),
+                ExportedNodeKind::Pkgname => format!("n{index}([\"{label}\"])"),
+                ExportedNodeKind::Official => format!("n{index}[(\"{label}\")]"),
+            }
+        };
+
+        let mut mermaid = String::from("flowchart TD\n");
+        for (index, node) in self.nodes.iter().enumerate() {
+            if node.parent.is_some() {
+                continue;
+            }
+            let children: Vec<_> = self
+                .nodes
+                .iter()
+                .enumerate()
+                .filter(|(_, child)| child.parent.as_ref() == Some(&node.id))
+                .collect();
+            if children.is_empty() {
+                writeln!(mermaid, "  {}", mermaid_node(index, node)).unwrap();
+                continue;
+            }
+            writeln!(mermaid, "  subgraph group{index} [\"{}\"]", node.label).unwrap();
+            writeln!(mermaid, "    {}", mermaid_node(index, node)).unwrap();
+            for (index, child) in children {
+                writeln!(mermaid, "    {}", mermaid_node(index, child)).unwrap();
+            }
+            writeln!(mermaid, "  end").unwrap();
+        }
+        for edge in &self.edges {
+            let arrow = if edge.kind == DependencyKind::Depends {
+                "-->"
+            } else {
+                "-.->"
+            };
+            let label = edge.dependency.replace('"', "#quot;");
+            let (from, to) = (mermaid_id(&edge.from), mermaid_id(&edge.to));
+            writeln!(mermaid, "  {from} {arrow}|\"{label}\"| {to}").unwrap();
+        }
+        let highlighted: Vec<_> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.highlighted)
+            .map(|(index, _)| format!("n{index}"))
+            .collect();
+        if !highlighted.is_empty() {
+            mermaid.push_str("  classDef highlighted stroke:#d00,stroke-width:3px\n");
+            writeln!(mermaid, "  class {} highlighted", highlighted.join(",")).unwrap();
+        }
+        mermaid
+    }
+
+    /// Render the graph as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("the graph is always serializable")
+    }
+}
+
+/// Get the identifier of a pkgbase node.
+fn pkgbase_id(base: &str) -> String {
+    format!("pkgbase:{base}")
+}
+
+/// Get the identifier of a package node.
+fn pkgname_id(name: &str) -> String {
+    format!("pkgname:{name}")
+}
+
+/// Get the identifier of an official package node.
+fn official_id(name: &str) -> String {
+    format!("official:{name}")
+}
+
+/// Render a node statement in the Graphviz DOT language.
+fn dot_node(node: &ExportedNode) -> String {
+    let shape = match node.kind {
+        ExportedNodeKind::Pkgbase => "box",
+        ExportedNodeKind::Pkgname => "ellipse",
+        ExportedNodeKind::Official => "cylinder",
+    };
+    let color = if node.highlighted {
+        ", color = red"
+    } else {
+        ""
+    };
+    format!(
+        "{} [label = {}, shape = {shape}{color}];",
+        dot_string(&node.id),
+        dot_string(&node.label),
+    )
+}
+
+/// Quote a string as a Graphviz DOT identifier.
+///
+/// Backslashes are escaped too, since Graphviz reads escape sequences such as `\n` in labels.
+fn dot_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for char in text.chars() {
+        match char {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            char => quoted.push(char),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `foo` that depends on a package of the split `bar` and on `glibc`, and a `baz` that
+    /// depends on `foo`.
+    fn srcinfos() -> Vec<Srcinfo> {
+        let parse = |text: &str| Srcinfo::parse(text).unwrap();
+        vec![
+            parse(concat!(
+                "pkgbase = foo\n\tpkgver = 1.0\n\tpkgrel = 1\n\tarch = any\n",
+                "\tdepends = bar-lib>=1\n\tmakedepends = glibc\n\npkgname = foo\n",
+            )),
+            parse(concat!(
+                "pkgbase = bar\n\tpkgver = 1.0\n\tpkgrel = 1\n\tarch = any\n",
+                "\npkgname = bar-cli\n\npkgname = bar-lib\n",
+            )),
+            parse(concat!(
+                "pkgbase = baz\n\tpkgver = 1.0\n\tpkgrel = 1\n\tarch = any\n",
+                "\tcheckdepends = foo\n\npkgname = baz\n",
+            )),
+        ]
+    }
+
+    fn export(options: ExportOptions) -> ExportedGraph {
+        let srcinfos = srcinfos();
+        let graph = DependencyGraph::new(&srcinfos);
+        ExportedGraph::new(&srcinfos, &graph, options)
+    }
+
+    fn official() -> SyncPackages {
+        SyncPackages {
+            provided: ["glibc".to_string()].into(),
+            ..SyncPackages::default()
+        }
+    }
+
+    #[test]
+    fn dot() {
+        let official = official();
+        let options = ExportOptions {
+            official: Some(&official),
+            ..ExportOptions::default()
+        };
+        assert_eq!(
+            export(options).to_dot(),
+            r#"digraph dependencies {
+  "pkgbase:foo" [label = "foo", shape = box];
+  subgraph cluster_0 {
+    label = "bar";
+    "pkgbase:bar" [label = "bar", shape = box];
+    "pkgname:bar-cli" [label = "bar-cli", shape = ellipse];
+    "pkgname:bar-lib" [label = "bar-lib", shape = ellipse];
+  }
+  "pkgbase:baz" [label = "baz", shape = box];
+  "official:glibc" [label = "glibc", shape = cylinder];
+  "pkgbase:foo" -> "pkgname:bar-lib" [label = "bar-lib>=1"];
+  "pkgbase:baz" -> "pkgbase:foo" [label = "foo", style = dashed];
+  "pkgbase:foo" -> "official:glibc" [label = "glibc", style = dashed];
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn mermaid() {
+        let official = official();
+        let options = ExportOptions {
+            official: Some(&official),
+            ..ExportOptions::default()
+        };
+        assert_eq!(
+            export(options).to_mermaid(),
+            r#"flowchart TD
+  n0["foo"]
+  subgraph group3 ["bar"]
+    n3["bar"]
+    n1(["bar-cli"])
+    n2(["bar-lib"])
+  end
+  n4["baz"]
+  n5[("glibc")]
+  n0 -->|"bar-lib>=1"| n2
+  n4 -.->|"foo"| n0
+  n0 -.->|"glibc"| n5
+"#,
+        );
+    }
+
+    #[test]
+    fn json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&export(ExportOptions::default()).to_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "nodes": [
+                    {"id": "pkgbase:foo", "label": "foo", "kind": "pkgbase", "highlighted": false},
+                    {
+                        "id": "pkgname:bar-cli",
+                        "label": "bar-cli",
+                        "kind": "pkgname",
+                        "parent": "pkgbase:bar",
+                        "highlighted": false,
+                    },
+                    {
+                        "id": "pkgname:bar-lib",
+                        "label": "bar-lib",
+                        "kind": "pkgname",
+                        "parent": "pkgbase:bar",
+                        "highlighted": false,
+                    },
+                    {"id": "pkgbase:bar", "label": "bar", "kind": "pkgbase", "highlighted": false},
+                    {"id": "pkgbase:baz", "label": "baz", "kind": "pkgbase", "highlighted": false},
+                ],
+                "edges": [
+                    {
+                        "from": "pkgbase:foo",
+                        "to": "pkgname:bar-lib",
+                        "kind": "depends",
+                        "dependency": "bar-lib>=1",
+                    },
+                    {
+                        "from": "pkgbase:baz",
+                        "to": "pkgbase:foo",
+                        "kind": "checkdepends",
+                        "dependency": "foo",
+                    },
+                ],
+            }),
+        );
+    }
+
+    #[test]
+    fn collapse_and_highlight() {
+        let options = ExportOptions {
+            collapse_split: true,
+            highlight: Some(0),
+            ..ExportOptions::default()
+        };
+        let graph = export(options);
+        assert_eq!(
+            graph.to_dot(),
+            r#"digraph dependencies {
+  "pkgbase:foo" [label = "foo", shape = box, color = red];
+  "pkgbase:bar" [label = "bar", shape = box];
+  "pkgbase:baz" [label = "baz", shape = box, color = red];
+  "pkgbase:foo" -> "pkgbase:bar" [label = "bar-lib>=1"];
+  "pkgbase:baz" -> "pkgbase:foo" [label = "foo", style = dashed];
+}
+"#,
+        );
+        assert_eq!(
+            graph.to_mermaid(),
+            r#"flowchart TD
+  n0["foo"]
+  n1["bar"]
+  n2["baz"]
+  n0 -->|"bar-lib>=1"| n1
+  n2 -.->|"foo"| n0
+  classDef highlighted stroke:#d00,stroke-width:3px
+  class n0,n2 highlighted
+"#,
+        );
+        let highlighted: Vec<_> = graph
+            .nodes
+            .iter()
+            .map(|node| (node.id.as_str(), node.highlighted))
+            .collect();
+        assert_eq!(
+            highlighted,
+            [
+                ("pkgbase:foo", true),
+                ("pkgbase:bar", false),
+                ("pkgbase:baz", true),
+            ],
+        );
+    }
+
+    #[test]
+    fn dot_strings_are_escaped() {
+        assert_eq!(dot_string("foo"), r#""foo""#);
+        assert_eq!(dot_string(r#"a"b\c"#), r#""a\"b\\c""#);
+        assert_eq!(dot_string("a\nb"), r#""a\nb""#);
+        assert_eq!(dot_string("é"), "\"é\"");
+    }
+}
@@ -1,11 +1,26 @@
 pub mod app;
+pub mod aur;
+pub mod build;
+pub mod build_plan;
+pub mod container;
+pub mod dependency_spec;
+pub mod fetch;
 pub mod file_base_name;
+pub mod graph;
+pub mod lockfile;
 pub mod manifest;
+pub mod package_version;
 pub mod pkgbuild_desc;
 pub mod pkgbuild_group;
 pub mod pkgbuild_name;
+pub mod repo_db;
 pub mod repo_name;
+pub mod resolve;
+pub mod sign;
+pub mod srcinfo;
+pub mod sync_db;
 pub mod template;
+pub mod vercmp;
 
 pub mod misc {
     pub use serde;
@@ -0,0 +1,481 @@
+use crate::pkgbuild_desc::{GitPkgBuildDesc, PkgBuildDesc};
+use derive_more::{Display, Error};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Header written at the top of every lockfile.
+const HEADER: &str = "# This file is generated by build-pacman-repo. Do not edit it by hand.\n";
+
+/// Commits of the git sources that were resolved by a previous fetch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[non_exhaustive]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Lockfile {
+    /// Locked commit of each git source, indexed by `pkgbase`.
+    #[serde(default)]
+    pub sources: BTreeMap<String, LockedSource>,
+    /// Pkgbase of each package of the [AUR groups](crate::pkgbuild_group::AurPkgBuildGroup),
+    /// indexed by package name.
+    #[serde(default)]
+    pub aur: BTreeMap<String, String>,
+}
+
+/// Locked commit of a git source.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[non_exhaustive]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LockedSource {
+    /// URL of the repo that the commit was fetched from.
+    pub git_url: String,
+    /// Branch, tag, or commit that the manifest requested when the commit was locked.
+    pub git_ref: Option<String>,
+    /// Sub directory of the PKGBUILD that the manifest requested when the commit was locked.
+    pub sub_dir: Option<String>,
+    /// Hash of the commit.
+    pub commit: String,
+    /// Pkgbase of the source whose clone the commit was checked out in, if it is not this one.
+    pub shared_with: Option<String>,
+}
+
+impl LockedSource {
+    /// Whether the commit was locked for a source with the same URL, ref, and sub directory.
+    pub fn locks(&self, source: &GitPkgBuildDesc) -> bool {
+        self.git_url == source.git_url
+            && self.git_ref == source.git_ref
+            && self.sub_dir == source.sub_dir
+    }
+}
+
+/// How to treat the existing lockfile when fetching.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LockMode {
+    /// Reuse the locked commits, lock the new sources, and forget the removed ones.
+    Reuse,
+    /// Only fetch the locked commits, fail if the lockfile is missing or outdated.
+    ///
+    /// [`Lockfile::load`] only [checks](Lockfile::check_locked) that every git source of the
+    /// manifest is locked, the caller is responsible for [verifying](Lockfile::verify) the
+    /// lockfile once all sources are known.
+    Locked,
+    /// Ignore the lockfile and lock the current commits of all sources.
+    UpdateAll,
+    /// Ignore the locked commits of the given pkgbases, and the locked pkgbases of their AUR
+    /// packages.
+    Update(Vec<String>),
+}
+
+/// Error when reading or writing a lockfile fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum LockfileError {
+    #[display("Failed to read {}: {error}", path.display())]
+    Read {
+        path: PathBuf,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("Failed to parse {}: {error}", path.display())]
+    Parse {
+        path: PathBuf,
+        #[error(source)]
+        error: serde_hjson::Error,
+    },
+    #[display("Failed to serialize the lockfile: {_0}")]
+    Serialize(serde_hjson::Error),
+    #[display("Failed to write {}: {error}", path.display())]
+    Write {
+        path: PathBuf,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("{}: Lockfile is required but does not exist", _0.display())]
+    Missing(#[error(not(source))] PathBuf),
+    #[display("{}: {error}", path.display())]
+    Mismatch {
+        path: PathBuf,
+        #[error(source)]
+        error: LockMismatchError,
+    },
+    #[display("Cannot update {_0:?}: No git source with such pkgbase")]
+    UnknownSource(#[error(not(source))] String),
+}
+
+/// Difference between a lockfile and the git sources of a manifest.
+#[derive(Debug, Display, Error)]
+#[display(
+    "Lockfile disagrees with the manifest (missing: {missing:?}, extraneous: {extraneous:?}, changed: {changed:?})"
+)]
+#[non_exhaustive]
+pub struct LockMismatchError {
+    /// Pkgbases of the git sources that are not locked.
+    pub missing: Vec<String>,
+    /// Locked pkgbases that are no longer git sources.
+    pub extraneous: Vec<String>,
+    /// Pkgbases whose git URLs, refs, or sub directories differ from the locked ones.
+    pub changed: Vec<String>,
+}
+
+impl Lockfile {
+    /// Get the path to the lockfile of a manifest file, such as `foo.lock.hjson` for `foo.hjson`.
+    pub fn path(manifest_path: &Path) -> PathBuf {
+        let stem = manifest_path
+            .file_stem()
+            .map_or_else(|| "manifest".into(), |stem| stem.to_string_lossy());
+        manifest_path.with_file_name(format!("{stem}.lock.hjson"))
+    }
+
+    /// Read a lockfile, `None` means that the file does not exist.
+    pub fn read(path: &Path) -> Result<Option<Lockfile>, LockfileError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => {
+                return Err(LockfileError::Read {
+                    path: path.to_path_buf(),
+                    error,
+                })
+            }
+        };
+        serde_hjson::from_str(&text)
+            .map(Some)
+            .map_err(|error| LockfileError::Parse {
+                path: path.to_path_buf(),
+                error,
+            })
+    }
+
+    /// Write the lockfile.
+    pub fn write(&self, path: &Path) -> Result<(), LockfileError> {
+        let text = serde_hjson::to_string(self).map_err(LockfileError::Serialize)?;
+        fs::write(path, format!("{HEADER}{text}\n")).map_err(|error| LockfileError::Write {
+            path: path.to_path_buf(),
+            error,
+        })
+    }
+
+    /// Read the lockfile at `path` and keep the locked commits that `mode` allows to reuse.
+    pub fn load(
+        path: &Path,
+        mode: &LockMode,
+        sources: &[PkgBuildDesc],
+    ) -> Result<Lockfile, LockfileError> {
+        let lockfile = Lockfile::read(path)?;
+        match mode {
+            LockMode::Reuse => Ok(lockfile.unwrap_or_default()),
+            LockMode::Locked => {
+                let lockfile =
+                    lockfile.ok_or_else(|| LockfileError::Missing(path.to_path_buf()))?;
+                lockfile
+                    .check_locked(sources)
+                    .map_err(|error| LockfileError::Mismatch {
+                        path: path.to_path_buf(),
+                        error,
+                    })?;
+                Ok(lockfile)
+            }
+            LockMode::UpdateAll => Ok(Lockfile::default()),
+            LockMode::Update(bases) => {
+                let mut lockfile = lockfile.unwrap_or_default();
+                for base in bases {
+                    let is_known = lockfile.sources.contains_key(base)
+                        || git_sources(sources).any(|source| source.package.base() == base);
+                    if !is_known {
+                        return Err(LockfileError::UnknownSource(base.clone()));
+                    }
+                    lockfile.sources.remove(base);
+                    // Look up the pkgbases of its AUR packages again, in case they moved.
+                    lockfile.aur.retain(|_, aur_base| aur_base != base);
+                }
+                Ok(lockfile)
+            }
+        }
+    }
+
+    /// Check that the lockfile locks exactly the git sources, with the same URLs, refs, and
+    /// sub directories.
+    pub fn verify(&self, sources: &[PkgBuildDesc]) -> Result<(), LockMismatchError> {
+        let error = self.mismatch(sources);
+        if error.missing.is_empty() && error.extraneous.is_empty() && error.changed.is_empty() {
+            return Ok(());
+        }
+        Err(error)
+    }
+
+    /// Check that every git source is locked with its current URL, ref, and sub directory.
+    ///
+    /// Unlike [`Lockfile::verify`], extraneous entries are allowed, since they may be
+    /// dependencies that are yet to be resolved.
+    pub fn check_locked(&self, sources: &[PkgBuildDesc]) -> Result<(), LockMismatchError> {
+        let error = self.mismatch(sources);
+        if error.missing.is_empty() && error.changed.is_empty() {
+            return Ok(());
+        }
+        Err(error)
+    }
+
+    /// Compare the lockfile with the git sources.
+    fn mismatch(&self, sources: &[PkgBuildDesc]) -> LockMismatchError {
+        let mut missing = Vec::new();
+        let mut changed = Vec::new();
+        for source in git_sources(sources) {
+            let base = source.package.base();
+            match self.sources.get(base) {
+                None => missing.push(base.to_string()),
+                Some(locked) if !locked.locks(source) => changed.push(base.to_string()),
+                Some(_) => {}
+            }
+        }
+        let extraneous: Vec<_> = self
+            .sources
+            .keys()
+            .filter(|base| !git_sources(sources).any(|source| source.package.base() == *base))
+            .cloned()
+            .collect();
+        LockMismatchError {
+            missing,
+            extraneous,
+            changed,
+        }
+    }
+
+    /// Get the source to fetch, whose ref is replaced by the locked commit if there is one.
+    ///
+    /// A locked commit is ignored when the git URL, the ref, or the sub directory of the source
+    /// has changed.
+    pub fn pin(&self, source: &PkgBuildDesc) -> PkgBuildDesc {
+        let PkgBuildDesc::Git(git_source) = source else {
+            return source.clone();
+        };
+        match self.sources.get(git_source.package.base()) {
+            Some(locked) if locked.locks(git_source) => PkgBuildDesc::Git(GitPkgBuildDesc {
+                git_ref: Some(locked.commit.clone()),
+                ..git_source.clone()
+            }),
+            _ => source.clone(),
+        }
+    }
+
+    /// Lock the commit of a git source, which was checked out in the clone of `shared_with` if
+    /// the clone is shared with another source.
+    pub fn insert(
+        &mut self,
+        source: &GitPkgBuildDesc,
+        commit: String,
+        shared_with: Option<String>,
+    ) {
+        let locked = LockedSource {
+            git_url: source.git_url.clone(),
+            git_ref: source.git_ref.clone(),
+            sub_dir: source.sub_dir.clone(),
+            commit,
+            shared_with,
+        };
+        self.sources
+            .insert(source.package.base().to_string(), locked);
+    }
+}
+
+/// Iterate over the git sources.
+fn git_sources(sources: &[PkgBuildDesc]) -> impl Iterator<Item = &GitPkgBuildDesc> + '_ {
+    sources.iter().filter_map(|source| match source {
+        PkgBuildDesc::Git(source) => Some(source),
+        PkgBuildDesc::Local(_) => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkgbuild_name::PkgBuildName;
+    use std::{env, process};
+
+    fn git_source(base: &str, git_ref: Option<&str>) -> GitPkgBuildDesc {
+        GitPkgBuildDesc {
+            package: PkgBuildName::single(base.to_string()),
+            git_url: format!("https://example.com/{base}.git"),
+            git_depth: None,
+            git_ref: git_ref.map(str::to_string),
+            sub_dir: None,
+        }
+    }
+
+    fn sources(sources: &[&GitPkgBuildDesc]) -> Vec<PkgBuildDesc> {
+        sources
+            .iter()
+            .map(|source| PkgBuildDesc::Git((*source).clone()))
+            .collect()
+    }
+
+    fn lockfile(sources: &[&GitPkgBuildDesc]) -> Lockfile {
+        let mut lockfile = Lockfile::default();
+        for source in sources {
+            let commit = format!("{}-commit", source.package.base());
+            lockfile.insert(source, commit, None);
+        }
+        lockfile
+    }
+
+    /// Write a lockfile into a unique temporary path.
+    fn temporary(name: &str, lockfile: Option<&Lockfile>) -> PathBuf {
+        let dir = env::temp_dir().join(format!("pacman-repo-builder-lockfile-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{name}.lock.hjson"));
+        match lockfile {
+            Some(lockfile) => lockfile.write(&path).unwrap(),
+            None => drop(fs::remove_file(&path)),
+        }
+        path
+    }
+
+    #[test]
+    fn path_next_to_manifest() {
+        let path = Lockfile::path(Path::new("dir/foo.hjson"));
+        assert_eq!(path, Path::new("dir/foo.lock.hjson"));
+    }
+
+    #[test]
+    fn write_then_read() {
+        let foo = git_source("foo", Some("v1"));
+        let written = lockfile(&[&foo]);
+        let path = temporary("roundtrip", Some(&written));
+        assert_eq!(Lockfile::read(&path).unwrap(), Some(written));
+    }
+
+    #[test]
+    fn load_reuse() {
+        let foo = git_source("foo", None);
+        let path = temporary("reuse", Some(&lockfile(&[&foo])));
+        let loaded = Lockfile::load(&path, &LockMode::Reuse, &sources(&[&foo])).unwrap();
+        assert_eq!(loaded, lockfile(&[&foo]));
+
+        let path = temporary("reuse-missing", None);
+        let loaded = Lockfile::load(&path, &LockMode::Reuse, &sources(&[&foo])).unwrap();
+        assert_eq!(loaded, Lockfile::default());
+    }
+
+    #[test]
+    fn load_locked() {
+        let foo = git_source("foo", None);
+        let bar = git_source("bar", None);
+        let path = temporary("locked", Some(&lockfile(&[&foo, &bar])));
+        // Extraneous entries may be dependencies that are yet to be resolved.
+        let loaded = Lockfile::load(&path, &LockMode::Locked, &sources(&[&foo])).unwrap();
+        assert_eq!(loaded, lockfile(&[&foo, &bar]));
+
+        let baz = git_source("baz", None);
+        let error = Lockfile::load(&path, &LockMode::Locked, &sources(&[&foo, &baz])).unwrap_err();
+        let LockfileError::Mismatch { error, .. } = error else {
+            panic!("unexpected error: {error}");
+        };
+        assert_eq!(error.missing, ["baz"]);
+    }
+
+    #[test]
+    fn load_locked_without_lockfile() {
+        let path = temporary("locked-missing", None);
+        let sources = sources(&[&git_source("foo", None)]);
+        let error = Lockfile::load(&path, &LockMode::Locked, &sources).unwrap_err();
+        assert!(matches!(error, LockfileError::Missing(_)));
+    }
+
+    #[test]
+    fn load_locked_with_changed_ref() {
+        let old = git_source("foo", Some("v1"));
+        let new = git_source("foo", Some("v2"));
+        let path = temporary("locked-ref", Some(&lockfile(&[&old])));
+        let error = Lockfile::load(&path, &LockMode::Locked, &sources(&[&new])).unwrap_err();
+        let LockfileError::Mismatch { error, .. } = error else {
+            panic!("unexpected error: {error}");
+        };
+        assert_eq!(error.changed, ["foo"]);
+    }
+
+    #[test]
+    fn load_update_all() {
+        let foo = git_source("foo", None);
+        let path = temporary("update-all", Some(&lockfile(&[&foo])));
+        let loaded = Lockfile::load(&path, &LockMode::UpdateAll, &sources(&[&foo])).unwrap();
+        assert_eq!(loaded, Lockfile::default());
+    }
+
+    #[test]
+    fn load_update_some() {
+        let foo = git_source("foo", None);
+        let bar = git_source("bar", None);
+        let path = temporary("update", Some(&lockfile(&[&foo, &bar])));
+        let all = sources(&[&foo, &bar]);
+        let mode = LockMode::Update(vec!["bar".to_string()]);
+        let loaded = Lockfile::load(&path, &mode, &all).unwrap();
+        assert_eq!(loaded, lockfile(&[&foo]));
+
+        let mode = LockMode::Update(vec!["nope".to_string()]);
+        let error = Lockfile::load(&path, &mode, &all).unwrap_err();
+        assert!(matches!(error, LockfileError::UnknownSource(base) if base == "nope"));
+    }
+
+    #[test]
+    fn load_update_forgets_aur_pkgbases() {
+        let foo = git_source("foo", None);
+        let bar = git_source("bar", None);
+        let mut old = lockfile(&[&foo, &bar]);
+        old.aur = BTreeMap::from([
+            ("foo".to_string(), "foo".to_string()),
+            ("bar-cli".to_string(), "bar".to_string()),
+            ("bar-gui".to_string(), "bar".to_string()),
+        ]);
+        let path = temporary("update-aur", Some(&old));
+        let mode = LockMode::Update(vec!["bar".to_string()]);
+        let loaded = Lockfile::load(&path, &mode, &[]).unwrap();
+        assert_eq!(
+            loaded.aur,
+            BTreeMap::from([("foo".to_string(), "foo".to_string())]),
+        );
+        let loaded = Lockfile::load(&path, &LockMode::Reuse, &[]).unwrap();
+        assert_eq!(loaded.aur, old.aur);
+    }
+
+    #[test]
+    fn verify_reports_every_difference() {
+        let foo = git_source("foo", Some("v1"));
+        let bar = git_source("bar", None);
+        let old = lockfile(&[&foo, &bar]);
+        let moved = GitPkgBuildDesc {
+            sub_dir: Some("bar".to_string()),
+            ..bar.clone()
+        };
+        let current = sources(&[
+            &git_source("foo", Some("v2")),
+            &moved,
+            &git_source("baz", None),
+        ]);
+        let error = old.verify(&current).unwrap_err();
+        assert_eq!(error.missing, ["baz"]);
+        assert!(error.extraneous.is_empty());
+        assert_eq!(error.changed, ["foo", "bar"]);
+
+        let error = old.verify(&sources(&[&foo])).unwrap_err();
+        assert_eq!(error.extraneous, ["bar"]);
+        assert!(old.verify(&sources(&[&foo, &bar])).is_ok());
+    }
+
+    #[test]
+    fn pin_only_unchanged_sources() {
+        let foo = git_source("foo", Some("main"));
+        let locked = lockfile(&[&foo]);
+        let PkgBuildDesc::Git(pinned) = locked.pin(&PkgBuildDesc::Git(foo)) else {
+            panic!("expecting a git source");
+        };
+        assert_eq!(pinned.git_ref.as_deref(), Some("foo-commit"));
+
+        let changed = git_source("foo", Some("release"));
+        let PkgBuildDesc::Git(pinned) = locked.pin(&PkgBuildDesc::Git(changed)) else {
+            panic!("expecting a git source");
+        };
+        assert_eq!(pinned.git_ref.as_deref(), Some("release"));
+    }
+}
@@ -1,5 +1,17 @@
-use crate::{file_base_name::FileBaseName, pkgbuild_group::PkgBuildGroup, repo_name::RepoName};
+mod load;
+mod span;
+mod untagged;
+
+pub use load::{
+    ContextLoadManifestError, LoadManifestError, ParseManifestError, ReadManifestError,
+};
+
+use crate::{
+    file_base_name::FileBaseName, pkgbuild_group::PkgBuildGroup, repo_name::RepoName,
+    resolve::MissingDependencyAction, template::CommandPolicy,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Data stored in a manifest file.
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -20,24 +32,82 @@ pub struct Manifest {
     /// Examples: `Dockerfile`, `Containerfile`.
     pub container_file: FileBaseName,
 
+    /// Container image with `base-devel` from which the packages are built.
+    ///
+    /// Defaults to `docker.io/archlinux/archlinux:base-devel`.
+    pub base_image: Option<String>,
+
     /// Directory to store all the repositories that contain PKGBUILD and .SRCINFO.
     ///
     /// The path is relative to the manifest file.
+    ///
+    /// Defaults to `pkgbuild`.
     pub pkgbuild_dir: Option<String>,
 
     /// Directory to store all the directories to build container images in.
     ///
     /// The path is relative to the manifest file.
+    ///
+    /// Defaults to `container`.
     pub container_dir: Option<String>,
 
     /// Directory to store all the built pacman package archives.
     ///
     /// The path is relative to the manifest file.
+    ///
+    /// Defaults to `package`.
     pub package_dir: Option<String>,
 
     /// Name of the repository of the built pacman packages.
     pub repo_name: RepoName,
 
+    /// Variables to be used by the templates via the `var:` scheme.
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+
+    /// Restrictions on the commands run by the `cmd:` template queries.
+    ///
+    /// The caller is responsible for applying this policy to the
+    /// [context](crate::template::TemplateContext) passed to [`Manifest::into_build_plan`].
+    #[serde(default)]
+    pub command_policy: CommandPolicy,
+
+    /// URL of the AUR to query and clone the [AUR packages](crate::pkgbuild_group::AurPkgBuildGroup) from.
+    ///
+    /// Defaults to `https://aur.archlinux.org`.
+    pub aur_url: Option<String>,
+
+    /// What to do with the dependencies that are neither in the official repositories nor in the sources.
+    ///
+    /// Defaults to `error`.
+    #[serde(default)]
+    pub missing_dependencies: MissingDependencyAction,
+
+    /// Directory of the pacman sync databases of the official repositories.
+    ///
+    /// The path is relative to the manifest file.
+    ///
+    /// Defaults to the sync databases of the [base image](Manifest::base_image), which are
+    /// refreshed in a disposable container whenever they are needed.
+    pub sync_db_dir: Option<String>,
+
+    /// Names of the official repositories whose packages need not be built.
+    ///
+    /// Defaults to `core` and `extra`, add `multilib` if the base image enables it.
+    pub official_repositories: Option<Vec<String>>,
+
+    /// ID of the GPG key to sign the packages and the repository database with.
+    ///
+    /// Without a key, nothing is signed nor verified.
+    pub signing_key: Option<String>,
+
+    /// GPG home directory that holds the [signing key](Manifest::signing_key).
+    ///
+    /// The path is relative to the manifest file.
+    ///
+    /// Defaults to that of `gpg`.
+    pub gpg_home: Option<String>,
+
     /// Sources from whence to fetch PKGBUILD and .SRCINFO to build packages.
     pub sources: Vec<PkgBuildGroup>,
 }
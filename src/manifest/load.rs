@@ -0,0 +1,333 @@
+use super::{span::Spans, untagged, Manifest};
+use crate::{
+    aur::DEFAULT_AUR_URL,
+    build_plan::BuildPlan,
+    container::DEFAULT_BASE_IMAGE,
+    pkgbuild_desc::{GitPkgBuildDesc, LocalPkgBuildDesc, PkgBuildDesc},
+    pkgbuild_group::ApplyTemplateError,
+    sync_db::DEFAULT_OFFICIAL_REPOSITORIES,
+    template::{
+        params::{QueryCmd, QueryEnv, QueryFile},
+        ManifestParams, QueryContext,
+    },
+};
+use derive_more::{Display, Error};
+use pipe_trait::Pipe;
+use serde_hjson::{ErrorCode, Value};
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Default value of [`Manifest::pkgbuild_dir`].
+const DEFAULT_PKGBUILD_DIR: &str = "pkgbuild";
+/// Default value of [`Manifest::container_dir`].
+const DEFAULT_CONTAINER_DIR: &str = "container";
+/// Default value of [`Manifest::package_dir`].
+const DEFAULT_PACKAGE_DIR: &str = "package";
+
+/// Error when [`Manifest::load`] fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum LoadManifestError<EnvError, CommandError, FileError> {
+    #[display("{_0}")]
+    Read(ReadManifestError),
+    #[display("{}: Path is not valid UTF-8: {}", manifest.display(), path.display())]
+    NonUtf8Path { manifest: PathBuf, path: PathBuf },
+    #[display("{}: Duplicated pkgbase {base:?}", manifest.display())]
+    DuplicatedBase {
+        manifest: PathBuf,
+        #[error(not(source))]
+        base: String,
+    },
+    #[display("{}: {base}: Git ref {git_ref:?} must not start with '-'", manifest.display())]
+    InvalidGitRef {
+        manifest: PathBuf,
+        base: String,
+        #[error(not(source))]
+        git_ref: String,
+    },
+    #[display("{}: {field}: Invalid template {template:?}: {message}", manifest.display())]
+    InvalidTemplate {
+        manifest: PathBuf,
+        field: String,
+        template: String,
+        #[error(not(source))]
+        message: String,
+    },
+    #[display("{}: {error}", manifest.display())]
+    Template {
+        manifest: PathBuf,
+        #[error(source)]
+        error: Box<ApplyTemplateError<EnvError, CommandError, FileError>>,
+    },
+}
+
+/// [`LoadManifestError`] of a [query context](QueryContext).
+pub type ContextLoadManifestError<Context> = LoadManifestError<
+    <Context as QueryEnv>::Error,
+    <Context as QueryCmd>::Error,
+    <Context as QueryFile>::Error,
+>;
+
+/// Error when [`Manifest::read`] fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ReadManifestError {
+    #[display("Failed to read {}: {error}", path.display())]
+    Io {
+        path: PathBuf,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("{_0}")]
+    Parse(ParseManifestError),
+}
+
+/// Error when the content of a manifest file is invalid.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub struct ParseManifestError {
+    /// Path to the manifest file.
+    pub path: PathBuf,
+    /// Line of the error (1-based), if known.
+    pub line: Option<usize>,
+    /// Column of the error (1-based), if known.
+    pub column: Option<usize>,
+    /// Path to the offending key, such as `sources[3].git-url`.
+    pub key: String,
+    /// Description of the error.
+    pub message: String,
+}
+
+impl fmt::Display for ParseManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ParseManifestError {
+            path,
+            line,
+            column,
+            key,
+            message,
+        } = self;
+        write!(f, "{}", path.display())?;
+        if let Some(line) = line {
+            write!(f, ":{line}")?;
+            if let Some(column) = column {
+                write!(f, ":{column}")?;
+            }
+        }
+        write!(f, ": {key}: {message}")
+    }
+}
+
+impl Manifest {
+    /// Load a manifest file and normalize it into a [`BuildPlan`].
+    ///
+    /// The templates of the sources are rendered with the parameters from `context`.
+    pub fn load<Context: QueryContext>(
+        path: &Path,
+        context: &Context,
+    ) -> Result<BuildPlan, ContextLoadManifestError<Context>> {
+        Manifest::read(path)
+            .map_err(LoadManifestError::Read)?
+            .into_build_plan(path, context)
+    }
+
+    /// Read and parse a manifest file.
+    pub fn read(path: &Path) -> Result<Manifest, ReadManifestError> {
+        let text = fs::read_to_string(path).map_err(|error| ReadManifestError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        Manifest::parse(&text).map_err(|(error, key)| {
+            let (message, line, column) = match error {
+                // Errors of the `Deserialize` implementations have no position.
+                serde_hjson::Error::Syntax(ErrorCode::Custom(message), 0, _) => {
+                    (message, None, None)
+                }
+                serde_hjson::Error::Syntax(ErrorCode::Custom(message), line, column) => {
+                    (message, Some(line), Some(column))
+                }
+                serde_hjson::Error::Syntax(code, line, column) => {
+                    // `ErrorCode` only implements `Debug`, which quotes some of its messages.
+                    let message = format!("{code:?}").trim_matches('"').to_string();
+                    (message, Some(line), Some(column))
+                }
+                error => (error.to_string(), None, None),
+            };
+            let (line, column) = match line {
+                Some(line) => (Some(line), column),
+                None => match Spans::scan(&text).find(&key) {
+                    Some((line, column)) => (Some(line), Some(column)),
+                    None => (None, None),
+                },
+            };
+            ParseManifestError {
+                path: path.to_path_buf(),
+                line,
+                column,
+                key,
+                message,
+            }
+            .pipe(ReadManifestError::Parse)
+        })
+    }
+
+    /// Parse the content of a manifest file.
+    ///
+    /// On failure, return the error alongside the path to the offending key.
+    fn parse(text: &str) -> Result<Manifest, (serde_hjson::Error, String)> {
+        // Parsing into a `Value` first preserves the line and column of syntax errors.
+        // The errors of the `Deserialize` implementations have no position, so they are
+        // located by the path to their key instead.
+        let value: Value = serde_hjson::from_str(text).map_err(|error| (error, ".".to_string()))?;
+        serde_path_to_error::deserialize(value.clone())
+            .map_err(|error| untagged::explain(&value, error))
+    }
+
+    /// Get the directory that contains a manifest file.
+    pub fn dir(manifest_path: &Path) -> PathBuf {
+        match manifest_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    /// Resolve all paths and normalize all sources into a [`BuildPlan`].
+    ///
+    /// `manifest_path` is the path to the file from which the manifest was read. The AUR groups
+    /// are left for [`BuildPlan::resolve_aur`], so that loading needs no network access.
+    pub fn into_build_plan<Context: QueryContext>(
+        self,
+        manifest_path: &Path,
+        context: &Context,
+    ) -> Result<BuildPlan, ContextLoadManifestError<Context>> {
+        let Manifest {
+            container_manager,
+            container_file,
+            base_image,
+            pkgbuild_dir,
+            container_dir,
+            package_dir,
+            repo_name,
+            variables,
+            command_policy: _,
+            aur_url,
+            missing_dependencies,
+            sync_db_dir,
+            official_repositories,
+            signing_key,
+            gpg_home,
+            sources: groups,
+        } = self;
+
+        let manifest_dir = Manifest::dir(manifest_path);
+        let resolve_dir = |dir: Option<String>, default: &str| {
+            manifest_dir.join(dir.as_deref().unwrap_or(default))
+        };
+        let pkgbuild_dir = resolve_dir(pkgbuild_dir, DEFAULT_PKGBUILD_DIR);
+        let container_dir = resolve_dir(container_dir, DEFAULT_CONTAINER_DIR);
+        let package_dir = resolve_dir(package_dir, DEFAULT_PACKAGE_DIR);
+        let sync_db_dir = sync_db_dir.map(|dir| manifest_dir.join(dir));
+        let gpg_home = gpg_home.map(|dir| manifest_dir.join(dir));
+        let official_repositories = official_repositories.unwrap_or_else(|| {
+            DEFAULT_OFFICIAL_REPOSITORIES
+                .iter()
+                .map(|name| name.to_string())
+                .collect()
+        });
+
+        let parsed_groups = groups
+            .iter()
+            .enumerate()
+            .map(|(index, group)| {
+                group
+                    .parse_templates()
+                    .map_err(|invalid| LoadManifestError::InvalidTemplate {
+                        manifest: manifest_path.to_path_buf(),
+                        field: format!("sources[{index}].{}", invalid.field),
+                        template: invalid.template.to_string(),
+                        message: invalid.error.to_string(),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let params = ManifestParams {
+            repo_name: &repo_name,
+            variables: &variables,
+            context,
+        };
+        let mut sources = Vec::new();
+        let mut aur_groups = Vec::new();
+        for group in &parsed_groups {
+            group
+                .normalize(&params, &mut sources, &mut aur_groups)
+                .map_err(|error| LoadManifestError::Template {
+                    manifest: manifest_path.to_path_buf(),
+                    error: Box::new(error),
+                })?;
+        }
+
+        let mut bases = HashSet::new();
+        for source in &mut sources {
+            let base = source.package().base();
+            if !bases.insert(base.to_string()) {
+                return Err(LoadManifestError::DuplicatedBase {
+                    manifest: manifest_path.to_path_buf(),
+                    base: base.to_string(),
+                });
+            }
+            match source {
+                PkgBuildDesc::Local(LocalPkgBuildDesc { dir, .. }) => {
+                    *dir = manifest_dir
+                        .join(&*dir)
+                        .into_os_string()
+                        .into_string()
+                        .map_err(|path| LoadManifestError::NonUtf8Path {
+                            manifest: manifest_path.to_path_buf(),
+                            path: path.into(),
+                        })?;
+                }
+                // A ref that looks like an option could be mistaken for one by `git fetch`.
+                PkgBuildDesc::Git(GitPkgBuildDesc {
+                    package,
+                    git_ref: Some(git_ref),
+                    ..
+                }) if git_ref.starts_with('-') => {
+                    return Err(LoadManifestError::InvalidGitRef {
+                        manifest: manifest_path.to_path_buf(),
+                        base: package.base().to_string(),
+                        git_ref: git_ref.clone(),
+                    });
+                }
+                PkgBuildDesc::Git(_) => {}
+            }
+        }
+
+        Ok(BuildPlan {
+            manifest_path: manifest_path.to_path_buf(),
+            manifest_dir,
+            container_manager,
+            container_file,
+            base_image: base_image.unwrap_or_else(|| DEFAULT_BASE_IMAGE.to_string()),
+            pkgbuild_dir,
+            container_dir,
+            package_dir,
+            repo_name,
+            variables,
+            aur_url: aur_url
+                .as_deref()
+                .unwrap_or(DEFAULT_AUR_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            missing_dependencies,
+            sync_db_dir,
+            official_repositories,
+            signing_key,
+            gpg_home,
+            sources,
+            aur_groups,
+        })
+    }
+}
@@ -0,0 +1,151 @@
+use serde::de::{DeserializeSeed, Deserializer, Error, MapAccess, SeqAccess, Visitor};
+use std::{cell::Cell, collections::HashMap, fmt};
+
+/// Positions of the keys of an HJSON document.
+///
+/// The positions are indexed by the paths that [`serde_path_to_error`] reports, such as
+/// `sources[3].git-url`, so that errors of the `Deserialize` implementations, which have no
+/// position of their own, can be traced back to a line.
+#[derive(Debug, Default)]
+pub(super) struct Spans(HashMap<String, (usize, usize)>);
+
+impl Spans {
+    /// Parse an HJSON document with [`serde_hjson`] for the positions of its keys.
+    ///
+    /// Like [`serde_hjson::from_str`], the document may omit the braces of its root object.
+    pub(super) fn scan(text: &str) -> Self {
+        let text = match text.ends_with(char::is_whitespace) {
+            true => text.to_string(),
+            false => format!("{text}\n"),
+        };
+        let scan = |braceless_root: bool| {
+            let consumed = Cell::new(0);
+            let bytes = text.bytes().inspect(|_| consumed.set(consumed.get() + 1));
+            let mut deserializer = match braceless_root {
+                true => serde_hjson::Deserializer::new_for_root(bytes),
+                false => serde_hjson::Deserializer::new(bytes),
+            };
+            let mut spans = Spans::default();
+            let seed = SpanSeed {
+                path: String::new(),
+                text: &text,
+                consumed: &consumed,
+                spans: &mut spans,
+            };
+            seed.deserialize(&mut deserializer).ok()?;
+            deserializer.end().ok()?;
+            Some(spans)
+        };
+        scan(true).or_else(|| scan(false)).unwrap_or_default()
+    }
+
+    /// Find the line and column (both 1-based) of a key, or of its closest ancestor.
+    pub(super) fn find(&self, mut key: &str) -> Option<(usize, usize)> {
+        loop {
+            if let Some(position) = self.0.get(key) {
+                return Some(*position);
+            }
+            key = &key[..key.rfind(['.', '['])?];
+        }
+    }
+}
+
+/// [`DeserializeSeed`] that records the positions of the keys of a value whose path is `path`.
+struct SpanSeed<'a> {
+    path: String,
+    text: &'a str,
+    /// Number of bytes of `text` that the deserializer has consumed so far.
+    consumed: &'a Cell<usize>,
+    spans: &'a mut Spans,
+}
+
+impl SpanSeed<'_> {
+    /// Create the seed of a nested value.
+    fn child(&mut self, path: String) -> SpanSeed<'_> {
+        SpanSeed {
+            path,
+            text: self.text,
+            consumed: self.consumed,
+            spans: self.spans,
+        }
+    }
+
+    /// Get the line and column of a key that the deserializer has just consumed.
+    ///
+    /// The deserializer stops right after the key, or after the colon that follows it, so the
+    /// key is the last occurrence of its name, or of its name in quotes.
+    fn position_of(&self, key: &str) -> (usize, usize) {
+        let consumed = &self.text[..self.consumed.get().min(self.text.len())];
+        let mut start = consumed.rfind(key).unwrap_or(consumed.len());
+        if consumed[..start].ends_with('"') {
+            start -= 1;
+        }
+        let line_start = consumed[..start].rfind('\n').map_or(0, |index| index + 1);
+        let line = consumed[..line_start].matches('\n').count() + 1;
+        let column = consumed[line_start..start].chars().count() + 1;
+        (line, column)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for SpanSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for SpanSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any HJSON value")
+    }
+
+    fn visit_bool<E: Error>(self, _: bool) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i64<E: Error>(self, _: i64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u64<E: Error>(self, _: u64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_f64<E: Error>(self, _: f64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_str<E: Error>(self, _: &str) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<Seq: SeqAccess<'de>>(mut self, mut seq: Seq) -> Result<(), Seq::Error> {
+        for index in 0.. {
+            let path = format!("{}[{index}]", self.path);
+            if seq.next_element_seed(self.child(path))?.is_none() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_map<Map: MapAccess<'de>>(mut self, mut map: Map) -> Result<(), Map::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            let position = self.position_of(&key);
+            let path = match self.path.as_str() {
+                "" => key,
+                parent => format!("{parent}.{key}"),
+            };
+            self.spans.0.entry(path.clone()).or_insert(position);
+            map.next_value_seed(self.child(path))?;
+        }
+        Ok(())
+    }
+}
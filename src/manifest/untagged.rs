@@ -0,0 +1,228 @@
+use crate::{
+    pkgbuild_desc::{GitPkgBuildDesc, LocalPkgBuildDesc},
+    pkgbuild_group::{
+        git::{GitPkgBuildComplexMember, GitPkgBuildHeader},
+        local::{LocalPkgBuildComplexMember, LocalPkgBuildHeader},
+        AurPkgBuildGroup, GitPkgBuildGroup, LocalPkgBuildGroup,
+    },
+    pkgbuild_name::PkgBuildName,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_hjson::{ErrorCode, Value};
+use serde_path_to_error::Error;
+
+/// Error of deserializing a manifest, with the path to the offending key.
+type PathError = Error<serde_hjson::Error>;
+
+/// Prefix of the message of serde when no variant of an untagged enum matches.
+const UNTAGGED_MESSAGE: &str = "data did not match any variant of untagged enum ";
+
+/// Replace the error of an untagged enum that matches no variant with the error of the
+/// variant that the value most likely meant to be.
+///
+/// Return the error alongside the path to the offending key.
+pub(super) fn explain(root: &Value, error: PathError) -> (serde_hjson::Error, String) {
+    let mut key = error.path().to_string();
+    let mut error = error;
+    loop {
+        let serde_hjson::Error::Syntax(ErrorCode::Custom(message), _, _) = error.inner() else {
+            break;
+        };
+        let Some(name) = message.strip_prefix(UNTAGGED_MESSAGE) else {
+            break;
+        };
+        let Some(value) = find(root, &key) else {
+            break;
+        };
+        let Some(Err(inner)) = closest_variant(name, value) else {
+            break;
+        };
+        key = join(&key, &inner.path().to_string());
+        error = inner;
+    }
+    // Keys that are unknown to a flattened struct are reported at the path of its parent.
+    if let serde_hjson::Error::Syntax(ErrorCode::Custom(message), _, _) = error.inner() {
+        let unknown = message
+            .strip_prefix("unknown field `")
+            .and_then(|rest| rest.split_once('`'))
+            .map(|(unknown, _)| unknown);
+        if let Some(unknown) = unknown {
+            if !key.ends_with(unknown) {
+                key = join(&key, unknown);
+            }
+        }
+    }
+    (error.into_inner(), key)
+}
+
+/// Deserialize a value as the variant of the untagged enum `name` that shares the most keys
+/// with it, the first one on a tie.
+fn closest_variant(name: &str, value: &Value) -> Option<Result<(), PathError>> {
+    let variants = match name {
+        "PkgBuildGroup" => vec![
+            Variant::of::<LocalPkgBuildDesc>(),
+            Variant::of::<GitPkgBuildDesc>(),
+            Variant::of::<LocalPkgBuildGroup>(),
+            Variant::of::<GitPkgBuildGroup>(),
+            Variant::of::<AurPkgBuildGroup>(),
+        ],
+        "PkgBuildDesc" => vec![
+            Variant::of::<LocalPkgBuildDesc>(),
+            Variant::of::<GitPkgBuildDesc>(),
+        ],
+        "GitPkgBuildMember" => vec![Variant::of::<GitPkgBuildComplexMember>()],
+        "LocalPkgBuildMember" => vec![Variant::of::<LocalPkgBuildComplexMember>()],
+        _ => return None,
+    };
+    let shared_keys = |variant: &Variant| {
+        variant
+            .keys
+            .iter()
+            .filter(|key| value.find(key).is_some())
+            .count()
+    };
+    let mut closest = &variants[0];
+    for variant in &variants[1..] {
+        if shared_keys(variant) > shared_keys(closest) {
+            closest = variant;
+        }
+    }
+    Some((closest.deserialize)(value))
+}
+
+/// Struct that is a variant of an untagged enum of the manifest.
+struct Variant {
+    /// Keys of the struct, as serialized.
+    keys: Vec<String>,
+    /// Deserialize a value as the struct.
+    deserialize: fn(&Value) -> Result<(), PathError>,
+}
+
+impl Variant {
+    fn of<Target: Sample>() -> Self {
+        let keys = match serde_json::to_value(Target::sample()) {
+            Ok(serde_json::Value::Object(object)) => {
+                object.into_iter().map(|(key, _)| key).collect()
+            }
+            _ => Vec::new(),
+        };
+        Variant {
+            keys,
+            deserialize: |value| {
+                serde_path_to_error::deserialize::<_, Target>(value.clone()).map(drop)
+            },
+        }
+    }
+}
+
+/// Struct whose keys are known from serializing an instance of it.
+///
+/// Optional fields are serialized as `null`, so the instance has every key of the struct.
+trait Sample: Serialize + DeserializeOwned {
+    fn sample() -> Self;
+}
+
+impl Sample for LocalPkgBuildDesc {
+    fn sample() -> Self {
+        LocalPkgBuildDesc {
+            package: PkgBuildName::single(String::new()),
+            dir: String::new(),
+        }
+    }
+}
+
+impl Sample for GitPkgBuildDesc {
+    fn sample() -> Self {
+        GitPkgBuildDesc {
+            package: PkgBuildName::single(String::new()),
+            git_url: String::new(),
+            git_depth: None,
+            git_ref: None,
+            sub_dir: None,
+        }
+    }
+}
+
+impl Sample for LocalPkgBuildGroup {
+    fn sample() -> Self {
+        LocalPkgBuildGroup {
+            header: LocalPkgBuildHeader {
+                dir_path_template: String::new(),
+            },
+            members: Vec::new(),
+        }
+    }
+}
+
+impl Sample for GitPkgBuildGroup {
+    fn sample() -> Self {
+        GitPkgBuildGroup {
+            header: GitPkgBuildHeader {
+                git_url_template: String::new(),
+                git_depth: None,
+                git_ref_template: None,
+                sub_dir_template: None,
+            },
+            members: Vec::new(),
+        }
+    }
+}
+
+impl Sample for AurPkgBuildGroup {
+    fn sample() -> Self {
+        AurPkgBuildGroup {
+            aur: Vec::new(),
+            git_depth: None,
+        }
+    }
+}
+
+impl Sample for GitPkgBuildComplexMember {
+    fn sample() -> Self {
+        GitPkgBuildComplexMember {
+            package: PkgBuildName::single(String::new()),
+            git_url: None,
+            git_depth: None,
+            git_ref: None,
+            sub_dir: None,
+        }
+    }
+}
+
+impl Sample for LocalPkgBuildComplexMember {
+    fn sample() -> Self {
+        LocalPkgBuildComplexMember {
+            package: PkgBuildName::single(String::new()),
+            dir: None,
+        }
+    }
+}
+
+/// Find the value at a path such as `sources[3].members`.
+fn find<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut value = root;
+    if path == "." {
+        return Some(value);
+    }
+    for segment in path.split('.') {
+        let (key, indices) = segment.split_once('[').unwrap_or((segment, ""));
+        if !key.is_empty() {
+            value = value.find(key)?;
+        }
+        for index in indices.split('[').filter(|index| !index.is_empty()) {
+            let index: usize = index.trim_end_matches(']').parse().ok()?;
+            value = value.as_array()?.get(index)?;
+        }
+    }
+    Some(value)
+}
+
+/// Append the path of a nested error to the path of its parent.
+fn join(parent: &str, child: &str) -> String {
+    match (parent, child) {
+        (parent, ".") => parent.to_string(),
+        (".", child) => child.to_string(),
+        (parent, child) if child.starts_with('[') => format!("{parent}{child}"),
+        (parent, child) => format!("{parent}.{child}"),
+    }
+}
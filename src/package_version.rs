@@ -0,0 +1,145 @@
+use crate::vercmp::{parse_evr, rpmvercmp};
+use derive_more::{Display, Error};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+/// Full version of a package, such as `1:2.0-3`.
+///
+/// The ordering is that of `alpm_pkg_vercmp`, except that a version without a pkgrel is less
+/// than the same version with one, so that the ordering is total. Use
+/// [`PackageVersion::vercmp`] to compare versions like pacman does.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+#[non_exhaustive]
+pub struct PackageVersion {
+    /// The epoch, if any.
+    pub epoch: Option<String>,
+    /// The upstream version.
+    pub pkgver: String,
+    /// The release number of the package, if any.
+    pub pkgrel: Option<String>,
+}
+
+/// Error when parsing a [`PackageVersion`] fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ParsePackageVersionError {
+    #[display("The pkgver of {_0:?} is empty")]
+    EmptyPkgver(#[error(not(source))] String),
+    #[display("The pkgrel of {_0:?} is empty")]
+    EmptyPkgrel(#[error(not(source))] String),
+}
+
+impl PackageVersion {
+    /// Create a version from its parts.
+    pub fn new(epoch: Option<String>, pkgver: String, pkgrel: Option<String>) -> Self {
+        PackageVersion {
+            epoch,
+            pkgver,
+            pkgrel,
+        }
+    }
+
+    /// Parse a version of the form `[epoch:]pkgver[-pkgrel]`, split like libalpm does.
+    pub fn parse(text: &str) -> Result<Self, ParsePackageVersionError> {
+        let (epoch, pkgver, pkgrel) = parse_evr(text);
+        if pkgver.is_empty() {
+            return Err(ParsePackageVersionError::EmptyPkgver(text.to_string()));
+        }
+        if pkgrel == Some("") {
+            return Err(ParsePackageVersionError::EmptyPkgrel(text.to_string()));
+        }
+        let has_epoch = text
+            .trim_start_matches(|char: char| char.is_ascii_digit())
+            .starts_with(':');
+        Ok(PackageVersion {
+            epoch: has_epoch.then(|| epoch.to_string()),
+            pkgver: pkgver.to_string(),
+            pkgrel: pkgrel.map(str::to_string),
+        })
+    }
+
+    /// Get the epoch, `0` if there is none.
+    pub fn epoch_or_zero(&self) -> &str {
+        self.epoch.as_deref().unwrap_or("0")
+    }
+
+    /// Compare two versions like `alpm_pkg_vercmp`.
+    ///
+    /// A missing epoch is `0`, and the pkgrels are only compared when both versions have one.
+    /// As a consequence, `1.0` is equivalent to both `1.0-1` and `1.0-2`, which differ from each
+    /// other, so this is not a total order.
+    pub fn vercmp(&self, other: &Self) -> Ordering {
+        self.cmp_with(other, |_, _| Ordering::Equal)
+    }
+
+    /// Compare the epochs, then the pkgvers, then the pkgrels, with `missing_pkgrel` deciding
+    /// when either pkgrel is missing.
+    fn cmp_with(
+        &self,
+        other: &Self,
+        missing_pkgrel: impl FnOnce(Option<&str>, Option<&str>) -> Ordering,
+    ) -> Ordering {
+        rpmvercmp(self.epoch_or_zero(), other.epoch_or_zero())
+            .then_with(|| rpmvercmp(&self.pkgver, &other.pkgver))
+            .then_with(|| match (self.pkgrel.as_deref(), other.pkgrel.as_deref()) {
+                (Some(pkgrel), Some(other)) => rpmvercmp(pkgrel, other),
+                (pkgrel, other) => missing_pkgrel(pkgrel, other),
+            })
+    }
+}
+
+impl fmt::Display for PackageVersion {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(epoch) = &self.epoch {
+            write!(formatter, "{epoch}:")?;
+        }
+        write!(formatter, "{}", self.pkgver)?;
+        if let Some(pkgrel) = &self.pkgrel {
+            write!(formatter, "-{pkgrel}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for PackageVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_with(other, |pkgrel, other| {
+            pkgrel.is_some().cmp(&other.is_some())
+        })
+    }
+}
+
+impl PartialOrd for PackageVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for PackageVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PackageVersion {}
+
+impl FromStr for PackageVersion {
+    type Err = ParsePackageVersionError;
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        PackageVersion::parse(text)
+    }
+}
+
+impl TryFrom<String> for PackageVersion {
+    type Error = ParsePackageVersionError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        PackageVersion::parse(&value)
+    }
+}
+
+impl From<PackageVersion> for String {
+    fn from(value: PackageVersion) -> Self {
+        value.to_string()
+    }
+}
@@ -41,3 +41,13 @@ pub struct GitPkgBuildDesc {
     /// Path to the sub directory that contains the PKGBUILD, relative to the git repo root.
     pub sub_dir: Option<String>,
 }
+
+impl PkgBuildDesc {
+    /// Name(s) and base of the packages being built by the PKGBUILD.
+    pub fn package(&self) -> &PkgBuildName {
+        match self {
+            PkgBuildDesc::Local(desc) => &desc.package,
+            PkgBuildDesc::Git(desc) => &desc.package,
+        }
+    }
+}
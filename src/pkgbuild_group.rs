@@ -1,10 +1,24 @@
+pub mod aur;
 pub mod git;
 pub mod local;
 
+pub use aur::AurPkgBuildGroup;
 pub use git::GitPkgBuildGroup;
 pub use local::LocalPkgBuildGroup;
 
-use crate::pkgbuild_desc::PkgBuildDesc;
+use git::GitHeaderTemplates;
+use local::LocalHeaderTemplates;
+
+use crate::{
+    pkgbuild_desc::PkgBuildDesc,
+    pkgbuild_name::PkgBuildName,
+    template::{
+        params::{QueryCmd, QueryEnv, QueryFile},
+        parse::{ParseTemplateError, ParsedTemplate},
+        ParseTemplate, PkgbuildParams, QueryManifestContext, RenderTemplateError,
+    },
+};
+use derive_more::{Display, Error};
 use serde::{Deserialize, Serialize};
 
 /// Grouping of multiple PKGBUILD directories with similar properties.
@@ -18,4 +32,151 @@ pub enum PkgBuildGroup {
     Local(LocalPkgBuildGroup),
     /// Grouping of git directories.
     Git(GitPkgBuildGroup),
+    /// Grouping of packages from the AUR.
+    Aur(AurPkgBuildGroup),
+}
+
+/// Error when rendering a template of a group header for a member fails.
+#[derive(Debug, Display, Error)]
+#[display("Failed to render {field} {template:?} for {member:?}: {error}")]
+#[non_exhaustive]
+pub struct ApplyTemplateError<EnvError, CommandError, FileError> {
+    /// The `pkgbase` of the member.
+    pub member: String,
+    /// Name of the templated field, such as `git-url-template`.
+    pub field: &'static str,
+    /// The template that failed to render.
+    pub template: String,
+    /// Cause of the failure.
+    #[error(source)]
+    pub error: RenderTemplateError<EnvError, CommandError, FileError>,
+}
+
+/// [`ApplyTemplateError`] of a [query context](QueryManifestContext).
+pub type ContextApplyTemplateError<Context> = ApplyTemplateError<
+    <Context as QueryEnv>::Error,
+    <Context as QueryCmd>::Error,
+    <Context as QueryFile>::Error,
+>;
+
+/// A [`PkgBuildGroup`] whose header templates have been parsed, once ahead of being rendered
+/// for each member.
+#[derive(Debug, Clone)]
+pub(crate) enum ParsedPkgBuildGroup<'a> {
+    Single(&'a PkgBuildDesc),
+    Local(&'a LocalPkgBuildGroup, LocalHeaderTemplates<'a>),
+    Git(&'a GitPkgBuildGroup, GitHeaderTemplates<'a>),
+    Aur(&'a AurPkgBuildGroup),
+}
+
+/// Parsed template of a field of a group header.
+#[derive(Debug, Clone)]
+pub(crate) struct HeaderTemplate<'a> {
+    field: &'static str,
+    template: &'a str,
+    parsed: ParsedTemplate<'a>,
+}
+
+/// Error when a template of a group header is invalid.
+#[derive(Debug)]
+pub(crate) struct InvalidHeaderTemplate<'a> {
+    /// Name of the templated field, such as `git-url-template`.
+    pub field: &'static str,
+    /// The invalid template.
+    pub template: &'a str,
+    /// Cause of the failure.
+    pub error: ParseTemplateError<'a>,
+}
+
+impl PkgBuildGroup {
+    /// Parse the templates of the group header.
+    pub(crate) fn parse_templates(
+        &self,
+    ) -> Result<ParsedPkgBuildGroup<'_>, InvalidHeaderTemplate<'_>> {
+        Ok(match self {
+            PkgBuildGroup::Single(desc) => ParsedPkgBuildGroup::Single(desc),
+            PkgBuildGroup::Local(group) => {
+                ParsedPkgBuildGroup::Local(group, group.header.parse_templates()?)
+            }
+            PkgBuildGroup::Git(group) => {
+                ParsedPkgBuildGroup::Git(group, group.header.parse_templates()?)
+            }
+            PkgBuildGroup::Aur(group) => ParsedPkgBuildGroup::Aur(group),
+        })
+    }
+}
+
+impl ParsedPkgBuildGroup<'_> {
+    /// Append the group to a list as normalized descriptions.
+    ///
+    /// AUR groups, whose pkgbases are yet to be resolved, are appended to `aur_target` instead.
+    pub(crate) fn normalize<Context: QueryManifestContext>(
+        &self,
+        context: &Context,
+        target: &mut Vec<PkgBuildDesc>,
+        aur_target: &mut Vec<AurPkgBuildGroup>,
+    ) -> Result<(), ContextApplyTemplateError<Context>> {
+        match self {
+            ParsedPkgBuildGroup::Single(desc) => target.push((*desc).clone()),
+            ParsedPkgBuildGroup::Local(group, templates) => {
+                group.normalize(templates, context, target)?
+            }
+            ParsedPkgBuildGroup::Git(group, templates) => {
+                group.normalize(templates, context, target)?
+            }
+            ParsedPkgBuildGroup::Aur(group) => aur_target.push((*group).clone()),
+        }
+        Ok(())
+    }
+}
+
+impl<'a> HeaderTemplate<'a> {
+    /// Parse the template of a field.
+    fn parse(field: &'static str, template: &'a str) -> Result<Self, InvalidHeaderTemplate<'a>> {
+        match template.parse_template() {
+            Ok(parsed) => Ok(HeaderTemplate {
+                field,
+                template,
+                parsed,
+            }),
+            Err(error) => Err(InvalidHeaderTemplate {
+                field,
+                template,
+                error,
+            }),
+        }
+    }
+
+    /// Parse the template of an optional field.
+    fn parse_optional(
+        field: &'static str,
+        template: &'a Option<String>,
+    ) -> Result<Option<Self>, InvalidHeaderTemplate<'a>> {
+        template
+            .as_deref()
+            .map(|template| HeaderTemplate::parse(field, template))
+            .transpose()
+    }
+
+    /// Render the template for the member at `index` of its group.
+    fn render<Context: QueryManifestContext>(
+        &self,
+        package: &PkgBuildName,
+        index: usize,
+        context: &Context,
+    ) -> Result<String, ContextApplyTemplateError<Context>> {
+        let params = PkgbuildParams {
+            package,
+            index,
+            context,
+        };
+        self.parsed
+            .render(&params)
+            .map_err(|error| ApplyTemplateError {
+                member: package.base().to_string(),
+                field: self.field,
+                template: self.template.to_string(),
+                error,
+            })
+    }
 }
@@ -0,0 +1,44 @@
+use crate::{aur::AurClient, pkgbuild_desc::GitPkgBuildDesc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Grouping of packages from the AUR.
+///
+/// The pkgbases of the packages are unknown until the `fetch` subcommand looks them up and
+/// records them in the [lockfile](crate::lockfile::Lockfile::aur).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[non_exhaustive]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AurPkgBuildGroup {
+    /// Names of the packages to build.
+    pub aur: Vec<String>,
+    /// Historical depth to clone.
+    pub git_depth: Option<u64>,
+}
+
+impl AurPkgBuildGroup {
+    /// Create git descriptions of the packages from their pkgbases, indexed by package name.
+    ///
+    /// Packages that share the same pkgbase are merged into a single description. On failure,
+    /// return the names of the packages whose pkgbases are unknown.
+    pub fn resolve(
+        &self,
+        pkgbases: &BTreeMap<String, String>,
+        aur: &AurClient,
+    ) -> Result<Vec<GitPkgBuildDesc>, Vec<String>> {
+        let unknown: Vec<_> = self
+            .aur
+            .iter()
+            .filter(|name| !pkgbases.contains_key(*name))
+            .cloned()
+            .collect();
+        if !unknown.is_empty() {
+            return Err(unknown);
+        }
+        let packages = self
+            .aur
+            .iter()
+            .map(|name| (name.as_str(), pkgbases[name].as_str()));
+        Ok(aur.git_descs(packages, self.git_depth))
+    }
+}
@@ -1,9 +1,12 @@
+use super::{ContextApplyTemplateError, HeaderTemplate, InvalidHeaderTemplate};
 use crate::{
     pkgbuild_desc::{GitPkgBuildDesc, PkgBuildDesc},
     pkgbuild_name::PkgBuildName,
+    template::QueryManifestContext,
 };
 use pipe_trait::Pipe;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 /// Grouping of multiple PKGBUILD git repositories with similar properties.
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -61,41 +64,90 @@ pub struct GitPkgBuildComplexMember {
     pub sub_dir: Option<String>,
 }
 
+/// Parsed templates of a [`GitPkgBuildHeader`].
+#[derive(Debug, Clone)]
+pub(crate) struct GitHeaderTemplates<'a> {
+    git_url: HeaderTemplate<'a>,
+    git_ref: Option<HeaderTemplate<'a>>,
+    sub_dir: Option<HeaderTemplate<'a>>,
+}
+
 impl GitPkgBuildGroup {
     /// Append this group to a list as normalized descriptions.
-    #[allow(unused)] // TODO: remove this
-    pub(crate) fn normalize(self, target: &mut Vec<PkgBuildDesc>) {
+    pub(crate) fn normalize<Context: QueryManifestContext>(
+        &self,
+        templates: &GitHeaderTemplates<'_>,
+        context: &Context,
+        target: &mut Vec<PkgBuildDesc>,
+    ) -> Result<(), ContextApplyTemplateError<Context>> {
         let GitPkgBuildGroup {
             header,
             members: packages,
         } = self;
-        for member in packages {
-            let desc = header.apply(member).pipe(PkgBuildDesc::Git);
+        for (index, member) in packages.iter().enumerate() {
+            let desc = header
+                .apply(member, index, templates, context)?
+                .pipe(PkgBuildDesc::Git);
             target.push(desc);
         }
+        Ok(())
     }
 }
 
 impl GitPkgBuildHeader {
-    /// Apply this header to a member.
-    fn apply(&self, member: GitPkgBuildMember) -> GitPkgBuildDesc {
+    /// Parse the templates of this header.
+    pub(crate) fn parse_templates(
+        &self,
+    ) -> Result<GitHeaderTemplates<'_>, InvalidHeaderTemplate<'_>> {
+        Ok(GitHeaderTemplates {
+            git_url: HeaderTemplate::parse("git-url-template", &self.git_url_template)?,
+            git_ref: HeaderTemplate::parse_optional("git-ref-template", &self.git_ref_template)?,
+            sub_dir: HeaderTemplate::parse_optional("sub-dir-template", &self.sub_dir_template)?,
+        })
+    }
+
+    /// Apply this header to the member at `index` of the group.
+    fn apply<Context: QueryManifestContext>(
+        &self,
+        member: &GitPkgBuildMember,
+        index: usize,
+        templates: &GitHeaderTemplates<'_>,
+        context: &Context,
+    ) -> Result<GitPkgBuildDesc, ContextApplyTemplateError<Context>> {
         let member = member.normalize();
-        GitPkgBuildDesc {
-            package: member.package,
-            git_url: member.git_url.unwrap_or_else(|| todo!()), // TODO: apply member.name.base to self.git_url_template
+        let apply = |template: &HeaderTemplate| template.render(&member.package, index, context);
+        let apply_optional =
+            |template: &Option<HeaderTemplate>| template.as_ref().map(apply).transpose();
+        let git_url = match &member.git_url {
+            Some(git_url) => git_url.clone(),
+            None => apply(&templates.git_url)?,
+        };
+        let git_ref = match &member.git_ref {
+            Some(git_ref) => Some(git_ref.clone()),
+            None => apply_optional(&templates.git_ref)?,
+        };
+        let sub_dir = match &member.sub_dir {
+            Some(sub_dir) => Some(sub_dir.clone()),
+            None => apply_optional(&templates.sub_dir)?,
+        };
+        Ok(GitPkgBuildDesc {
+            package: member.package.clone(),
+            git_url,
             git_depth: member.git_depth.or(self.git_depth),
-            git_ref: member.git_ref.or(None), // TODO: apply member.name.base to self.git_ref_template
-            sub_dir: member.sub_dir.or(None), // TODO: apply member.name.base to self.sub_dir_template
-        }
+            git_ref,
+            sub_dir,
+        })
     }
 }
 
 impl GitPkgBuildMember {
     /// Normalize all variants of this member into a complex spec.
-    fn normalize(self) -> GitPkgBuildComplexMember {
+    fn normalize(&self) -> Cow<'_, GitPkgBuildComplexMember> {
         match self {
-            Self::SimpleName(name) => GitPkgBuildComplexMember::with_single_name(name),
-            Self::ComplexSpec(spec) => spec,
+            Self::SimpleName(name) => {
+                Cow::Owned(GitPkgBuildComplexMember::with_single_name(name.clone()))
+            }
+            Self::ComplexSpec(spec) => Cow::Borrowed(spec),
         }
     }
 }
@@ -1,9 +1,12 @@
+use super::{ContextApplyTemplateError, HeaderTemplate, InvalidHeaderTemplate};
 use crate::{
     pkgbuild_desc::{LocalPkgBuildDesc, PkgBuildDesc},
     pkgbuild_name::PkgBuildName,
+    template::QueryManifestContext,
 };
 use pipe_trait::Pipe;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 /// Grouping of multiple local PKGBUILD directories with similar properties.
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -37,6 +40,7 @@ pub enum LocalPkgBuildMember {
     ComplexSpec(LocalPkgBuildComplexMember),
 }
 
+/// Complex specification of a [`LocalPkgBuildMember`] with potential overrides.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[non_exhaustive]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
@@ -44,33 +48,86 @@ pub struct LocalPkgBuildComplexMember {
     /// Name(s) and base of the packages being built by the PKGBUILD.
     #[serde(flatten)]
     pub package: PkgBuildName,
+    /// Override the path to the directory, relative to the manifest file.
+    pub dir: Option<String>,
+}
+
+/// Parsed templates of a [`LocalPkgBuildHeader`].
+#[derive(Debug, Clone)]
+pub(crate) struct LocalHeaderTemplates<'a> {
+    dir_path: HeaderTemplate<'a>,
 }
 
 impl LocalPkgBuildGroup {
     /// Append this group to a list as normalized descriptions.
-    #[allow(unused)] // TODO: remove this
-    pub(crate) fn normalize(self, target: &mut Vec<PkgBuildDesc>) {
+    pub(crate) fn normalize<Context: QueryManifestContext>(
+        &self,
+        templates: &LocalHeaderTemplates<'_>,
+        context: &Context,
+        target: &mut Vec<PkgBuildDesc>,
+    ) -> Result<(), ContextApplyTemplateError<Context>> {
         let LocalPkgBuildGroup {
             header,
             members: packages,
         } = self;
-        for member in packages {
-            let desc = header.apply(member).pipe(PkgBuildDesc::Local);
+        for (index, member) in packages.iter().enumerate() {
+            let desc = header
+                .apply(member, index, templates, context)?
+                .pipe(PkgBuildDesc::Local);
             target.push(desc);
         }
+        Ok(())
     }
 }
 
 impl LocalPkgBuildHeader {
-    /// Apply this header to a member.
-    fn apply(&self, member: LocalPkgBuildMember) -> LocalPkgBuildDesc {
-        let package = match member {
-            LocalPkgBuildMember::SimpleName(name) => PkgBuildName::single(name),
-            LocalPkgBuildMember::ComplexSpec(LocalPkgBuildComplexMember { package: name }) => name,
+    /// Parse the templates of this header.
+    pub(crate) fn parse_templates(
+        &self,
+    ) -> Result<LocalHeaderTemplates<'_>, InvalidHeaderTemplate<'_>> {
+        Ok(LocalHeaderTemplates {
+            dir_path: HeaderTemplate::parse("dir-path-template", &self.dir_path_template)?,
+        })
+    }
+
+    /// Apply this header to the member at `index` of the group.
+    fn apply<Context: QueryManifestContext>(
+        &self,
+        member: &LocalPkgBuildMember,
+        index: usize,
+        templates: &LocalHeaderTemplates<'_>,
+        context: &Context,
+    ) -> Result<LocalPkgBuildDesc, ContextApplyTemplateError<Context>> {
+        let member = member.normalize();
+        let dir = match &member.dir {
+            Some(dir) => dir.clone(),
+            None => templates.dir_path.render(&member.package, index, context)?,
         };
-        LocalPkgBuildDesc {
-            package,
-            dir: "TODO".to_string(), // TODO: apply package.base to self.dir_path_template
+        Ok(LocalPkgBuildDesc {
+            package: member.package.clone(),
+            dir,
+        })
+    }
+}
+
+impl LocalPkgBuildMember {
+    /// Normalize all variants of this member into a complex spec.
+    fn normalize(&self) -> Cow<'_, LocalPkgBuildComplexMember> {
+        match self {
+            Self::SimpleName(name) => {
+                Cow::Owned(LocalPkgBuildComplexMember::with_single_name(name.clone()))
+            }
+            Self::ComplexSpec(spec) => Cow::Borrowed(spec),
+        }
+    }
+}
+
+impl LocalPkgBuildComplexMember {
+    /// Create a complex spec with only a name.
+    fn with_single_name(name: String) -> Self {
+        LocalPkgBuildComplexMember {
+            package: PkgBuildName::single(name),
+            dir: None,
         }
     }
 }
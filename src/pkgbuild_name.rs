@@ -1,7 +1,13 @@
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{Error, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use std::fmt;
 
 /// Name(s) and base of the packages being built by a PKGBUILD.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+///
+/// It is deserialized from either `name`, or `base` and `names`.
+#[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
 pub enum PkgBuildName {
     /// The PKGBUILD builds only a single package.
@@ -20,6 +26,33 @@ impl PkgBuildName {
     pub fn split(base: String, names: Vec<String>) -> Self {
         PkgBuildName::Split(PkgBuildSplitName { base, names })
     }
+
+    /// Get the `pkgbase` of the PKGBUILD.
+    ///
+    /// A single-package PKGBUILD has its only package name as its base.
+    pub fn base(&self) -> &str {
+        match self {
+            PkgBuildName::Single(PkgBuildSingleName { name }) => name,
+            PkgBuildName::Split(PkgBuildSplitName { base, .. }) => base,
+        }
+    }
+
+    /// Get the names of all the packages built by the PKGBUILD.
+    pub fn names(&self) -> &[String] {
+        match self {
+            PkgBuildName::Single(PkgBuildSingleName { name }) => std::slice::from_ref(name),
+            PkgBuildName::Split(PkgBuildSplitName { names, .. }) => names,
+        }
+    }
+
+    /// Get the name of the first package built by the PKGBUILD.
+    ///
+    /// Fall back to the [base](PkgBuildName::base) if there are no names.
+    pub fn name(&self) -> &str {
+        self.names()
+            .first()
+            .map_or_else(|| self.base(), String::as_str)
+    }
 }
 
 /// Name of the only package being built by a single-package PKGBUILD.
@@ -39,3 +72,52 @@ pub struct PkgBuildSplitName {
     /// Names of the packages.
     pub names: Vec<String>,
 }
+
+impl<'de> Deserialize<'de> for PkgBuildName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Asking for a struct lets a flattening parent take only these keys, and report the
+        // others as unknown.
+        deserializer.deserialize_struct("PkgBuildName", PKGBUILD_NAME_FIELDS, PkgBuildNameVisitor)
+    }
+}
+
+/// Keys of all the variants of [`PkgBuildName`].
+const PKGBUILD_NAME_FIELDS: &[&str] = &["name", "base", "names"];
+
+/// [`Visitor`] that tells the variants of [`PkgBuildName`] apart by their keys.
+struct PkgBuildNameVisitor;
+
+impl<'de> Visitor<'de> for PkgBuildNameVisitor {
+    type Value = PkgBuildName;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("either `name`, or `base` and `names`")
+    }
+
+    fn visit_map<Map: MapAccess<'de>>(self, mut map: Map) -> Result<Self::Value, Map::Error> {
+        let mut name = None;
+        let mut base = None;
+        let mut names = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "name" if name.is_none() => name = Some(map.next_value()?),
+                "base" if base.is_none() => base = Some(map.next_value()?),
+                "names" if names.is_none() => names = Some(map.next_value()?),
+                "name" | "base" | "names" => {
+                    return Err(Map::Error::custom(format_args!("duplicate field `{key}`")))
+                }
+                _ => return Err(Map::Error::custom(format_args!("unknown field `{key}`"))),
+            }
+        }
+        match (name, base, names) {
+            (Some(name), None, None) => Ok(PkgBuildName::single(name)),
+            (None, Some(base), Some(names)) => Ok(PkgBuildName::split(base, names)),
+            (Some(_), _, _) => Err(Map::Error::custom(
+                "`name` cannot be used with `base` or `names`",
+            )),
+            (None, Some(_), None) => Err(Map::Error::missing_field("names")),
+            (None, None, Some(_)) => Err(Map::Error::missing_field("base")),
+            (None, None, None) => Err(Map::Error::missing_field("name")),
+        }
+    }
+}
@@ -0,0 +1,381 @@
+mod archive;
+mod package;
+
+pub use package::{Package, PkgInfo};
+
+use crate::repo_name::RepoName;
+use arch_pkg_db::{text::archive::LoadTarError, TextCollection};
+use arch_pkg_text::desc::{ForgetfulQuerier, Query};
+use archive::{compress, decompress, read_files};
+use derive_more::{Display, Error};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Entries of a pacman repository database, such as `repo.db.tar.gz` and `repo.files.tar.gz`.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RepoDb {
+    /// Entries by the name of their directory in the archive, such as `foo-1.0-1`.
+    pub entries: BTreeMap<String, DbEntry>,
+}
+
+/// Entry of a package in a [`RepoDb`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct DbEntry {
+    /// Name of the package.
+    pub name: String,
+    /// Full version of the package, such as `1:2.0-3`.
+    pub version: String,
+    /// Name of the package archive file.
+    pub file_name: String,
+    /// Content of the `desc` file.
+    pub desc: String,
+    /// Content of the `files` file, if known.
+    pub files: Option<String>,
+}
+
+/// Error when reading or writing a [`RepoDb`] or reading a [`Package`] fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum RepoDbError {
+    #[display("Failed to read {}: {error}", path.display())]
+    Read {
+        path: PathBuf,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("Failed to load {}: {error}", path.display())]
+    Archive {
+        path: PathBuf,
+        #[error(source)]
+        error: LoadTarError,
+    },
+    #[display("{}: The package has no .PKGINFO", path.display())]
+    MissingPkgInfo {
+        #[error(not(source))]
+        path: PathBuf,
+    },
+    #[display("{}: The .PKGINFO has no {field}", path.display())]
+    MissingField {
+        path: PathBuf,
+        #[error(not(source))]
+        field: &'static str,
+    },
+    #[display("Failed to write {}: {error}", path.display())]
+    Write {
+        path: PathBuf,
+        #[error(source)]
+        error: io::Error,
+    },
+}
+
+impl RepoDb {
+    /// Get the path to the database archive of `repo_name` in `dir`.
+    pub fn db_path(dir: &Path, repo_name: &RepoName) -> PathBuf {
+        dir.join(format!("{repo_name}.db.tar.gz"))
+    }
+
+    /// Get the path to the files database archive of `repo_name` in `dir`.
+    pub fn files_path(dir: &Path, repo_name: &RepoName) -> PathBuf {
+        dir.join(format!("{repo_name}.files.tar.gz"))
+    }
+
+    /// Get the paths to the database archives of `repo_name` in `dir`, each with the path to
+    /// the link that points to it, such as `repo.db`.
+    pub fn links(dir: &Path, repo_name: &RepoName) -> [(PathBuf, PathBuf); 2] {
+        [
+            (
+                RepoDb::db_path(dir, repo_name),
+                dir.join(format!("{repo_name}.db")),
+            ),
+            (
+                RepoDb::files_path(dir, repo_name),
+                dir.join(format!("{repo_name}.files")),
+            ),
+        ]
+    }
+
+    /// Read the database of `repo_name` in `dir`, or create an empty one if there is none.
+    ///
+    /// The files database is preferred because it also has the file lists.
+    pub fn read(dir: &Path, repo_name: &RepoName) -> Result<Self, RepoDbError> {
+        let files_path = RepoDb::files_path(dir, repo_name);
+        let db_path = RepoDb::db_path(dir, repo_name);
+        let (path, with_files) = match (files_path.exists(), db_path.exists()) {
+            (true, _) => (files_path, true),
+            (false, true) => (db_path, false),
+            (false, false) => return Ok(RepoDb::default()),
+        };
+        let read_error = |error| RepoDbError::Read {
+            path: path.clone(),
+            error,
+        };
+        let bytes = fs::read(&path).map_err(read_error)?;
+        // An empty tar archive has no magic bytes for `TextCollection::from_archive` to detect.
+        let tar = decompress(&bytes).map_err(read_error)?;
+        let descs =
+            TextCollection::from_tar(tar.as_slice()).map_err(|error| RepoDbError::Archive {
+                path: path.clone(),
+                error,
+            })?;
+        let mut files = match with_files {
+            true => read_files(&tar).map_err(read_error)?,
+            false => BTreeMap::new(),
+        };
+
+        let entries = descs
+            .iter()
+            .map(|desc| {
+                let querier = ForgetfulQuerier::new(desc.as_str());
+                let name = querier.name().map_or("", |name| name.as_str());
+                let version = querier.version().map_or("", |version| version.as_str());
+                let dir_name = format!("{name}-{version}");
+                let entry = DbEntry {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    file_name: querier
+                        .file_name()
+                        .map_or("", |file_name| file_name.as_str())
+                        .to_string(),
+                    files: files.remove(&dir_name),
+                    desc: desc.to_string(),
+                };
+                (dir_name, entry)
+            })
+            .collect();
+        Ok(RepoDb { entries })
+    }
+
+    /// Find the entry of a package by name.
+    pub fn get(&self, name: &str) -> Option<&DbEntry> {
+        self.entries.values().find(|entry| entry.name == name)
+    }
+
+    /// Add a package, replacing any other version of it.
+    ///
+    /// Return the name of the directory of the replaced entry, if any.
+    pub fn add(&mut self, package: &Package) -> Option<String> {
+        let replaced = self
+            .entries
+            .iter()
+            .find(|(_, entry)| entry.name == package.name())
+            .map(|(dir_name, _)| dir_name.clone());
+        if let Some(dir_name) = &replaced {
+            self.entries.remove(dir_name);
+        }
+        let dir_name = format!("{}-{}", package.name(), package.version());
+        let entry = DbEntry {
+            name: package.name().to_string(),
+            version: package.version().to_string(),
+            file_name: package.file_name.clone(),
+            desc: package.desc(),
+            files: Some(package.files()),
+        };
+        self.entries.insert(dir_name, entry);
+        replaced
+    }
+
+    /// Write the database and the files database of `repo_name` into `dir`, alongside the
+    /// `.db` and `.files` links that pacman downloads.
+    ///
+    /// The links are symbolic links where the platform allows, and copies elsewhere.
+    pub fn write(&self, dir: &Path, repo_name: &RepoName) -> Result<(), RepoDbError> {
+        let [db, files] = RepoDb::links(dir, repo_name);
+        for ((path, link), with_files) in [(db, false), (files, true)] {
+            let write_error = |path: &Path| {
+                let path = path.to_path_buf();
+                move |error| RepoDbError::Write { path, error }
+            };
+            let archive = self.archive(with_files).map_err(write_error(&path))?;
+            let temporary = path.with_extension("gz.tmp");
+            fs::write(&temporary, archive).map_err(write_error(&temporary))?;
+            fs::rename(&temporary, &path).map_err(write_error(&path))?;
+
+            if link.symlink_metadata().is_ok() {
+                fs::remove_file(&link).map_err(write_error(&link))?;
+            }
+            link_file(&path, &link).map_err(write_error(&link))?;
+        }
+        Ok(())
+    }
+
+    /// Create the compressed tar archive of the database.
+    fn archive(&self, with_files: bool) -> io::Result<Vec<u8>> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (dir_name, entry) in &self.entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Directory);
+            header.set_mode(0o755);
+            header.set_size(0);
+            builder.append_data(&mut header, format!("{dir_name}/"), io::empty())?;
+
+            let mut files = vec![("desc", &entry.desc)];
+            if with_files {
+                if let Some(content) = &entry.files {
+                    files.push(("files", content));
+                }
+            }
+            for (file_name, content) in files {
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_mode(0o644);
+                header.set_size(content.len() as u64);
+                let path = format!("{dir_name}/{file_name}");
+                builder.append_data(&mut header, path, content.as_bytes())?;
+            }
+        }
+        compress(&builder.into_inner()?)
+    }
+}
+
+/// Make `link` point to the file at `path` in the same directory with a relative symbolic link.
+#[cfg(unix)]
+pub(crate) fn link_file(path: &Path, link: &Path) -> io::Result<()> {
+    let target = path.file_name().expect("the path ends with a file name");
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Copy the file at `path` to `link`, since creating symbolic links may require privileges on
+/// this platform.
+#[cfg(not(unix))]
+pub(crate) fn link_file(path: &Path, link: &Path) -> io::Result<()> {
+    fs::copy(path, link).map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sign::Signer;
+    use std::{env, process};
+
+    /// Write an uncompressed package archive of `foo` at `version` into `dir`, with a signature.
+    fn write_package(dir: &Path, version: &str) -> PathBuf {
+        let pkginfo = format!(
+            "# Generated by makepkg\npkgname = foo\npkgbase = foo-base\npkgver = {version}\n\
+             pkgdesc = Foo = bar\nurl = https://example.com\nbuilddate = 1700000000\n\
+             packager = Someone <someone@example.com>\nsize = 4096\narch = any\n\
+             license = MIT\nlicense = Apache-2.0\nprovides = libfoo.so=1-64\n\
+             depend = glibc\ndepend = bar>=1.0\nmakedepend = cmake\n",
+        );
+        // Directories have no content.
+        let entries = [
+            (".PKGINFO", Some(pkginfo.as_str())),
+            (".MTREE", Some("")),
+            ("usr", None),
+            ("usr/bin", None),
+            ("usr/bin/foo", Some("#!/bin/sh\n")),
+        ];
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(match content {
+                Some(_) => tar::EntryType::Regular,
+                None => tar::EntryType::Directory,
+            });
+            let content = content.unwrap_or_default();
+            header.set_mode(0o644);
+            header.set_mtime(0);
+            header.set_size(content.len() as u64);
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        let path = dir.join(format!("foo-{version}-any.pkg.tar"));
+        fs::write(&path, builder.into_inner().unwrap()).unwrap();
+        fs::write(Signer::signature_path(&path), "sig").unwrap();
+        path
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = env::temp_dir().join(format!(
+            "pacman-repo-builder-repo-db-round-trip-{}",
+            process::id(),
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let repo_name = RepoName::try_from_string("repo".to_string()).unwrap();
+
+        let old = Package::read(&write_package(&dir, "1.0-1")).unwrap();
+        // Checksums of `md5sum` and `sha256sum`, signature of `base64`.
+        let desc = "\
+%FILENAME%\nfoo-1.0-1-any.pkg.tar\n\n\
+%NAME%\nfoo\n\n\
+%BASE%\nfoo-base\n\n\
+%VERSION%\n1.0-1\n\n\
+%DESC%\nFoo = bar\n\n\
+%CSIZE%\n4608\n\n\
+%ISIZE%\n4096\n\n\
+%MD5SUM%\na93bfde32a7fa5ba356b18073fcc6dd9\n\n\
+%SHA256SUM%\nd78214e45459b63ef82e9cd197b9282e196c01bfb9bc17b26917af90eed93d05\n\n\
+%PGPSIG%\nc2ln\n\n\
+%URL%\nhttps://example.com\n\n\
+%LICENSE%\nMIT\nApache-2.0\n\n\
+%ARCH%\nany\n\n\
+%BUILDDATE%\n1700000000\n\n\
+%PACKAGER%\nSomeone <someone@example.com>\n\n\
+%PROVIDES%\nlibfoo.so=1-64\n\n\
+%DEPENDS%\nglibc\nbar>=1.0\n\n\
+%MAKEDEPENDS%\ncmake\n\n";
+        assert_eq!(old.desc(), desc);
+        assert_eq!(old.files(), "%FILES%\nusr/\nusr/bin/\nusr/bin/foo\n");
+
+        let mut db = RepoDb::default();
+        assert_eq!(db.add(&old), None);
+        let new = Package::read(&write_package(&dir, "1:1.1-1")).unwrap();
+        assert_eq!(db.add(&new).as_deref(), Some("foo-1.0-1"));
+        assert_eq!(db.entries.keys().collect::<Vec<_>>(), ["foo-1:1.1-1"]);
+        db.write(&dir, &repo_name).unwrap();
+
+        let describe = |db: &RepoDb| -> Vec<_> {
+            db.entries
+                .iter()
+                .map(|(dir_name, entry)| {
+                    let DbEntry {
+                        name,
+                        version,
+                        file_name,
+                        desc,
+                        files,
+                    } = entry.clone();
+                    (dir_name.clone(), name, version, file_name, desc, files)
+                })
+                .collect()
+        };
+        assert_eq!(
+            describe(&RepoDb::read(&dir, &repo_name).unwrap()),
+            describe(&db)
+        );
+        let entry = &RepoDb::read(&dir, &repo_name).unwrap().entries["foo-1:1.1-1"];
+        assert_eq!(entry.version, "1:1.1-1");
+        assert_eq!(entry.file_name, "foo-1:1.1-1-any.pkg.tar");
+
+        // Without the files database, the file lists are unknown.
+        fs::remove_file(RepoDb::files_path(&dir, &repo_name)).unwrap();
+        let read = RepoDb::read(&dir, &repo_name).unwrap();
+        assert_eq!(read.entries["foo-1:1.1-1"].desc, new.desc());
+        assert_eq!(read.entries["foo-1:1.1-1"].files, None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn write_links_to_the_archives() {
+        let dir = env::temp_dir().join(format!("pacman-repo-builder-repo-db-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let repo_name = RepoName::try_from_string("repo".to_string()).unwrap();
+        let db = RepoDb::default();
+        // Writing twice replaces the existing links.
+        db.write(&dir, &repo_name).unwrap();
+        db.write(&dir, &repo_name).unwrap();
+        for (path, link) in RepoDb::links(&dir, &repo_name) {
+            assert_eq!(fs::read(&link).unwrap(), fs::read(&path).unwrap());
+        }
+        assert!(RepoDb::read(&dir, &repo_name).unwrap().entries.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
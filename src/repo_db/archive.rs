@@ -0,0 +1,52 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Read},
+};
+
+/// Decompress an archive compressed with gzip, xz, or zstd, or return it as is.
+///
+/// The format is detected from the magic bytes, not from the file extension. Unlike
+/// [`TextCollection::from_archive`](arch_pkg_db::TextCollection::from_archive), this supports
+/// the zstd that `makepkg` and `repo-add` compress with by default.
+pub(super) fn decompress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        libflate::gzip::Decoder::new(bytes)?.read_to_end(&mut output)?;
+    } else if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        lzma_rs::xz_decompress(&mut &*bytes, &mut output).map_err(io::Error::other)?;
+    } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        ruzstd::decoding::StreamingDecoder::new(bytes)
+            .map_err(io::Error::other)?
+            .read_to_end(&mut output)?;
+    } else {
+        output.extend_from_slice(bytes);
+    }
+    Ok(output)
+}
+
+/// Compress a tar archive with gzip.
+pub(super) fn compress(tar: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = libflate::gzip::Encoder::new(Vec::new())?;
+    io::Write::write_all(&mut encoder, tar)?;
+    encoder.finish().into_result()
+}
+
+/// Get the content of the `files` files of a decompressed database archive by the name of
+/// their directory, such as `foo-1.0-1`.
+///
+/// [`TextCollection`](arch_pkg_db::TextCollection) only reads the `desc` files.
+pub(super) fn read_files(tar: &[u8]) -> io::Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    let mut archive = tar::Archive::new(tar);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        let Some((dir_name, "files")) = entry_path.split_once('/') else {
+            continue;
+        };
+        let mut text = String::new();
+        entry.read_to_string(&mut text)?;
+        files.insert(dir_name.to_string(), text);
+    }
+    Ok(files)
+}
@@ -0,0 +1,209 @@
+use super::{archive::decompress, RepoDbError};
+use crate::sign::Signer;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::Write,
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+/// Built package archive, with what a repository database says about it.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Package {
+    /// Name of the archive file, such as `foo-1.0-1-x86_64.pkg.tar.zst`.
+    pub file_name: String,
+    /// The `.PKGINFO` of the package.
+    pub info: PkgInfo,
+    /// Size of the archive file in bytes.
+    pub compressed_size: u64,
+    /// Hexadecimal MD5 checksum of the archive file.
+    pub md5sum: String,
+    /// Hexadecimal SHA-256 checksum of the archive file.
+    pub sha256sum: String,
+    /// Base64 of the detached signature next to the archive file, if any.
+    pub pgp_signature: Option<String>,
+    /// Paths of the files that the package installs, directories ending with `/`.
+    pub files: Vec<String>,
+}
+
+/// Fields of the `.PKGINFO` file of a package, in order of appearance.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct PkgInfo {
+    /// Pairs of key and value. Keys that have multiple values appear multiple times.
+    pub fields: Vec<(String, String)>,
+}
+
+impl PkgInfo {
+    /// Parse the `key = value` lines of a `.PKGINFO` file.
+    pub fn parse(text: &str) -> Self {
+        let fields = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+        PkgInfo { fields }
+    }
+
+    /// Get all values of a key.
+    pub fn values<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.fields
+            .iter()
+            .filter(move |(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the first value of a key.
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl Package {
+    /// Read the `.PKGINFO`, the file list, the checksums, and the signature of a package archive.
+    pub fn read(path: &Path) -> Result<Self, RepoDbError> {
+        let read_error = |error| RepoDbError::Read {
+            path: path.to_path_buf(),
+            error,
+        };
+        let bytes = fs::read(path).map_err(read_error)?;
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let tar = decompress(&bytes).map_err(read_error)?;
+
+        let mut info = None;
+        let mut files = Vec::new();
+        let mut archive = tar::Archive::new(tar.as_slice());
+        for entry in archive.entries().map_err(read_error)? {
+            let mut entry = entry.map_err(read_error)?;
+            let entry_path = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+            let entry_path = entry_path.trim_start_matches("./").to_string();
+            if entry_path == ".PKGINFO" {
+                let mut text = String::new();
+                entry.read_to_string(&mut text).map_err(read_error)?;
+                info = Some(PkgInfo::parse(&text));
+                continue;
+            }
+            if entry_path.is_empty() || entry_path.starts_with('.') {
+                continue;
+            }
+            if entry.header().entry_type().is_dir() && !entry_path.ends_with('/') {
+                files.push(entry_path + "/");
+            } else {
+                files.push(entry_path);
+            }
+        }
+        files.sort();
+
+        let signature_path = Signer::signature_path(path);
+        let pgp_signature = match fs::read(&signature_path) {
+            Ok(signature) => Some(STANDARD.encode(signature)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => {
+                return Err(RepoDbError::Read {
+                    path: signature_path,
+                    error,
+                })
+            }
+        };
+
+        let info = info.ok_or_else(|| RepoDbError::MissingPkgInfo {
+            path: path.to_path_buf(),
+        })?;
+        for field in ["pkgname", "pkgver"] {
+            if info.value(field).is_none() {
+                return Err(RepoDbError::MissingField {
+                    path: path.to_path_buf(),
+                    field,
+                });
+            }
+        }
+
+        Ok(Package {
+            file_name,
+            info,
+            compressed_size: bytes.len() as u64,
+            md5sum: hex(&Md5::digest(&bytes)),
+            sha256sum: hex(&Sha256::digest(&bytes)),
+            pgp_signature,
+            files,
+        })
+    }
+
+    /// Get the name of the package.
+    pub fn name(&self) -> &str {
+        self.info.value("pkgname").unwrap_or_default()
+    }
+
+    /// Get the full version of the package, such as `1:2.0-3`.
+    pub fn version(&self) -> &str {
+        self.info.value("pkgver").unwrap_or_default()
+    }
+
+    /// Generate the `desc` file of the package in a repository database, in the order of `repo-add`.
+    pub fn desc(&self) -> String {
+        let info = |key| -> Vec<String> { self.info.values(key).map(str::to_string).collect() };
+        let fields = [
+            ("FILENAME", vec![self.file_name.clone()]),
+            ("NAME", info("pkgname")),
+            ("BASE", info("pkgbase")),
+            ("VERSION", info("pkgver")),
+            ("DESC", info("pkgdesc")),
+            ("GROUPS", info("group")),
+            ("CSIZE", vec![self.compressed_size.to_string()]),
+            ("ISIZE", info("size")),
+            ("MD5SUM", vec![self.md5sum.clone()]),
+            ("SHA256SUM", vec![self.sha256sum.clone()]),
+            ("PGPSIG", self.pgp_signature.iter().cloned().collect()),
+            ("URL", info("url")),
+            ("LICENSE", info("license")),
+            ("ARCH", info("arch")),
+            ("BUILDDATE", info("builddate")),
+            ("PACKAGER", info("packager")),
+            ("REPLACES", info("replaces")),
+            ("CONFLICTS", info("conflict")),
+            ("PROVIDES", info("provides")),
+            ("DEPENDS", info("depend")),
+            ("OPTDEPENDS", info("optdepend")),
+            ("MAKEDEPENDS", info("makedepend")),
+            ("CHECKDEPENDS", info("checkdepend")),
+        ];
+        let mut desc = String::new();
+        for (key, values) in fields {
+            if values.is_empty() {
+                continue;
+            }
+            writeln!(desc, "%{key}%").unwrap();
+            for value in values {
+                writeln!(desc, "{value}").unwrap();
+            }
+            desc.push('\n');
+        }
+        desc
+    }
+
+    /// Generate the `files` file of the package in a repository database.
+    pub fn files(&self) -> String {
+        let mut files = String::from("%FILES%\n");
+        for file in &self.files {
+            writeln!(files, "{file}").unwrap();
+        }
+        files
+    }
+}
+
+/// Encode bytes as lowercase hexadecimal.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
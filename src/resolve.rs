@@ -0,0 +1,169 @@
+use crate::{
+    aur::{AurClient, AurError, AurPackage},
+    pkgbuild_desc::GitPkgBuildDesc,
+    srcinfo::{dependency_name, Srcinfo},
+    sync_db::SyncPackages,
+};
+use derive_more::{Display, Error};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt};
+
+/// How to handle the dependencies that are neither in the official repositories nor in the sources.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[non_exhaustive]
+#[serde(rename_all = "kebab-case")]
+pub enum MissingDependencyAction {
+    /// Report the missing dependencies as errors.
+    #[default]
+    Error,
+    /// Add the missing dependencies from the AUR to the sources, recursively.
+    Add,
+}
+
+/// Dependency that is neither in the official repositories nor in the sources.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct MissingDependency {
+    /// The pkgbase of the source, followed by the names of the dependencies that lead to the
+    /// missing one, the missing one being the last.
+    pub chain: Vec<String>,
+    /// Whether the missing dependency is available in the AUR.
+    pub in_aur: bool,
+}
+
+/// Error when [`resolve_aur_dependencies`] fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ResolveError {
+    #[display("{_0}")]
+    Aur(AurError),
+    #[display("Missing dependencies:{}", MissingList(_0))]
+    Missing(#[error(not(source))] Vec<MissingDependency>),
+}
+
+impl fmt::Display for MissingDependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = if self.in_aur {
+            "available in the AUR but not in the sources"
+        } else {
+            "found in neither the official repositories nor the AUR"
+        };
+        write!(f, "{}: {reason}", self.chain.join(" -> "))
+    }
+}
+
+/// Display one [`MissingDependency`] per line.
+struct MissingList<'a>(&'a [MissingDependency]);
+
+impl fmt::Display for MissingList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for missing in self.0 {
+            write!(f, "\n  {missing}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Find the AUR packages that the sources depend on, directly or indirectly.
+///
+/// Dependencies provided by `official` or by the sources themselves are satisfied. With
+/// [`MissingDependencyAction::Add`], the other dependencies are looked up in the AUR, and the
+/// descriptions of those found are returned. Otherwise, they are reported as missing.
+pub fn resolve_aur_dependencies(
+    sources: &[Srcinfo],
+    official: &SyncPackages,
+    aur: &AurClient,
+    action: MissingDependencyAction,
+) -> Result<Vec<GitPkgBuildDesc>, ResolveError> {
+    let mut provided: HashSet<String> = sources
+        .iter()
+        .flat_map(|srcinfo| {
+            let provisions = srcinfo.provisions().map(|provide| provide.name.as_str());
+            srcinfo.names().chain(provisions)
+        })
+        .map(str::to_string)
+        .collect();
+    let bases: HashSet<&str> = sources
+        .iter()
+        .map(|srcinfo| srcinfo.base.as_str())
+        .collect();
+    let mut visited = HashSet::new();
+    let mut pending = Vec::new();
+    for srcinfo in sources {
+        for (_, dependency) in srcinfo.dependencies() {
+            let name = dependency.name.as_str();
+            if !official.provides(name) && visited.insert(name.to_string()) {
+                pending.push(vec![srcinfo.base.clone(), name.to_string()]);
+            }
+        }
+    }
+
+    let mut added: Vec<AurPackage> = Vec::new();
+    let mut missing = Vec::new();
+    while !pending.is_empty() {
+        pending.retain(|chain: &Vec<String>| !provided.contains(last(chain)));
+        let names: Vec<_> = pending
+            .iter()
+            .map(|chain| last(chain).to_string())
+            .collect();
+        let found = aur.lookup(&names).map_err(ResolveError::Aur)?;
+
+        let mut next = Vec::new();
+        for chain in pending {
+            if provided.contains(last(&chain)) {
+                continue; // provided by a package added earlier in this round
+            }
+            let package = found.iter().find(|package| package.name == last(&chain));
+            let package = match (package, action) {
+                // A package whose pkgbase is already a source cannot be added separately.
+                (Some(package), MissingDependencyAction::Add)
+                    if !bases.contains(package.package_base.as_str()) =>
+                {
+                    package
+                }
+                (package, _) => {
+                    missing.push(MissingDependency {
+                        chain,
+                        in_aur: package.is_some(),
+                    });
+                    continue;
+                }
+            };
+            provided.insert(package.name.clone());
+            provided.extend(
+                package
+                    .provides
+                    .iter()
+                    .map(|provide| dependency_name(provide).to_string()),
+            );
+            let dependencies = package
+                .depends
+                .iter()
+                .chain(&package.make_depends)
+                .chain(&package.check_depends);
+            for dependency in dependencies {
+                let name = dependency_name(dependency);
+                if !official.provides(name) && visited.insert(name.to_string()) {
+                    let mut chain = chain.clone();
+                    chain.push(name.to_string());
+                    next.push(chain);
+                }
+            }
+            added.push(package.clone());
+        }
+        pending = next;
+    }
+
+    if !missing.is_empty() {
+        return Err(ResolveError::Missing(missing));
+    }
+    let added = added
+        .iter()
+        .map(|package| (package.name.as_str(), package.package_base.as_str()));
+    Ok(aur.git_descs(added, None))
+}
+
+/// Get the last name in a dependency chain.
+fn last(chain: &[String]) -> &str {
+    chain.last().expect("chains are never empty")
+}
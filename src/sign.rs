@@ -0,0 +1,355 @@
+use crate::repo_db::link_file;
+use derive_more::{Display, Error};
+use std::{
+    ffi::{OsStr, OsString},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+};
+
+/// Default value of [`Signer::program`].
+pub const DEFAULT_GPG_PROGRAM: &str = "gpg";
+
+/// Wrapper of the CLI of GnuPG that creates and verifies detached signatures.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Signer {
+    /// The name of or path to the `gpg` program.
+    pub program: String,
+    /// ID of the key to sign with.
+    pub key: String,
+    /// GPG home directory, if not the default one.
+    pub home: Option<PathBuf>,
+}
+
+/// Error when signing or verifying a file fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum SignError {
+    #[display("Failed to spawn {program:?}: {error}")]
+    Spawn {
+        program: String,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("{}: Command `{command}` exited with {status}: {stderr}", path.display())]
+    Status {
+        path: PathBuf,
+        command: String,
+        status: ExitStatus,
+        #[error(not(source))]
+        stderr: String,
+    },
+    #[display("{}: The signature is missing", path.display())]
+    MissingSignature {
+        #[error(not(source))]
+        path: PathBuf,
+    },
+    #[display("{}: Signed by {} instead of {key}", path.display(), DisplaySigners(signers))]
+    WrongKey {
+        path: PathBuf,
+        key: String,
+        /// Fingerprints of the primary keys that made the valid signatures.
+        #[error(not(source))]
+        signers: Vec<String>,
+    },
+    #[display("Output of `{_0}` is not valid UTF-8")]
+    NotUtf8(#[error(not(source))] String),
+    #[display("Failed to link {}: {error}", path.display())]
+    Link {
+        path: PathBuf,
+        #[error(source)]
+        error: io::Error,
+    },
+}
+
+/// Error when some signatures are missing or invalid.
+#[derive(Debug, Display, Error)]
+#[display("Signature verification failed:{}", SignErrorList(_0))]
+pub struct VerifyError(#[error(not(source))] pub Vec<SignError>);
+
+impl Signer {
+    /// Sign with `key` using the `gpg` program.
+    pub fn new(key: String) -> Self {
+        Signer {
+            program: DEFAULT_GPG_PROGRAM.to_string(),
+            key,
+            home: None,
+        }
+    }
+
+    /// Replace [`Signer::home`].
+    pub fn with_home(mut self, home: Option<PathBuf>) -> Self {
+        self.home = home;
+        self
+    }
+
+    /// Get the path to the detached signature of a file, which is the path with `.sig` appended.
+    pub fn signature_path(path: &Path) -> PathBuf {
+        let mut signature = path.as_os_str().to_os_string();
+        signature.push(".sig");
+        PathBuf::from(signature)
+    }
+
+    /// Create the binary detached signature of a file, replacing any existing one.
+    ///
+    /// Return the path to the signature.
+    pub fn sign(&self, path: &Path) -> Result<PathBuf, SignError> {
+        let signature = Signer::signature_path(path);
+        self.gpg(
+            path,
+            [
+                "--yes".as_ref(),
+                "--detach-sign".as_ref(),
+                "--no-armor".as_ref(),
+                "--local-user".as_ref(),
+                self.key.as_ref(),
+                "--output".as_ref(),
+                signature.as_os_str(),
+                path.as_os_str(),
+            ],
+        )?;
+        Ok(signature)
+    }
+
+    /// Sign a file and point `link.sig` to its signature, as pacman expects of `repo.db.sig`.
+    ///
+    /// The link is a symbolic link where the platform allows, and a copy elsewhere.
+    pub fn sign_linked(&self, path: &Path, link: &Path) -> Result<(), SignError> {
+        let signature = self.sign(path)?;
+        let link = Signer::signature_path(link);
+        let link_error = |error| SignError::Link {
+            path: link.clone(),
+            error,
+        };
+        if link.symlink_metadata().is_ok() {
+            fs::remove_file(&link).map_err(link_error)?;
+        }
+        link_file(&signature, &link).map_err(link_error)
+    }
+
+    /// Verify that the detached signature of a file is valid and made by [`Signer::key`].
+    ///
+    /// A signature made by a subkey is accepted if its primary key is designated by
+    /// [`Signer::key`], which may be a fingerprint, a key ID, or a user ID.
+    pub fn verify(&self, path: &Path) -> Result<(), SignError> {
+        let signature = Signer::signature_path(path);
+        if !signature.exists() {
+            return Err(SignError::MissingSignature {
+                path: path.to_path_buf(),
+            });
+        }
+        let status = self.gpg(
+            path,
+            [
+                "--status-fd".as_ref(),
+                "1".as_ref(),
+                "--verify".as_ref(),
+                signature.as_os_str(),
+                path.as_os_str(),
+            ],
+        )?;
+        let signers: Vec<_> = status.lines().filter_map(valid_signer).collect();
+        let keys = self.primary_fingerprints(path)?;
+        if !signers.is_empty() && signers.iter().all(|signer| keys.contains(signer)) {
+            return Ok(());
+        }
+        Err(SignError::WrongKey {
+            path: path.to_path_buf(),
+            key: self.key.clone(),
+            signers,
+        })
+    }
+
+    /// Verify the detached signatures of files, and report all the failures at once.
+    pub fn verify_all<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a Path>,
+    ) -> Result<(), VerifyError> {
+        let errors: Vec<_> = paths
+            .into_iter()
+            .filter_map(|path| self.verify(path).err())
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(VerifyError(errors))
+        }
+    }
+
+    /// Get the fingerprints of the primary keys in the keyring that [`Signer::key`] designates,
+    /// in order to verify the file `path`.
+    fn primary_fingerprints(&self, path: &Path) -> Result<Vec<String>, SignError> {
+        let listing = self.gpg(
+            path,
+            [
+                "--with-colons".as_ref(),
+                "--fingerprint".as_ref(),
+                "--list-keys".as_ref(),
+                self.key.as_ref(),
+            ],
+        )?;
+        let mut fingerprints = Vec::new();
+        let mut is_primary = false;
+        for line in listing.lines() {
+            let mut fields = line.split(':');
+            match fields.next() {
+                Some("pub") => is_primary = true,
+                Some("fpr") if is_primary => {
+                    fingerprints.extend(fields.nth(8).map(str::to_string));
+                    is_primary = false;
+                }
+                _ => {}
+            }
+        }
+        Ok(fingerprints)
+    }
+
+    /// Run `gpg` in batch mode with the home directory, about the file `path`, and get its
+    /// stdout.
+    fn gpg<'a>(
+        &self,
+        path: &Path,
+        args: impl IntoIterator<Item = &'a OsStr>,
+    ) -> Result<String, SignError> {
+        let mut all_args: Vec<OsString> = vec!["--batch".into()];
+        if let Some(home) = &self.home {
+            all_args.extend(["--homedir".into(), home.into()]);
+        }
+        all_args.extend(args.into_iter().map(OsString::from));
+        let output = Command::new(&self.program)
+            .args(&all_args)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|error| SignError::Spawn {
+                program: self.program.clone(),
+                error,
+            })?;
+        let command = || {
+            let args = all_args.iter().map(|arg| arg.to_string_lossy());
+            [self.program.as_str().into()]
+                .into_iter()
+                .chain(args)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        if output.status.success() {
+            return String::from_utf8(output.stdout).map_err(|_| SignError::NotUtf8(command()));
+        }
+        Err(SignError::Status {
+            path: path.to_path_buf(),
+            command: command(),
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
+    }
+}
+
+/// Get the fingerprint of the primary key that made a valid signature from a `VALIDSIG` line
+/// of the status output of `gpg --verify`.
+fn valid_signer(line: &str) -> Option<String> {
+    let fields: Vec<_> = line
+        .strip_prefix("[GNUPG:] VALIDSIG ")?
+        .split(' ')
+        .collect();
+    // The primary key fingerprint is the 10th field, the signing key fingerprint the 1st.
+    fields
+        .get(9)
+        .or(fields.first())
+        .map(|fingerprint| fingerprint.to_string())
+}
+
+/// Display the fingerprints of the keys that made the signatures, if any.
+struct DisplaySigners<'a>(&'a [String]);
+
+impl fmt::Display for DisplaySigners<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            [] => write!(f, "no key"),
+            signers => write!(f, "{}", signers.join(", ")),
+        }
+    }
+}
+
+/// Display one [`SignError`] per line.
+struct SignErrorList<'a>(&'a [SignError]);
+
+impl fmt::Display for SignErrorList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for error in self.0 {
+            write!(f, "\n  {error}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::{env, os::unix::fs::PermissionsExt, process};
+
+    /// Create a throwaway key with the user ID `name <name@example.com>` and get its fingerprint.
+    fn generate_key(home: &Path, name: &str) -> String {
+        let user_id = format!("{name} <{name}@example.com>");
+        let status = Command::new(DEFAULT_GPG_PROGRAM)
+            .args(["--batch", "--quiet", "--homedir"])
+            .arg(home)
+            .args(["--passphrase", "", "--quick-gen-key", &user_id])
+            .args(["ed25519", "sign", "never"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        Signer::new(user_id)
+            .with_home(Some(home.to_path_buf()))
+            .primary_fingerprints(home)
+            .unwrap()
+            .remove(0)
+    }
+
+    #[test]
+    fn verify_the_signing_key() {
+        let root = env::temp_dir().join(format!("pacman-repo-builder-sign-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let home = root.join("gnupg");
+        fs::create_dir_all(&home).unwrap();
+        fs::set_permissions(&home, fs::Permissions::from_mode(0o700)).unwrap();
+        let signer_fingerprint = generate_key(&home, "signer");
+        let other_fingerprint = generate_key(&home, "other");
+        let with_key = |key: &str| Signer::new(key.to_string()).with_home(Some(home.clone()));
+
+        let path = root.join("repo.db.tar.gz");
+        fs::write(&path, "content").unwrap();
+        assert!(matches!(
+            with_key("signer@example.com").verify(&path),
+            Err(SignError::MissingSignature { .. }),
+        ));
+
+        let link = root.join("repo.db");
+        with_key("signer@example.com")
+            .sign_linked(&path, &link)
+            .unwrap();
+        assert_eq!(
+            fs::read(Signer::signature_path(&link)).unwrap(),
+            fs::read(Signer::signature_path(&path)).unwrap(),
+        );
+        with_key("signer@example.com").verify(&path).unwrap();
+        with_key(&signer_fingerprint).verify(&path).unwrap();
+        match with_key("other@example.com").verify(&path) {
+            Err(SignError::WrongKey { signers, .. }) => assert_eq!(signers, [signer_fingerprint]),
+            result => panic!("unexpected result: {result:?}"),
+        }
+
+        with_key("other@example.com").sign(&path).unwrap();
+        match with_key("signer@example.com").verify(&path) {
+            Err(SignError::WrongKey { signers, .. }) => assert_eq!(signers, [other_fingerprint]),
+            result => panic!("unexpected result: {result:?}"),
+        }
+
+        let _ = Command::new("gpgconf")
+            .arg("--homedir")
+            .arg(&home)
+            .args(["--kill", "gpg-agent"])
+            .status();
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,485 @@
+mod generate;
+
+pub use generate::SrcinfoStatus;
+
+use crate::{
+    dependency_spec::{DependencySpec, ParseDependencySpecError},
+    graph::DependencyKind,
+    package_version::PackageVersion,
+    pkgbuild_name::PkgBuildName,
+};
+use arch_pkg_text::{
+    value::{Architecture, Dependency},
+    ParsedSrcinfo,
+};
+use derive_more::{Display, Error};
+use std::{
+    env::consts::ARCH,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Name of the file generated by `makepkg --printsrcinfo`.
+pub const SRCINFO_FILE_NAME: &str = ".SRCINFO";
+
+/// Information of a PKGBUILD from its `.SRCINFO`.
+///
+/// Fields that are specific to an architecture other than that of the host are ignored.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Srcinfo {
+    /// The `pkgbase` of the PKGBUILD.
+    pub base: String,
+    /// Full version of the packages, such as `1:2.0-3`.
+    pub version: PackageVersion,
+    /// Architectures supported by the packages, such as `x86_64` or `any`.
+    pub architectures: Vec<String>,
+    /// Build-time dependencies of the PKGBUILD.
+    pub make_depends: Vec<DependencySpec>,
+    /// Dependencies to run the tests of the PKGBUILD.
+    pub check_depends: Vec<DependencySpec>,
+    /// Packages built by the PKGBUILD, in the order of their `pkgname` sections.
+    pub packages: Vec<SrcinfoPackage>,
+}
+
+/// Package built by a PKGBUILD, from its `pkgname` section of the `.SRCINFO`.
+///
+/// Fields that the section does not override are inherited from the `pkgbase` section.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SrcinfoPackage {
+    /// Name of the package.
+    pub name: String,
+    /// Run-time dependencies of the package.
+    pub depends: Vec<DependencySpec>,
+    /// Virtual packages provided by the package.
+    pub provides: Vec<DependencySpec>,
+    /// Packages that conflict with the package.
+    pub conflicts: Vec<DependencySpec>,
+}
+
+/// Error when the text of a `.SRCINFO` is invalid.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ParseSrcinfoError {
+    #[display("{_0}")]
+    Syntax(#[error(not(source))] String),
+    #[display("Missing {_0}")]
+    MissingField(#[error(not(source))] &'static str),
+    #[display("{_0}")]
+    Dependency(ParseDependencySpecError),
+}
+
+/// Error when reading a `.SRCINFO` file fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ReadSrcinfoError {
+    #[display("Failed to read {}: {error}", path.display())]
+    Io {
+        path: PathBuf,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("{}: {error}", path.display())]
+    Parse {
+        path: PathBuf,
+        #[error(source)]
+        error: ParseSrcinfoError,
+    },
+}
+
+/// Difference between a `.SRCINFO` and the [name](PkgBuildName) of its source in the manifest.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub struct SrcinfoMismatchError {
+    /// The pkgbase declared by the manifest.
+    pub declared_base: String,
+    /// The pkgbase in the `.SRCINFO`, if it differs from the declared one.
+    pub actual_base: Option<String>,
+    /// Package names declared by the manifest that the `.SRCINFO` does not build.
+    pub missing: Vec<String>,
+}
+
+impl fmt::Display for SrcinfoMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let SrcinfoMismatchError {
+            declared_base,
+            actual_base,
+            missing,
+        } = self;
+        write!(f, "{declared_base}: .SRCINFO disagrees with the manifest")?;
+        let mut separator = ":";
+        if let Some(actual_base) = actual_base {
+            write!(f, "{separator} pkgbase is {actual_base:?}")?;
+            separator = ",";
+        }
+        if !missing.is_empty() {
+            write!(f, "{separator} does not build {}", missing.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Srcinfo {
+    /// Parse the text of a `.SRCINFO`.
+    pub fn parse(text: &str) -> Result<Srcinfo, ParseSrcinfoError> {
+        let parsed = ParsedSrcinfo::try_from(text)
+            .map_err(|error| ParseSrcinfoError::Syntax(error.to_string()))?;
+        let base_section = &parsed.base;
+        let base = base_section
+            .base_name()
+            .ok_or(ParseSrcinfoError::MissingField("pkgbase"))?
+            .as_str()
+            .to_string();
+        let pkgver = base_section
+            .version()
+            .ok_or(ParseSrcinfoError::MissingField("pkgver"))?
+            .as_str()
+            .to_string();
+        let pkgrel = base_section
+            .release()
+            .ok_or(ParseSrcinfoError::MissingField("pkgrel"))?
+            .as_str()
+            .to_string();
+        let epoch = base_section.epoch().map(|epoch| epoch.as_str().to_string());
+        let mut architectures = Vec::new();
+        for architecture in base_section.architecture() {
+            let architecture = architecture.as_str().to_string();
+            if !architectures.contains(&architecture) {
+                architectures.push(architecture);
+            }
+        }
+        let cleared = cleared_fields(text);
+        let packages = parsed
+            .derivatives
+            .iter()
+            .map(|(name, section)| {
+                let cleared = cleared
+                    .iter()
+                    .filter(|(section, _)| *section == name.as_str())
+                    .map(|(_, field)| *field)
+                    .collect::<Vec<_>>();
+                let inherit =
+                    |field, base, derivative| host_specs(field, base, derivative, &cleared);
+                Ok(SrcinfoPackage {
+                    name: name.as_str().to_string(),
+                    depends: inherit(
+                        "depends",
+                        base_section.dependencies(),
+                        section.dependencies(),
+                    )?,
+                    provides: inherit("provides", base_section.provides(), section.provides())?,
+                    conflicts: inherit("conflicts", base_section.conflicts(), section.conflicts())?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Srcinfo {
+            base,
+            version: PackageVersion::new(epoch, pkgver, Some(pkgrel)),
+            architectures,
+            make_depends: host_specs("makedepends", base_section.make_dependencies(), &[], &[])?,
+            check_depends: host_specs("checkdepends", base_section.check_dependencies(), &[], &[])?,
+            packages,
+        })
+    }
+
+    /// Read the `.SRCINFO` file of a PKGBUILD directory.
+    pub fn read(dir: &Path) -> Result<Srcinfo, ReadSrcinfoError> {
+        let path = dir.join(SRCINFO_FILE_NAME);
+        let text = fs::read_to_string(&path).map_err(|error| ReadSrcinfoError::Io {
+            path: path.clone(),
+            error,
+        })?;
+        Srcinfo::parse(&text).map_err(|error| ReadSrcinfoError::Parse { path, error })
+    }
+
+    /// Iterate over the names of the packages.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.packages.iter().map(|package| package.name.as_str())
+    }
+
+    /// Iterate over the provisions of all the packages.
+    pub fn provisions(&self) -> impl Iterator<Item = &DependencySpec> {
+        self.packages.iter().flat_map(|package| &package.provides)
+    }
+
+    /// Whether one of the packages has the name `name` or provides it.
+    pub fn has_name(&self, name: &str) -> bool {
+        self.names().any(|own| own == name) || self.provisions().any(|own| own.name == name)
+    }
+
+    /// Check that the `.SRCINFO` has the pkgbase and builds all the packages declared by the manifest.
+    pub fn check(&self, package: &PkgBuildName) -> Result<(), SrcinfoMismatchError> {
+        let actual_base = (self.base != package.base()).then(|| self.base.clone());
+        let missing: Vec<_> = package
+            .names()
+            .iter()
+            .filter(|name| !self.names().any(|own| own == name.as_str()))
+            .cloned()
+            .collect();
+        if actual_base.is_none() && missing.is_empty() {
+            return Ok(());
+        }
+        Err(SrcinfoMismatchError {
+            declared_base: package.base().to_string(),
+            actual_base,
+            missing,
+        })
+    }
+
+    /// Get the names of the packages that the `.SRCINFO` builds but the manifest does not declare.
+    pub fn undeclared_names<'a>(&'a self, package: &PkgBuildName) -> Vec<&'a str> {
+        self.names()
+            .filter(|name| !package.names().iter().any(|declared| declared == name))
+            .collect()
+    }
+
+    /// Get the distinct run-time dependencies of all the packages, then the build-time and
+    /// test dependencies, each with the field that declares it.
+    pub fn dependencies(&self) -> Vec<(DependencyKind, &DependencySpec)> {
+        let depends = self
+            .packages
+            .iter()
+            .flat_map(|package| &package.depends)
+            .map(|dependency| (DependencyKind::Depends, dependency));
+        let make_depends = self
+            .make_depends
+            .iter()
+            .map(|dependency| (DependencyKind::MakeDepends, dependency));
+        let check_depends = self
+            .check_depends
+            .iter()
+            .map(|dependency| (DependencyKind::CheckDepends, dependency));
+        let mut dependencies = Vec::new();
+        for item in depends.chain(make_depends).chain(check_depends) {
+            if !dependencies.contains(&item) {
+                dependencies.push(item);
+            }
+        }
+        dependencies
+    }
+}
+
+/// Get the name of a dependency, such as `foo` from `foo>=1.0`.
+pub fn dependency_name(dependency: &str) -> &str {
+    Dependency(dependency).components().0.as_str()
+}
+
+/// Parse the dependencies of a `pkgname` section that apply to the architecture of the host.
+///
+/// Like makepkg, the section overrides the values of the `pkgbase` section separately for each
+/// architecture. `cleared` lists the fields, such as `depends_x86_64`, that the section
+/// overrides with nothing.
+fn host_specs(
+    field: &str,
+    base: &[(Dependency<'_>, Option<Architecture<'_>>)],
+    derivative: &[(Dependency<'_>, Option<Architecture<'_>>)],
+    cleared: &[&str],
+) -> Result<Vec<DependencySpec>, ParseSrcinfoError> {
+    let mut specs = Vec::new();
+    for architecture in [None, Some(ARCH)] {
+        let key = match architecture {
+            None => field.to_string(),
+            Some(architecture) => format!("{field}_{architecture}"),
+        };
+        let mut values = values_of(derivative, architecture);
+        if values.is_empty() && !cleared.contains(&key.as_str()) {
+            values = values_of(base, architecture);
+        }
+        for value in values {
+            specs.push(DependencySpec::parse(value).map_err(ParseSrcinfoError::Dependency)?);
+        }
+    }
+    Ok(specs)
+}
+
+/// Find the fields without a value, such as `depends = `, with the `pkgname` of their sections.
+///
+/// makepkg writes them for the arrays that a package function empties, but [`ParsedSrcinfo`]
+/// skips them.
+fn cleared_fields(text: &str) -> Vec<(&str, &str)> {
+    let mut section = None;
+    let mut cleared = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix("pkgname = ") {
+            section = Some(name.trim());
+        } else if let (Some(section), Some(field)) = (section, line.strip_suffix(" =")) {
+            cleared.push((section, field.trim()));
+        }
+    }
+    cleared
+}
+
+/// Get the values of a field that are specific to `architecture`, or to none if it is `None`.
+fn values_of<'a>(
+    items: &[(Dependency<'a>, Option<Architecture<'a>>)],
+    architecture: Option<&str>,
+) -> Vec<&'a str> {
+    items
+        .iter()
+        .filter(|(_, own)| own.map(|own| own.as_str()) == architecture)
+        .map(|(dependency, _)| dependency.as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `.SRCINFO` of a split PKGBUILD, with values for the host and for another architecture.
+    fn split_srcinfo() -> String {
+        format!(
+            "pkgbase = foo
+\tpkgver = 2.0
+\tpkgrel = 3
+\tepoch = 1
+\tarch = {ARCH}
+\tarch = any
+\tmakedepends = cmake>=3
+\tcheckdepends = python
+\tdepends = glibc
+\tdepends_{ARCH} = libhost
+\tdepends_not-an-arch = libother
+\tprovides = foo-virtual=2.0
+
+pkgname = foo
+\tdepends = glibc
+\tdepends = zlib>=1.2
+
+pkgname = foo-docs
+\tdepends =\x20
+\tprovides =
+
+pkgname = libfoo
+\tprovides = libfoo.so=1-64
+\tconflicts = libfoo-git
+"
+        )
+    }
+
+    fn specs(dependencies: &[DependencySpec]) -> Vec<String> {
+        dependencies.iter().map(ToString::to_string).collect()
+    }
+
+    fn package(name: &str, names: &[&str]) -> PkgBuildName {
+        serde_json::from_value(serde_json::json!({ "base": name, "names": names })).unwrap()
+    }
+
+    #[test]
+    fn parse_split_packages() {
+        let srcinfo = Srcinfo::parse(&split_srcinfo()).unwrap();
+        assert_eq!(srcinfo.base, "foo");
+        assert_eq!(srcinfo.version.to_string(), "1:2.0-3");
+        assert_eq!(srcinfo.architectures, [ARCH, "any"]);
+        assert_eq!(specs(&srcinfo.make_depends), ["cmake>=3"]);
+        assert_eq!(specs(&srcinfo.check_depends), ["python"]);
+        assert_eq!(
+            srcinfo.names().collect::<Vec<_>>(),
+            ["foo", "foo-docs", "libfoo"]
+        );
+
+        let [foo, docs, lib] = &srcinfo.packages[..] else {
+            panic!("unexpected packages {:?}", srcinfo.packages);
+        };
+        // The architecture-specific values of `pkgbase` are only overridden by their own kind.
+        assert_eq!(specs(&foo.depends), ["glibc", "zlib>=1.2", "libhost"]);
+        assert_eq!(specs(&foo.provides), ["foo-virtual=2.0"]);
+        // Empty values clear the inherited ones.
+        assert_eq!(specs(&docs.depends), ["libhost"]);
+        assert!(docs.provides.is_empty());
+        assert_eq!(specs(&lib.depends), ["glibc", "libhost"]);
+        assert_eq!(specs(&lib.provides), ["libfoo.so=1-64"]);
+        assert_eq!(specs(&lib.conflicts), ["libfoo-git"]);
+    }
+
+    #[test]
+    fn dependencies_are_distinct_across_packages() {
+        let srcinfo = Srcinfo::parse(&split_srcinfo()).unwrap();
+        let dependencies: Vec<_> = srcinfo
+            .dependencies()
+            .into_iter()
+            .map(|(kind, dependency)| format!("{kind} {dependency}"))
+            .collect();
+        assert_eq!(
+            dependencies,
+            [
+                "depends glibc",
+                "depends zlib>=1.2",
+                "depends libhost",
+                "makedepends cmake>=3",
+                "checkdepends python",
+            ],
+        );
+        assert!(srcinfo.has_name("foo-docs"));
+        assert!(srcinfo.has_name("libfoo.so"));
+        assert!(srcinfo.has_name("foo-virtual"));
+        assert!(!srcinfo.has_name("cmake"));
+    }
+
+    #[test]
+    fn parse_without_epoch() {
+        let text = "pkgbase = bar\n\tpkgver = 1.0\n\tpkgrel = 1\n\npkgname = bar\n";
+        let srcinfo = Srcinfo::parse(text).unwrap();
+        assert_eq!(srcinfo.version.to_string(), "1.0-1");
+        assert_eq!(srcinfo.version.epoch, None);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |text: &str| Srcinfo::parse(text).unwrap_err();
+        assert!(matches!(
+            error("pkgver = 1.0\n\tpkgrel = 1\n"),
+            ParseSrcinfoError::MissingField("pkgbase"),
+        ));
+        assert!(matches!(
+            error("pkgbase = bar\n\tpkgrel = 1\n"),
+            ParseSrcinfoError::MissingField("pkgver"),
+        ));
+        assert!(matches!(
+            error("pkgbase = bar\n\tpkgver = 1.0\n"),
+            ParseSrcinfoError::MissingField("pkgrel"),
+        ));
+        assert!(matches!(
+            error(
+                "pkgbase = bar\n\tpkgver = 1.0\n\tpkgrel = 1\n\tdepends = baz>=\n\npkgname = bar\n"
+            ),
+            ParseSrcinfoError::Dependency(_),
+        ));
+    }
+
+    #[test]
+    fn check_against_the_manifest() {
+        let srcinfo = Srcinfo::parse(&split_srcinfo()).unwrap();
+        assert!(srcinfo.check(&package("foo", &["foo", "libfoo"])).is_ok());
+
+        let error = srcinfo
+            .check(&package("bar", &["foo", "bar-cli"]))
+            .unwrap_err();
+        assert_eq!(error.declared_base, "bar");
+        assert_eq!(error.actual_base.as_deref(), Some("foo"));
+        assert_eq!(error.missing, ["bar-cli"]);
+        assert_eq!(
+            error.to_string(),
+            "bar: .SRCINFO disagrees with the manifest: pkgbase is \"foo\", does not build bar-cli",
+        );
+
+        let error = srcinfo.check(&package("foo", &["foo-gui"])).unwrap_err();
+        assert_eq!(error.actual_base, None);
+        assert_eq!(
+            error.to_string(),
+            "foo: .SRCINFO disagrees with the manifest: does not build foo-gui",
+        );
+    }
+
+    #[test]
+    fn undeclared_names() {
+        let srcinfo = Srcinfo::parse(&split_srcinfo()).unwrap();
+        assert_eq!(
+            srcinfo.undeclared_names(&package("foo", &["foo-docs"])),
+            ["foo", "libfoo"],
+        );
+        assert!(srcinfo
+            .undeclared_names(&package("foo", &["libfoo", "foo", "foo-docs"]))
+            .is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+use super::{ReadSrcinfoError, Srcinfo, SRCINFO_FILE_NAME};
+use crate::container::{ContainerError, ContainerManager};
+use std::{fs, io, path::Path, time::SystemTime};
+
+/// Name of the build script of a PKGBUILD directory.
+const PKGBUILD_FILE_NAME: &str = "PKGBUILD";
+
+/// Freshness of the `.SRCINFO` of a PKGBUILD directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SrcinfoStatus {
+    /// The `.SRCINFO` is at least as recent as the `PKGBUILD`.
+    Fresh,
+    /// There is no `.SRCINFO`.
+    Missing,
+    /// The `.SRCINFO` is older than the `PKGBUILD`.
+    Stale,
+}
+
+impl Srcinfo {
+    /// Compare the modification times of the `.SRCINFO` and the `PKGBUILD` of a directory.
+    pub fn status(dir: &Path) -> Result<SrcinfoStatus, ReadSrcinfoError> {
+        let Some(srcinfo_time) = modified(&dir.join(SRCINFO_FILE_NAME))? else {
+            return Ok(SrcinfoStatus::Missing);
+        };
+        let pkgbuild_time = modified(&dir.join(PKGBUILD_FILE_NAME))?;
+        Ok(match pkgbuild_time {
+            Some(pkgbuild_time) if pkgbuild_time > srcinfo_time => SrcinfoStatus::Stale,
+            _ => SrcinfoStatus::Fresh,
+        })
+    }
+
+    /// Run `makepkg --printsrcinfo` in a container of `image` and get the text of the `.SRCINFO`.
+    ///
+    /// The PKGBUILD is never sourced on the host.
+    pub fn generate(
+        container: &ContainerManager,
+        image: &str,
+        dir: &Path,
+    ) -> Result<String, ContainerError> {
+        container.run_in_dir(image, dir, &["makepkg", "--printsrcinfo"])
+    }
+}
+
+/// Get the modification time of a file, `None` means that the file does not exist.
+fn modified(path: &Path) -> Result<Option<SystemTime>, ReadSrcinfoError> {
+    match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+        Ok(time) => Ok(Some(time)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(ReadSrcinfoError::Io {
+            path: path.to_path_buf(),
+            error,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs::File, process, time::Duration};
+
+    #[test]
+    fn status_compares_modification_times() {
+        let dir = env::temp_dir().join(format!("pacman-repo-builder-status-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let pkgbuild = dir.join(PKGBUILD_FILE_NAME);
+        let srcinfo = dir.join(SRCINFO_FILE_NAME);
+        fs::write(&pkgbuild, "pkgname=foo\n").unwrap();
+        assert_eq!(Srcinfo::status(&dir).unwrap(), SrcinfoStatus::Missing);
+
+        let set_modified = |path: &Path, time: SystemTime| {
+            File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+        };
+        let time = SystemTime::now();
+        fs::write(&srcinfo, "pkgbase = foo\n").unwrap();
+        set_modified(&pkgbuild, time);
+        set_modified(&srcinfo, time);
+        assert_eq!(Srcinfo::status(&dir).unwrap(), SrcinfoStatus::Fresh);
+
+        set_modified(&pkgbuild, time + Duration::from_secs(1));
+        assert_eq!(Srcinfo::status(&dir).unwrap(), SrcinfoStatus::Stale);
+
+        set_modified(&srcinfo, time + Duration::from_secs(2));
+        assert_eq!(Srcinfo::status(&dir).unwrap(), SrcinfoStatus::Fresh);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
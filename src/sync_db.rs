@@ -0,0 +1,223 @@
+use crate::{
+    container::{ContainerError, ContainerManager},
+    dependency_spec::DependencySpec,
+    srcinfo::{dependency_name, Srcinfo},
+};
+use arch_pkg_db::{
+    desc::{EagerQuerier, Query},
+    text::archive::LoadArchiveError,
+    TextCollection,
+};
+use derive_more::{Display, Error};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Directory of the pacman sync databases in the base image.
+const IMAGE_SYNC_DB_DIR: &str = "/var/lib/pacman/sync";
+
+/// Default names of the official repositories.
+///
+/// `multilib` is left out because the base image does not enable it.
+pub const DEFAULT_OFFICIAL_REPOSITORIES: &[&str] = &["core", "extra"];
+
+/// Names of the packages, real or virtual, that are available from pacman sync databases.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct SyncPackages {
+    /// Names of the packages and of the virtual packages they provide.
+    pub provided: HashSet<String>,
+    /// The packages with their versions and provisions.
+    pub packages: Vec<SyncPackage>,
+}
+
+/// Package of a pacman sync database.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SyncPackage {
+    /// Name of the package.
+    pub name: String,
+    /// Full version of the package, such as `1:2.0-3`.
+    pub version: String,
+    /// What the package provides, such as `libfoo.so=1-64`.
+    ///
+    /// Provisions that cannot be parsed are left out.
+    pub provides: Vec<DependencySpec>,
+}
+
+/// Error when loading a pacman sync database fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum LoadSyncDbError {
+    #[display("Failed to read {}: {error}", path.display())]
+    Read {
+        path: PathBuf,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("Failed to load {}: {error}", path.display())]
+    Archive {
+        path: PathBuf,
+        #[error(source)]
+        error: LoadArchiveError,
+    },
+    #[display("Failed to parse {}: {message}", path.display())]
+    Parse {
+        path: PathBuf,
+        #[error(not(source))]
+        message: String,
+    },
+    #[display("Failed to create {}: {error}", path.display())]
+    CreateDir {
+        path: PathBuf,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("Failed to download the sync databases: {_0}")]
+    Container(ContainerError),
+}
+
+impl SyncPackages {
+    /// Load the sync databases of `repositories`, such as `core.db`, from `dir`.
+    pub fn load(dir: &Path, repositories: &[String]) -> Result<Self, LoadSyncDbError> {
+        let mut packages = SyncPackages::default();
+        for repository in repositories {
+            let path = dir.join(format!("{repository}.db"));
+            let bytes = fs::read(&path).map_err(|error| LoadSyncDbError::Read {
+                path: path.clone(),
+                error,
+            })?;
+            let texts =
+                TextCollection::from_archive(&bytes).map_err(|error| LoadSyncDbError::Archive {
+                    path: path.clone(),
+                    error,
+                })?;
+            let db = texts
+                .parse::<EagerQuerier>()
+                .map_err(|error| LoadSyncDbError::Parse {
+                    path: path.clone(),
+                    message: error.to_string(),
+                })?;
+            for entry in db.entries() {
+                let querier = entry.querier();
+                let name = entry.name().as_str().to_string();
+                let provides: Vec<_> = querier
+                    .provides()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|provide| DependencySpec::parse(provide.as_str()).ok())
+                    .collect();
+                packages.provided.insert(name.clone());
+                for provide in &provides {
+                    packages.provided.insert(provide.name.clone());
+                }
+                packages.packages.push(SyncPackage {
+                    name,
+                    version: querier
+                        .version()
+                        .map(|version| version.as_str().to_string())
+                        .unwrap_or_default(),
+                    provides,
+                });
+            }
+        }
+        Ok(packages)
+    }
+
+    /// Refresh the sync databases of `repositories` in a disposable container of `image`, copy
+    /// them into `dir`, and load them.
+    pub fn download(
+        container: &ContainerManager,
+        image: &str,
+        dir: &Path,
+        repositories: &[String],
+    ) -> Result<Self, LoadSyncDbError> {
+        fs::create_dir_all(dir).map_err(|error| LoadSyncDbError::CreateDir {
+            path: dir.to_path_buf(),
+            error,
+        })?;
+        let name = format!("build-pacman-repo-sync-{}", process::id());
+        let source = format!("{name}:{IMAGE_SYNC_DB_DIR}/.");
+        let downloaded = container
+            .run([
+                "run",
+                "--name",
+                &name,
+                image,
+                "pacman",
+                "-Sy",
+                "--noconfirm",
+            ])
+            .and_then(|_| container.run(["cp".as_ref(), source.as_ref(), dir.as_os_str()]));
+        let removed = container.run(["rm", "--force", &name]);
+        downloaded
+            .and(removed)
+            .map_err(LoadSyncDbError::Container)?;
+        SyncPackages::load(dir, repositories)
+    }
+
+    /// Whether some dependencies of `sources` are provided by none of them, so that the sync
+    /// databases are needed to tell whether they are official packages.
+    pub fn are_needed_by(sources: &[Srcinfo]) -> bool {
+        sources
+            .iter()
+            .flat_map(Srcinfo::dependencies)
+            .any(|(_, dependency)| {
+                !sources
+                    .iter()
+                    .any(|srcinfo| srcinfo.has_name(&dependency.name))
+            })
+    }
+
+    /// Whether a dependency, such as `foo>=1.0`, is satisfied by name by one of the packages.
+    pub fn provides(&self, dependency: &str) -> bool {
+        self.provided.contains(dependency_name(dependency))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::container::fake::{calls, fake_container_manager};
+    use std::env;
+
+    #[test]
+    fn download_from_the_base_image() {
+        let root = env::temp_dir().join(format!("pacman-repo-builder-sync-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let fixture = root.join("fixture");
+        fs::create_dir_all(fixture.join("glibc-2.40-1")).unwrap();
+        fs::write(
+            fixture.join("glibc-2.40-1/desc"),
+            "%NAME%\nglibc\n\n%VERSION%\n2.40-1\n\n%PROVIDES%\nlibc.so=6-64\n\n",
+        )
+        .unwrap();
+
+        // Let `cp` copy a sync database made of the fixture.
+        let script = format!(
+            "if [ \"$1\" = cp ]; then tar -cf \"$3/core.db\" -C '{}' .; fi",
+            fixture.display(),
+        );
+        let container = fake_container_manager(&root, &script);
+        let dir = root.join("sync");
+        let packages =
+            SyncPackages::download(&container, "arch:latest", &dir, &["core".to_string()]).unwrap();
+        assert!(packages.provides("glibc"));
+        assert!(packages.provides("libc.so>=6"));
+        assert_eq!(packages.packages[0].version, "2.40-1");
+
+        let name = format!("build-pacman-repo-sync-{}", process::id());
+        assert_eq!(
+            calls(&root),
+            [
+                format!("run --name {name} arch:latest pacman -Sy --noconfirm"),
+                format!("cp {name}:/var/lib/pacman/sync/. {}", dir.display()),
+                format!("rm --force {name}"),
+            ],
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,4 +1,14 @@
+pub mod context;
+pub mod filter;
 pub mod params;
 pub mod parse;
+pub mod pkgbuild_params;
+pub mod policy;
+pub mod render;
 
+pub use context::TemplateContext;
+pub use filter::TemplateFilter;
 pub use parse::ParseTemplate;
+pub use pkgbuild_params::{ManifestParams, PkgbuildParams, QueryContext, QueryManifestContext};
+pub use policy::CommandPolicy;
+pub use render::{render, RenderTemplateError};
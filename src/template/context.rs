@@ -0,0 +1,499 @@
+use super::{
+    params::{QueryCmd, QueryCommon, QueryEnv, QueryFile},
+    policy::{CommandPolicy, CommandPolicyError},
+    ManifestParams,
+};
+use crate::repo_name::RepoName;
+use derive_more::{Display, Error};
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Interval between checks of whether a command has exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Source of template parameters backed by the environment and the shell of the current process.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct TemplateContext {
+    /// Names of the environment variables that the templates are allowed to read.
+    ///
+    /// `None` means that all environment variables are allowed.
+    pub env_allow_list: Option<Vec<String>>,
+    /// The shell program and its leading arguments, the command would be appended as the last argument.
+    pub shell: Vec<String>,
+    /// Restrictions on the commands.
+    pub policy: CommandPolicy,
+    /// Working directory of the commands, which is usually the directory of the manifest file.
+    ///
+    /// Paths of files to read are also relative to this directory.
+    pub working_dir: PathBuf,
+    /// Commands that were not run because of [`CommandPolicy::dry_run`], shared by the clones.
+    dry_run_commands: Arc<Mutex<Vec<String>>>,
+}
+
+impl TemplateContext {
+    /// Create a context whose commands are run in `working_dir` by `sh -c`.
+    pub fn new(working_dir: PathBuf) -> Self {
+        TemplateContext {
+            env_allow_list: None,
+            shell: vec!["sh".to_string(), "-c".to_string()],
+            policy: CommandPolicy::default(),
+            working_dir,
+            dry_run_commands: Arc::default(),
+        }
+    }
+
+    /// Replace [`TemplateContext::env_allow_list`].
+    pub fn with_env_allow_list(mut self, env_allow_list: Option<Vec<String>>) -> Self {
+        self.env_allow_list = env_allow_list;
+        self
+    }
+
+    /// Replace [`TemplateContext::shell`].
+    pub fn with_shell(mut self, shell: Vec<String>) -> Self {
+        self.shell = shell;
+        self
+    }
+
+    /// Replace [`TemplateContext::policy`].
+    pub fn with_policy(mut self, policy: CommandPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Get the commands that were not run because of [`CommandPolicy::dry_run`], in order.
+    pub fn dry_run_commands(&self) -> Vec<String> {
+        self.dry_run_commands
+            .lock()
+            .expect("lock the dry run commands")
+            .clone()
+    }
+
+    /// Create the template parameters of a manifest.
+    pub fn for_manifest<'a>(
+        &'a self,
+        repo_name: &'a RepoName,
+        variables: &'a BTreeMap<String, String>,
+    ) -> ManifestParams<'a, Self> {
+        ManifestParams {
+            repo_name,
+            variables,
+            context: self,
+        }
+    }
+}
+
+/// Error when [`TemplateContext`] fails to query an environment variable.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum EnvError {
+    #[display("Environment variable {_0:?} is not in the allow list")]
+    NotAllowed(#[error(not(source))] String),
+    #[display("Environment variable {name:?} is not valid UTF-8: {value:?}")]
+    NotUnicode {
+        name: String,
+        #[error(not(source))]
+        value: OsString,
+    },
+}
+
+/// Error when [`TemplateContext`] fails to run a command.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum CommandError {
+    #[display("No shell was configured to run commands")]
+    NoShell,
+    #[display("{_0}")]
+    Policy(CommandPolicyError),
+    #[display("Failed to spawn {shell:?}: {error}")]
+    Spawn {
+        shell: String,
+        #[error(source)]
+        error: io::Error,
+    },
+    #[display("Failed to wait for the command: {_0}")]
+    Wait(io::Error),
+    #[display("The command did not finish within {timeout:?}")]
+    Timeout {
+        #[error(not(source))]
+        timeout: Duration,
+    },
+    #[display("The command exited with {status}: {stderr}")]
+    Status {
+        status: ExitStatus,
+        #[error(not(source))]
+        stderr: String,
+    },
+    #[display("The output of the command exceeded {limit} bytes")]
+    OutputTooLarge {
+        #[error(not(source))]
+        limit: usize,
+    },
+    #[display("The output of the command is not valid UTF-8")]
+    NotUtf8,
+}
+
+/// Error when [`TemplateContext`] fails to read a file.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum FileError {
+    #[display("Path {_0:?} is not inside the working directory")]
+    OutsideWorkingDir(#[error(not(source))] String),
+    #[display("Failed to read {path:?}: {error}")]
+    Read {
+        path: String,
+        #[error(source)]
+        error: io::Error,
+    },
+}
+
+impl QueryCommon for TemplateContext {
+    type Value = String;
+}
+
+impl QueryEnv for TemplateContext {
+    type Name = String;
+    type Error = EnvError;
+    fn env(&self, name: Self::Name) -> Result<Option<Self::Value>, Self::Error> {
+        if let Some(allow_list) = &self.env_allow_list {
+            if !allow_list.contains(&name) {
+                return Err(EnvError::NotAllowed(name));
+            }
+        }
+        match env::var_os(&name) {
+            None => Ok(None),
+            Some(value) => value
+                .into_string()
+                .map(Some)
+                .map_err(|value| EnvError::NotUnicode { name, value }),
+        }
+    }
+}
+
+impl QueryCmd for TemplateContext {
+    type Command = String;
+    type Error = CommandError;
+    fn run(&self, command: Self::Command) -> Result<Self::Value, Self::Error> {
+        self.policy.check(&command).map_err(CommandError::Policy)?;
+        if self.policy.dry_run {
+            self.dry_run_commands
+                .lock()
+                .expect("lock the dry run commands")
+                .push(command);
+            return Ok(String::new());
+        }
+
+        let (shell, shell_args) = self.shell.split_first().ok_or(CommandError::NoShell)?;
+        let mut child = Command::new(shell)
+            .args(shell_args)
+            .arg(&command)
+            .current_dir(&self.working_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| CommandError::Spawn {
+                shell: shell.clone(),
+                error,
+            })?;
+
+        let max_output_size = self.policy.max_output_size;
+        let stdout = child
+            .stdout
+            .take()
+            .map(|pipe| BackgroundReader::spawn(pipe, max_output_size));
+        let stderr = child
+            .stderr
+            .take()
+            .map(|pipe| BackgroundReader::spawn(pipe, None));
+
+        let deadline = self
+            .policy
+            .timeout()
+            .map(|timeout| (timeout, Instant::now() + timeout));
+        let kill = |child: &mut Child, error| {
+            child.kill().map_err(CommandError::Wait)?;
+            child.wait().map_err(CommandError::Wait)?;
+            Err(error)
+        };
+        let status = loop {
+            if let Some(limit) = max_output_size {
+                if stdout.as_ref().is_some_and(BackgroundReader::has_exceeded) {
+                    return kill(&mut child, CommandError::OutputTooLarge { limit });
+                }
+            }
+            if let Some(status) = child.try_wait().map_err(CommandError::Wait)? {
+                break status;
+            }
+            if let Some((timeout, deadline)) = deadline {
+                if Instant::now() >= deadline {
+                    return kill(&mut child, CommandError::Timeout { timeout });
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+
+        // A background process of the command may still hold the pipes after the shell exits.
+        let finish = |reader: Option<BackgroundReader>| match reader {
+            None => Ok(Vec::new()),
+            Some(reader) => reader.finish(deadline),
+        };
+        let stdout = finish(stdout)?;
+        let stderr = finish(stderr)?;
+
+        if let Some(limit) = max_output_size {
+            if stdout.len() > limit {
+                return Err(CommandError::OutputTooLarge { limit });
+            }
+        }
+
+        if !status.success() {
+            return Err(CommandError::Status {
+                status,
+                stderr: String::from_utf8_lossy(&stderr).trim().to_string(),
+            });
+        }
+
+        String::from_utf8(stdout)
+            .map(|stdout| stdout.trim().to_string())
+            .map_err(|_| CommandError::NotUtf8)
+    }
+}
+
+impl QueryFile for TemplateContext {
+    type Path = String;
+    type Error = FileError;
+    fn file(&self, path: Self::Path) -> Result<Self::Value, Self::Error> {
+        let is_inside = Path::new(&path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !is_inside {
+            return Err(FileError::OutsideWorkingDir(path));
+        }
+        match fs::read_to_string(self.working_dir.join(&path)) {
+            Ok(content) => Ok(content.trim().to_string()),
+            Err(error) => Err(FileError::Read { path, error }),
+        }
+    }
+}
+
+/// Content of a pipe that is read in a separate thread.
+struct BackgroundReader {
+    receiver: mpsc::Receiver<io::Result<Vec<u8>>>,
+    exceeded: Arc<AtomicBool>,
+}
+
+impl BackgroundReader {
+    /// Start reading a pipe, until it is closed or more than `limit` bytes are read.
+    ///
+    /// Past the limit, the pipe is closed so that the writers fail instead of blocking.
+    fn spawn<Pipe>(pipe: Pipe, limit: Option<usize>) -> Self
+    where
+        Pipe: Read + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let exceeded = Arc::new(AtomicBool::new(false));
+        let flag = exceeded.clone();
+        thread::spawn(move || {
+            // Reading one byte past the limit is enough to detect an oversized output.
+            let max_len = limit.map_or(u64::MAX, |limit| limit as u64 + 1);
+            let mut buf = Vec::new();
+            let result = pipe.take(max_len).read_to_end(&mut buf);
+            if limit.is_some_and(|limit| buf.len() > limit) {
+                flag.store(true, Ordering::Relaxed);
+            }
+            // The receiver is gone if the command has already failed.
+            let _ = sender.send(result.map(|_| buf));
+        });
+        BackgroundReader { receiver, exceeded }
+    }
+
+    /// Whether more bytes than the limit were written to the pipe.
+    fn has_exceeded(&self) -> bool {
+        self.exceeded.load(Ordering::Relaxed)
+    }
+
+    /// Wait for the pipe to be closed, until the deadline of the timeout if any, and get its
+    /// content.
+    fn finish(self, deadline: Option<(Duration, Instant)>) -> Result<Vec<u8>, CommandError> {
+        let disconnected = || CommandError::Wait(io::Error::other("the reader thread panicked"));
+        let result = match deadline {
+            Some((timeout, deadline)) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                self.receiver
+                    .recv_timeout(remaining)
+                    .map_err(|error| match error {
+                        mpsc::RecvTimeoutError::Timeout => CommandError::Timeout { timeout },
+                        mpsc::RecvTimeoutError::Disconnected => disconnected(),
+                    })?
+            }
+            None => self.receiver.recv().map_err(|_| disconnected())?,
+        };
+        result.map_err(CommandError::Wait)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn context(policy: CommandPolicy) -> TemplateContext {
+        TemplateContext::new(env::temp_dir()).with_policy(policy)
+    }
+
+    fn limit_output(max_output_size: usize) -> CommandPolicy {
+        CommandPolicy {
+            max_output_size: Some(max_output_size),
+            ..CommandPolicy::default()
+        }
+    }
+
+    #[test]
+    fn output_within_the_limit() {
+        let output = context(limit_output(5)).run("printf 'a b '".to_string());
+        assert_eq!(output.unwrap(), "a b");
+    }
+
+    #[test]
+    fn output_past_the_limit() {
+        let error = context(limit_output(100))
+            .run("yes | head -c 200000".to_string())
+            .unwrap_err();
+        assert!(matches!(error, CommandError::OutputTooLarge { limit: 100 }));
+    }
+
+    #[test]
+    fn output_past_the_limit_of_a_writer_that_ignores_the_closed_pipe() {
+        let command = "trap '' PIPE; while :; do echo xxxxxxxx; done".to_string();
+        let error = context(limit_output(100)).run(command).unwrap_err();
+        assert!(matches!(error, CommandError::OutputTooLarge { limit: 100 }));
+    }
+
+    #[test]
+    fn timeout_with_a_background_process_that_holds_the_pipe() {
+        let policy = CommandPolicy {
+            timeout: Some(1),
+            ..CommandPolicy::default()
+        };
+        let start = Instant::now();
+        let error = context(policy)
+            .run("sleep 5 & echo started".to_string())
+            .unwrap_err();
+        assert!(matches!(error, CommandError::Timeout { .. }), "{error}");
+        assert!(start.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn executables_outside_the_allow_list() {
+        let policy = CommandPolicy {
+            allowed_executables: Some(vec!["echo".to_string()]),
+            ..CommandPolicy::default()
+        };
+        let context = context(policy);
+        assert_eq!(context.run("echo allowed".to_string()).unwrap(), "allowed");
+        let error = context
+            .run("echo allowed | tr a b".to_string())
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            CommandError::Policy(CommandPolicyError::ExecutableNotAllowed(executable))
+                if executable == "tr",
+        ));
+    }
+
+    #[test]
+    fn dry_run_records_the_commands() {
+        let policy = CommandPolicy {
+            dry_run: true,
+            ..CommandPolicy::default()
+        };
+        let context = context(policy);
+        assert_eq!(context.run("false".to_string()).unwrap(), "");
+        assert_eq!(context.run("exit 1".to_string()).unwrap(), "");
+        assert_eq!(context.dry_run_commands(), ["false", "exit 1"]);
+    }
+
+    #[test]
+    fn env_allow_list() {
+        let context = context(CommandPolicy::default()).with_env_allow_list(Some(vec![
+            "PATH".to_string(),
+            "NOT_SET_BY_ANYONE".to_string(),
+        ]));
+        assert_eq!(
+            context.env("PATH".to_string()).unwrap(),
+            env::var("PATH").ok()
+        );
+        assert_eq!(context.env("NOT_SET_BY_ANYONE".to_string()).unwrap(), None);
+        assert!(matches!(
+            context.env("HOME".to_string()),
+            Err(EnvError::NotAllowed(name)) if name == "HOME",
+        ));
+    }
+
+    #[test]
+    fn env_without_allow_list() {
+        let context = context(CommandPolicy::default());
+        assert_eq!(
+            context.env("PATH".to_string()).unwrap(),
+            env::var("PATH").ok()
+        );
+    }
+
+    #[test]
+    fn exit_status_and_stderr() {
+        let error = context(CommandPolicy::default())
+            .run("echo ignored; echo '  went wrong ' >&2; exit 3".to_string())
+            .unwrap_err();
+        match error {
+            CommandError::Status { status, stderr } => {
+                assert_eq!(status.code(), Some(3));
+                assert_eq!(stderr, "went wrong");
+            }
+            error => panic!("unexpected error: {error}"),
+        }
+    }
+
+    #[test]
+    fn timeout() {
+        let policy = CommandPolicy {
+            timeout: Some(1),
+            ..CommandPolicy::default()
+        };
+        let context = context(policy);
+        assert_eq!(context.run("echo fast".to_string()).unwrap(), "fast");
+        let error = context.run("sleep 5".to_string()).unwrap_err();
+        assert!(matches!(
+            error,
+            CommandError::Timeout { timeout } if timeout == Duration::from_secs(1),
+        ));
+    }
+
+    #[test]
+    fn custom_and_missing_shell() {
+        let context = context(CommandPolicy::default());
+        let shell = context.clone().with_shell(vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo \"$0\"".to_string(),
+        ]);
+        assert_eq!(shell.run("argument".to_string()).unwrap(), "argument");
+        let error = context
+            .with_shell(Vec::new())
+            .run("echo".to_string())
+            .unwrap_err();
+        assert!(matches!(error, CommandError::NoShell));
+    }
+}
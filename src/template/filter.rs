@@ -0,0 +1,73 @@
+use super::params::ParseTemplateParamQueryError;
+use std::fmt::Write;
+
+/// Transformation to apply to the value of a template parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TemplateFilter<'a> {
+    /// Convert the value to lowercase.
+    Lowercase,
+    /// Convert the value to uppercase.
+    Uppercase,
+    /// Remove leading and trailing whitespaces.
+    Trim,
+    /// Percent-encode all characters other than the unreserved characters of RFC 3986.
+    UrlEncode,
+    /// Use a fallback value when the parameter is not defined.
+    Default(&'a str),
+}
+
+impl<'a> TemplateFilter<'a> {
+    /// Parse a raw filter string.
+    pub fn parse(raw_filter: &'a str) -> Result<Self, ParseTemplateParamQueryError<'a>> {
+        let raw_filter = raw_filter.trim();
+        let (name, argument) = match raw_filter.split_once(':') {
+            Some((name, argument)) => (name.trim_end(), Some(argument.trim_start())),
+            None => (raw_filter, None),
+        };
+
+        let filter = match name {
+            "" => return Err(ParseTemplateParamQueryError::EmptyFilter),
+            "lowercase" => TemplateFilter::Lowercase,
+            "uppercase" => TemplateFilter::Uppercase,
+            "trim" => TemplateFilter::Trim,
+            "urlencode" => TemplateFilter::UrlEncode,
+            "default" => {
+                return argument
+                    .map(TemplateFilter::Default)
+                    .ok_or(ParseTemplateParamQueryError::MissingFilterArgument(name))
+            }
+            _ => return Err(ParseTemplateParamQueryError::UnknownFilter(name)),
+        };
+
+        match argument {
+            None => Ok(filter),
+            Some(_) => Err(ParseTemplateParamQueryError::UnexpectedFilterArgument(name)),
+        }
+    }
+
+    /// Apply the filter to a value, `None` means that the parameter is not defined.
+    pub fn apply(self, value: Option<String>) -> Option<String> {
+        match self {
+            TemplateFilter::Default(fallback) => value.or_else(|| Some(fallback.to_string())),
+            TemplateFilter::Lowercase => value.map(|value| value.to_lowercase()),
+            TemplateFilter::Uppercase => value.map(|value| value.to_uppercase()),
+            TemplateFilter::Trim => value.map(|value| value.trim().to_string()),
+            TemplateFilter::UrlEncode => value.as_deref().map(url_encode),
+        }
+    }
+}
+
+/// Percent-encode all characters other than the unreserved characters of RFC 3986.
+fn url_encode(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                output.push(byte as char)
+            }
+            _ => write!(output, "%{byte:02X}").expect("write to a string"),
+        }
+    }
+    output
+}
@@ -1,3 +1,4 @@
+use super::filter::TemplateFilter;
 use derive_more::{Display, Error};
 use pipe_trait::Pipe;
 
@@ -11,6 +12,36 @@ pub trait QueryCommon {
 pub trait QueryPkgbuild: QueryCommon {
     /// Query the `base` value in the manifest corresponding to the PKGBUILD entry.
     fn base(&self) -> Self::Value;
+    /// Query the name of the first package built by the PKGBUILD entry.
+    fn name(&self) -> Self::Value;
+    /// Query the names of all packages built by the PKGBUILD entry, separated by spaces.
+    fn names(&self) -> Self::Value;
+    /// Query the position of the PKGBUILD entry among the members of its group, starting from 0.
+    fn index(&self) -> Self::Value;
+}
+
+/// Query information from the manifest.
+pub trait QueryManifest: QueryCommon {
+    /// Query the name of the pacman repository.
+    fn repo(&self) -> Self::Value;
+}
+
+/// Query the variables defined in the manifest.
+pub trait QueryVar: QueryCommon {
+    /// Name of the variable.
+    type Key;
+    /// Query the variable.
+    fn var(&self, key: Self::Key) -> Option<Self::Value>;
+}
+
+/// Read the contents of a file.
+pub trait QueryFile: QueryCommon {
+    /// Path to the file.
+    type Path;
+    /// Error when the file fails to be read.
+    type Error;
+    /// Read the contents of the file.
+    fn file(&self, path: Self::Path) -> Result<Self::Value, Self::Error>;
 }
 
 /// Query the environment variables.
@@ -34,11 +65,18 @@ pub trait QueryCmd: QueryCommon {
 }
 
 /// Run query for a template parameter.
-pub trait QueryTemplateParam: QueryPkgbuild + QueryEnv + QueryCmd {
+pub trait QueryTemplateParam:
+    QueryPkgbuild + QueryManifest + QueryVar + QueryEnv + QueryCmd + QueryFile
+{
     /// Query a value for a template parameter
     fn query(&self, query: QueryTemplateParamInput<Self>) -> QueryTemplateParamResult<Self> {
         match query {
             TemplateParamQuery::PkgbuildBase => self.base().pipe(Ok),
+            TemplateParamQuery::PkgbuildName => self.name().pipe(Ok),
+            TemplateParamQuery::PkgbuildNames => self.names().pipe(Ok),
+            TemplateParamQuery::PkgbuildIndex => self.index().pipe(Ok),
+            TemplateParamQuery::RepoName => self.repo().pipe(Ok),
+            TemplateParamQuery::GetVar(key) => self.var(key).ok_or(QueryTemplateParamError::NoVar),
             TemplateParamQuery::GetEnv(name) => self
                 .env(name)
                 .map_err(QueryTemplateParamError::Env)?
@@ -46,22 +84,40 @@ pub trait QueryTemplateParam: QueryPkgbuild + QueryEnv + QueryCmd {
             TemplateParamQuery::RunCommand(command) => {
                 self.run(command).map_err(QueryTemplateParamError::Command)
             }
+            TemplateParamQuery::ReadFile(path) => {
+                self.file(path).map_err(QueryTemplateParamError::File)
+            }
         }
     }
 }
 
-impl<Params: QueryPkgbuild + QueryEnv + QueryCmd> QueryTemplateParam for Params {}
+impl<Params> QueryTemplateParam for Params where
+    Params: QueryPkgbuild + QueryManifest + QueryVar + QueryEnv + QueryCmd + QueryFile
+{
+}
 
 /// Query to pass into [`QueryTemplateParam::query`].
 #[derive(Debug, Clone, Copy)]
 #[non_exhaustive]
-pub enum TemplateParamQuery<EnvName, Command> {
+pub enum TemplateParamQuery<EnvName, Command, FilePath, VarKey> {
     /// Query the base name that corresponds to the concerning PKGBUILD.
     PkgbuildBase,
+    /// Query the name of the first package built by the concerning PKGBUILD.
+    PkgbuildName,
+    /// Query the names of all packages built by the concerning PKGBUILD.
+    PkgbuildNames,
+    /// Query the position of the concerning PKGBUILD among the members of its group.
+    PkgbuildIndex,
+    /// Query the name of the pacman repository.
+    RepoName,
+    /// Query a variable defined in the manifest.
+    GetVar(VarKey),
     /// Query an environment variable.
     GetEnv(EnvName),
     /// Run a command and get its stdout.
     RunCommand(Command),
+    /// Read the contents of a file relative to the manifest file.
+    ReadFile(FilePath),
 }
 
 /// Error when parsing [`TemplateParamQuery`] fails.
@@ -72,14 +128,27 @@ pub enum ParseTemplateParamQueryError<'a> {
     MissingColon,
     #[display("Unknown scheme: {_0:?}")]
     UnknownScheme(#[error(not(source))] &'a str),
+    #[display("Expecting a filter but found nothing")]
+    EmptyFilter,
+    #[display("Unknown filter: {_0:?}")]
+    UnknownFilter(#[error(not(source))] &'a str),
+    #[display("Filter {_0:?} requires an argument")]
+    MissingFilterArgument(#[error(not(source))] &'a str),
+    #[display("Filter {_0:?} does not accept any argument")]
+    UnexpectedFilterArgument(#[error(not(source))] &'a str),
 }
 
-impl<'a> TemplateParamQuery<&'a str, &'a str> {
+impl<'a> TemplateParamQuery<&'a str, &'a str, &'a str, &'a str> {
     /// Parse a raw query string.
     pub fn parse(raw_query: &'a str) -> Result<Self, ParseTemplateParamQueryError<'a>> {
         let raw_query = raw_query.trim();
-        if raw_query == "base" {
-            return Ok(TemplateParamQuery::PkgbuildBase);
+        match raw_query {
+            "base" => return Ok(TemplateParamQuery::PkgbuildBase),
+            "name" => return Ok(TemplateParamQuery::PkgbuildName),
+            "names" => return Ok(TemplateParamQuery::PkgbuildNames),
+            "index" => return Ok(TemplateParamQuery::PkgbuildIndex),
+            "repo" => return Ok(TemplateParamQuery::RepoName),
+            _ => {}
         }
 
         let (scheme, suffix) = raw_query
@@ -90,37 +159,100 @@ impl<'a> TemplateParamQuery<&'a str, &'a str> {
         Ok(match scheme {
             "env" => TemplateParamQuery::GetEnv(suffix),
             "cmd" => TemplateParamQuery::RunCommand(suffix),
+            "file" => TemplateParamQuery::ReadFile(suffix),
+            "var" => TemplateParamQuery::GetVar(suffix),
             _ => return Err(ParseTemplateParamQueryError::UnknownScheme(scheme)),
         })
     }
 }
 
-impl<'a> TryFrom<&'a str> for TemplateParamQuery<&'a str, &'a str> {
+impl<'a> TryFrom<&'a str> for TemplateParamQuery<&'a str, &'a str, &'a str, &'a str> {
     type Error = ParseTemplateParamQueryError<'a>;
     fn try_from(raw_query: &'a str) -> Result<Self, Self::Error> {
         TemplateParamQuery::parse(raw_query)
     }
 }
 
+/// [Query](TemplateParamQuery) whose value is passed through a list of [filters](TemplateFilter).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct TemplateParamPipeline<'a> {
+    /// Query of the initial value.
+    pub query: TemplateParamQuery<&'a str, &'a str, &'a str, &'a str>,
+    /// Filters to apply to the value, from left to right.
+    pub filters: Vec<TemplateFilter<'a>>,
+}
+
+impl<'a> TemplateParamPipeline<'a> {
+    /// Parse a raw pipeline string such as `env:BRANCH | default:master | urlencode`.
+    ///
+    /// Since a shell command may contain pipes, only the trailing segments of a `cmd:` query
+    /// that are valid filters are treated as filters.
+    pub fn parse(raw_pipeline: &'a str) -> Result<Self, ParseTemplateParamQueryError<'a>> {
+        let is_command = raw_pipeline
+            .trim_start()
+            .strip_prefix("cmd")
+            .is_some_and(|rest| rest.trim_start().starts_with(':'));
+
+        if is_command {
+            let mut raw_query = raw_pipeline;
+            let mut filters = Vec::new();
+            while let Some((rest, raw_filter)) = raw_query.rsplit_once('|') {
+                let Ok(filter) = TemplateFilter::parse(raw_filter) else {
+                    break;
+                };
+                filters.push(filter);
+                raw_query = rest;
+            }
+            filters.reverse();
+            let query = TemplateParamQuery::parse(raw_query)?;
+            return Ok(TemplateParamPipeline { query, filters });
+        }
+
+        let mut segments = raw_pipeline.split('|');
+        let query = segments
+            .next()
+            .unwrap_or_default()
+            .pipe(TemplateParamQuery::parse)?;
+        let filters = segments
+            .map(TemplateFilter::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(TemplateParamPipeline { query, filters })
+    }
+}
+
 /// Error type of [`QueryTemplateParam::query`] fails.
 #[derive(Debug, Display, Clone, Error)]
 #[non_exhaustive]
-pub enum QueryTemplateParamError<EnvError, CommandError> {
+pub enum QueryTemplateParamError<EnvError, CommandError, FileError> {
     /// No env with the given name.
     #[display("No env with the given name")]
     NoEnv,
+    /// No variable with the given name.
+    #[display("No variable with the given name")]
+    NoVar,
     /// Failed to query an environment variable.
     Env(EnvError),
     /// Failed to run a command.
     Command(CommandError),
+    /// Failed to read a file.
+    File(FileError),
 }
 
 /// Input to pass to [`QueryTemplateParam::query`].
-type QueryTemplateParamInput<Params> =
-    TemplateParamQuery<<Params as QueryEnv>::Name, <Params as QueryCmd>::Command>;
+type QueryTemplateParamInput<Params> = TemplateParamQuery<
+    <Params as QueryEnv>::Name,
+    <Params as QueryCmd>::Command,
+    <Params as QueryFile>::Path,
+    <Params as QueryVar>::Key,
+>;
 
 /// Return type of [`QueryTemplateParam::query`].
 type QueryTemplateParamResult<Params> = Result<
     <Params as QueryCommon>::Value,
-    QueryTemplateParamError<<Params as QueryEnv>::Error, <Params as QueryCmd>::Error>,
+    QueryTemplateParamError<
+        <Params as QueryEnv>::Error,
+        <Params as QueryCmd>::Error,
+        <Params as QueryFile>::Error,
+    >,
 >;
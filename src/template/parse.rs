@@ -1,3 +1,10 @@
+use super::params::{ParseTemplateParamQueryError, TemplateParamPipeline};
+use derive_more::{Display, Error};
+use lazy_template::{
+    enclosed::{self, simple_escape, simple_query, Segment, SimpleEscapeParser, SimpleQueryParser},
+    Parse,
+};
+
 /// Capability to parse a data structure into a template.
 pub trait ParseTemplate {
     /// The parsed template.
@@ -9,3 +16,71 @@ pub trait ParseTemplate {
     /// Parse the data structure into a template.
     fn parse_template(&self) -> Result<Self::Template<'_>, Self::Error>;
 }
+
+/// Template string that has been parsed ahead of rendering.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ParsedTemplate<'a> {
+    /// Segments of the template, in order.
+    pub segments: Vec<TemplateSegment<'a>>,
+}
+
+/// Segment of a [`ParsedTemplate`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum TemplateSegment<'a> {
+    /// Character to be written as-is.
+    Character(char),
+    /// Query whose value is to be written.
+    Expression {
+        /// The query as written in the template.
+        raw: &'a str,
+        /// The parsed query.
+        pipeline: TemplateParamPipeline<'a>,
+    },
+}
+
+/// Error of the underlying template syntax.
+pub type TemplateSyntaxError =
+    enclosed::ParseError<simple_escape::ParseError, simple_query::ParseError>;
+
+/// Error when parsing a template string fails.
+#[derive(Debug, Display, Clone, Error)]
+#[non_exhaustive]
+pub enum ParseTemplateError<'a> {
+    #[display("Invalid template syntax: {_0}")]
+    Syntax(TemplateSyntaxError),
+    #[display("Invalid query {query:?}: {error}")]
+    Query {
+        query: &'a str,
+        #[error(not(source))]
+        error: ParseTemplateParamQueryError<'a>,
+    },
+}
+
+impl<'s> ParseTemplate for &'s str {
+    type Template<'a>
+        = ParsedTemplate<'s>
+    where
+        Self: 'a;
+    type Error = ParseTemplateError<'s>;
+    fn parse_template(&self) -> Result<Self::Template<'_>, Self::Error> {
+        let parser = enclosed::Parser::curly_braces()
+            .with_escape_parser(SimpleEscapeParser)
+            .with_query_parser(SimpleQueryParser);
+        let mut segments = Vec::new();
+        let mut rest = *self;
+        while !rest.is_empty() {
+            let (segment, next) = parser.parse(rest).map_err(ParseTemplateError::Syntax)?;
+            let segment = match segment {
+                Segment::Character(char) => TemplateSegment::Character(char),
+                Segment::Expression(raw) => TemplateParamPipeline::parse(raw)
+                    .map(|pipeline| TemplateSegment::Expression { raw, pipeline })
+                    .map_err(|error| ParseTemplateError::Query { query: raw, error })?,
+            };
+            segments.push(segment);
+            rest = next;
+        }
+        Ok(ParsedTemplate { segments })
+    }
+}
@@ -0,0 +1,152 @@
+use super::params::{
+    QueryCmd, QueryCommon, QueryEnv, QueryFile, QueryManifest, QueryPkgbuild, QueryVar,
+};
+use crate::{pkgbuild_name::PkgBuildName, repo_name::RepoName};
+use std::collections::BTreeMap;
+
+/// Source of the template parameters that are independent of the manifest.
+pub trait QueryContext:
+    QueryCommon<Value = String>
+    + QueryEnv<Name = String>
+    + QueryCmd<Command = String>
+    + QueryFile<Path = String>
+{
+}
+
+impl<Context> QueryContext for Context where
+    Context: QueryCommon<Value = String>
+        + QueryEnv<Name = String>
+        + QueryCmd<Command = String>
+        + QueryFile<Path = String>
+{
+}
+
+/// Source of the template parameters that are shared by all PKGBUILD entries of a manifest.
+pub trait QueryManifestContext: QueryContext + QueryManifest + QueryVar<Key = String> {}
+
+impl<Context> QueryManifestContext for Context where
+    Context: QueryContext + QueryManifest + QueryVar<Key = String>
+{
+}
+
+/// Template parameters of a manifest.
+#[derive(Debug, Clone, Copy)]
+pub struct ManifestParams<'a, Context> {
+    /// Name of the pacman repository.
+    pub repo_name: &'a RepoName,
+    /// Variables defined in the manifest.
+    pub variables: &'a BTreeMap<String, String>,
+    /// Source of the parameters that are independent of the manifest.
+    pub context: &'a Context,
+}
+
+impl<Context: QueryContext> QueryCommon for ManifestParams<'_, Context> {
+    type Value = String;
+}
+
+impl<Context: QueryContext> QueryManifest for ManifestParams<'_, Context> {
+    fn repo(&self) -> Self::Value {
+        self.repo_name.to_string()
+    }
+}
+
+impl<Context: QueryContext> QueryVar for ManifestParams<'_, Context> {
+    type Key = String;
+    fn var(&self, key: Self::Key) -> Option<Self::Value> {
+        self.variables.get(&key).cloned()
+    }
+}
+
+impl<Context: QueryContext> QueryEnv for ManifestParams<'_, Context> {
+    type Name = String;
+    type Error = <Context as QueryEnv>::Error;
+    fn env(&self, name: Self::Name) -> Result<Option<Self::Value>, Self::Error> {
+        self.context.env(name)
+    }
+}
+
+impl<Context: QueryContext> QueryCmd for ManifestParams<'_, Context> {
+    type Command = String;
+    type Error = <Context as QueryCmd>::Error;
+    fn run(&self, command: Self::Command) -> Result<Self::Value, Self::Error> {
+        self.context.run(command)
+    }
+}
+
+impl<Context: QueryContext> QueryFile for ManifestParams<'_, Context> {
+    type Path = String;
+    type Error = <Context as QueryFile>::Error;
+    fn file(&self, path: Self::Path) -> Result<Self::Value, Self::Error> {
+        self.context.file(path)
+    }
+}
+
+/// Template parameters of a single PKGBUILD entry.
+#[derive(Debug, Clone, Copy)]
+pub struct PkgbuildParams<'a, Context> {
+    /// Name(s) and base of the packages being built by the PKGBUILD.
+    pub package: &'a PkgBuildName,
+    /// Position of the PKGBUILD entry among the members of its group.
+    pub index: usize,
+    /// Source of the parameters that are not specific to the PKGBUILD.
+    pub context: &'a Context,
+}
+
+impl<Context: QueryManifestContext> QueryCommon for PkgbuildParams<'_, Context> {
+    type Value = String;
+}
+
+impl<Context: QueryManifestContext> QueryPkgbuild for PkgbuildParams<'_, Context> {
+    fn base(&self) -> Self::Value {
+        self.package.base().to_string()
+    }
+
+    fn name(&self) -> Self::Value {
+        self.package.name().to_string()
+    }
+
+    fn names(&self) -> Self::Value {
+        self.package.names().join(" ")
+    }
+
+    fn index(&self) -> Self::Value {
+        self.index.to_string()
+    }
+}
+
+impl<Context: QueryManifestContext> QueryManifest for PkgbuildParams<'_, Context> {
+    fn repo(&self) -> Self::Value {
+        self.context.repo()
+    }
+}
+
+impl<Context: QueryManifestContext> QueryVar for PkgbuildParams<'_, Context> {
+    type Key = String;
+    fn var(&self, key: Self::Key) -> Option<Self::Value> {
+        self.context.var(key)
+    }
+}
+
+impl<Context: QueryManifestContext> QueryEnv for PkgbuildParams<'_, Context> {
+    type Name = String;
+    type Error = <Context as QueryEnv>::Error;
+    fn env(&self, name: Self::Name) -> Result<Option<Self::Value>, Self::Error> {
+        self.context.env(name)
+    }
+}
+
+impl<Context: QueryManifestContext> QueryCmd for PkgbuildParams<'_, Context> {
+    type Command = String;
+    type Error = <Context as QueryCmd>::Error;
+    fn run(&self, command: Self::Command) -> Result<Self::Value, Self::Error> {
+        self.context.run(command)
+    }
+}
+
+impl<Context: QueryManifestContext> QueryFile for PkgbuildParams<'_, Context> {
+    type Path = String;
+    type Error = <Context as QueryFile>::Error;
+    fn file(&self, path: Self::Path) -> Result<Self::Value, Self::Error> {
+        self.context.file(path)
+    }
+}
@@ -0,0 +1,239 @@
+use derive_more::{Display, Error};
+use pipe_trait::Pipe;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Restrictions on the commands run by the `cmd:` template queries.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[non_exhaustive]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CommandPolicy {
+    /// Forbid all commands.
+    #[serde(default)]
+    pub disabled: bool,
+    /// Names of or paths to the executables that the commands are allowed to invoke.
+    ///
+    /// `None` means that all executables are allowed.
+    pub allowed_executables: Option<Vec<String>>,
+    /// Maximum number of seconds each command is allowed to run.
+    pub timeout: Option<u64>,
+    /// Maximum number of bytes each command is allowed to write to stdout.
+    pub max_output_size: Option<usize>,
+    /// Record the commands instead of running them, for the caller to print.
+    ///
+    /// Their values would be empty, so nothing should be done with the loaded manifest.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Error when a command violates a [`CommandPolicy`].
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum CommandPolicyError {
+    #[display("Commands are disabled")]
+    Disabled,
+    #[display("Executable {_0:?} is not in the allow list")]
+    ExecutableNotAllowed(#[error(not(source))] String),
+    #[display("Cannot verify the executables of a command substitution")]
+    CommandSubstitution,
+}
+
+impl CommandPolicy {
+    /// Combine two policies, keeping the most restrictive setting of each.
+    pub fn restrict(self, other: CommandPolicy) -> CommandPolicy {
+        let allowed_executables = match (self.allowed_executables, other.allowed_executables) {
+            (Some(left), Some(right)) => left
+                .into_iter()
+                .filter(|executable| right.contains(executable))
+                .collect::<Vec<_>>()
+                .pipe(Some),
+            (left, right) => left.or(right),
+        };
+        CommandPolicy {
+            disabled: self.disabled || other.disabled,
+            allowed_executables,
+            timeout: min_option(self.timeout, other.timeout),
+            max_output_size: min_option(self.max_output_size, other.max_output_size),
+            dry_run: self.dry_run || other.dry_run,
+        }
+    }
+
+    /// Maximum duration of each command.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout.map(Duration::from_secs)
+    }
+
+    /// Check whether a command is allowed to run.
+    ///
+    /// An executable that is invoked by name must be allowed by that name, and one that is
+    /// invoked by path must be allowed by that exact path.
+    ///
+    /// The executables are detected by a best-effort scan of the shell syntax, this is not a sandbox.
+    pub fn check(&self, command: &str) -> Result<(), CommandPolicyError> {
+        if self.disabled {
+            return Err(CommandPolicyError::Disabled);
+        }
+        let Some(allow_list) = &self.allowed_executables else {
+            return Ok(());
+        };
+        if command.contains('`') || command.contains("$(") {
+            return Err(CommandPolicyError::CommandSubstitution);
+        }
+        for executable in executables(command) {
+            if !allow_list.iter().any(|allowed| allowed == executable) {
+                return Err(CommandPolicyError::ExecutableNotAllowed(
+                    executable.to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// List the executables invoked by each stage of a shell command.
+fn executables(command: &str) -> impl Iterator<Item = &str> {
+    command
+        .split(['|', '&', ';', '\n', '(', ')'])
+        .filter_map(|stage| {
+            stage
+                .split_whitespace()
+                .find(|word| !is_assignment(word))
+                .map(|word| word.trim_matches(['\'', '"']))
+        })
+}
+
+/// Whether a shell word is an environment variable assignment such as `FOO=bar`.
+fn is_assignment(word: &str) -> bool {
+    let Some((name, _)) = word.split_once('=') else {
+        return false;
+    };
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|char| char.is_ascii_alphabetic() || char == '_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
+
+/// Get the smaller value of two optional limits, `None` means no limit.
+fn min_option<Value: Ord>(left: Option<Value>, right: Option<Value>) -> Option<Value> {
+    match (left, right) {
+        (Some(left), Some(right)) => Some(left.min(right)),
+        (left, right) => left.or(right),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allow(executables: &[&str]) -> CommandPolicy {
+        CommandPolicy {
+            allowed_executables: Some(executables.iter().map(|name| name.to_string()).collect()),
+            ..CommandPolicy::default()
+        }
+    }
+
+    #[test]
+    fn executables_of_stages() {
+        let command = "FOO=1 BAR=2 git log | head -n 1 && echo done; (cd x) || 'sort' -V";
+        assert_eq!(
+            executables(command).collect::<Vec<_>>(),
+            ["git", "head", "echo", "cd", "sort"],
+        );
+    }
+
+    #[test]
+    fn executables_of_empty_stages() {
+        assert_eq!(executables("a ||  | b").collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(executables("").count(), 0);
+    }
+
+    #[test]
+    fn assignments() {
+        assert!(is_assignment("FOO=bar"));
+        assert!(is_assignment("_foo2="));
+        assert!(!is_assignment("2FOO=bar"));
+        assert!(!is_assignment("--foo=bar"));
+        assert!(!is_assignment("=bar"));
+        assert!(!is_assignment("./a=b"));
+        assert!(!is_assignment("echo"));
+    }
+
+    #[test]
+    fn allow_bare_names() {
+        let policy = allow(&["git", "echo"]);
+        assert!(policy.check("git describe --tags | echo").is_ok());
+        assert!(matches!(
+            policy.check("git log | head"),
+            Err(CommandPolicyError::ExecutableNotAllowed(executable)) if executable == "head",
+        ));
+    }
+
+    #[test]
+    fn bare_names_do_not_allow_paths() {
+        let policy = allow(&["echo"]);
+        for command in ["./evil/echo hi", "/tmp/echo hi", "x/echo"] {
+            assert!(
+                matches!(
+                    policy.check(command),
+                    Err(CommandPolicyError::ExecutableNotAllowed(_)),
+                ),
+                "{command}",
+            );
+        }
+    }
+
+    #[test]
+    fn paths_must_match_exactly() {
+        let policy = allow(&["/usr/bin/echo"]);
+        assert!(policy.check("/usr/bin/echo hi").is_ok());
+        assert!(policy.check("echo hi").is_err());
+        assert!(policy.check("/usr/local/bin/echo hi").is_err());
+    }
+
+    #[test]
+    fn reject_command_substitution() {
+        let policy = allow(&["echo"]);
+        assert!(matches!(
+            policy.check("echo $(whoami)"),
+            Err(CommandPolicyError::CommandSubstitution),
+        ));
+        assert!(matches!(
+            policy.check("echo `whoami`"),
+            Err(CommandPolicyError::CommandSubstitution),
+        ));
+    }
+
+    #[test]
+    fn disabled_and_unrestricted() {
+        let disabled = CommandPolicy {
+            disabled: true,
+            ..CommandPolicy::default()
+        };
+        assert!(matches!(
+            disabled.check("echo"),
+            Err(CommandPolicyError::Disabled),
+        ));
+        assert!(CommandPolicy::default().check("./anything $(x)").is_ok());
+    }
+
+    #[test]
+    fn restrict_keeps_the_strictest_settings() {
+        let left = CommandPolicy {
+            timeout: Some(10),
+            ..allow(&["git", "echo"])
+        };
+        let right = CommandPolicy {
+            timeout: Some(5),
+            max_output_size: Some(100),
+            dry_run: true,
+            ..allow(&["echo", "head"])
+        };
+        let policy = left.restrict(right);
+        assert_eq!(policy.allowed_executables, Some(vec!["echo".to_string()]));
+        assert_eq!(policy.timeout, Some(5));
+        assert_eq!(policy.max_output_size, Some(100));
+        assert!(policy.dry_run);
+        assert!(!policy.disabled);
+    }
+}
@@ -0,0 +1,143 @@
+use super::{
+    params::{
+        QueryCmd, QueryCommon, QueryEnv, QueryFile, QueryTemplateParam, QueryTemplateParamError,
+        QueryVar, TemplateParamPipeline, TemplateParamQuery,
+    },
+    parse::{ParseTemplate, ParseTemplateError, ParsedTemplate, TemplateSegment},
+};
+use derive_more::{Display, Error};
+
+/// Error when [`render`] fails.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum RenderTemplateError<EnvError, CommandError, FileError> {
+    #[display("Invalid template syntax: {_0}")]
+    Syntax(#[error(not(source))] String),
+    #[display("Invalid query {query:?}: {message}")]
+    ParseQuery { query: String, message: String },
+    #[display("Failed to query {query:?}: {error}")]
+    Query {
+        query: String,
+        #[error(source)]
+        error: QueryTemplateParamError<EnvError, CommandError, FileError>,
+    },
+    #[display("The filters of {query:?} left no value")]
+    NoValue {
+        #[error(not(source))]
+        query: String,
+    },
+}
+
+/// Render a template string with the values from the template parameters.
+pub fn render<Params>(template: &str, params: &Params) -> RenderResult<Params>
+where
+    Params: RenderParams,
+{
+    template
+        .parse_template()
+        .map_err(|error| match error {
+            ParseTemplateError::Syntax(error) => RenderTemplateError::Syntax(error.to_string()),
+            ParseTemplateError::Query { query, error } => RenderTemplateError::ParseQuery {
+                query: query.to_string(),
+                message: error.to_string(),
+            },
+        })?
+        .render(params)
+}
+
+impl ParsedTemplate<'_> {
+    /// Render the template with the values from the template parameters.
+    pub fn render<Params>(&self, params: &Params) -> RenderResult<Params>
+    where
+        Params: RenderParams,
+    {
+        let mut output = String::new();
+        for segment in &self.segments {
+            match segment {
+                TemplateSegment::Character(char) => output.push(*char),
+                TemplateSegment::Expression { raw, pipeline } => {
+                    output += &render_pipeline(raw, pipeline, params)?;
+                }
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Query the value of a pipeline and pass it through the filters.
+fn render_pipeline<Params>(
+    raw_query: &str,
+    pipeline: &TemplateParamPipeline<'_>,
+    params: &Params,
+) -> RenderResult<Params>
+where
+    Params: RenderParams,
+{
+    let query = match pipeline.query {
+        TemplateParamQuery::PkgbuildBase => TemplateParamQuery::PkgbuildBase,
+        TemplateParamQuery::PkgbuildName => TemplateParamQuery::PkgbuildName,
+        TemplateParamQuery::PkgbuildNames => TemplateParamQuery::PkgbuildNames,
+        TemplateParamQuery::PkgbuildIndex => TemplateParamQuery::PkgbuildIndex,
+        TemplateParamQuery::RepoName => TemplateParamQuery::RepoName,
+        TemplateParamQuery::GetVar(key) => TemplateParamQuery::GetVar(key.to_string()),
+        TemplateParamQuery::GetEnv(name) => TemplateParamQuery::GetEnv(name.to_string()),
+        TemplateParamQuery::RunCommand(command) => {
+            TemplateParamQuery::RunCommand(command.to_string())
+        }
+        TemplateParamQuery::ReadFile(path) => TemplateParamQuery::ReadFile(path.to_string()),
+    };
+    let (value, missing) = match params.query(query) {
+        Ok(value) => (Some(value), None),
+        Err(error @ (QueryTemplateParamError::NoEnv | QueryTemplateParamError::NoVar)) => {
+            (None, Some(error))
+        }
+        Err(error) => {
+            return Err(RenderTemplateError::Query {
+                query: raw_query.to_string(),
+                error,
+            })
+        }
+    };
+    pipeline
+        .filters
+        .iter()
+        .fold(value, |value, filter| filter.apply(value))
+        .ok_or_else(|| {
+            let query = raw_query.to_string();
+            match missing {
+                Some(error) => RenderTemplateError::Query { query, error },
+                None => RenderTemplateError::NoValue { query },
+            }
+        })
+}
+
+/// Template parameters that can be used by [`render`].
+pub trait RenderParams:
+    QueryTemplateParam
+    + QueryCommon<Value = String>
+    + QueryEnv<Name = String>
+    + QueryCmd<Command = String>
+    + QueryFile<Path = String>
+    + QueryVar<Key = String>
+{
+}
+
+impl<Params> RenderParams for Params where
+    Params: QueryTemplateParam
+        + QueryCommon<Value = String>
+        + QueryEnv<Name = String>
+        + QueryCmd<Command = String>
+        + QueryFile<Path = String>
+        + QueryVar<Key = String>
+{
+}
+
+/// Return type of [`render`].
+pub type RenderResult<Params> = Result<
+    String,
+    RenderTemplateError<
+        <Params as QueryEnv>::Error,
+        <Params as QueryCmd>::Error,
+        <Params as QueryFile>::Error,
+    >,
+>;
@@ -0,0 +1,117 @@
+use std::cmp::Ordering;
+
+/// Compare two full package versions, such as `1:2.0-3`, like `vercmp` and `alpm_pkg_vercmp`.
+///
+/// A missing epoch is `0`. The pkgrels are only compared when both versions have one. Unlike
+/// [`PackageVersion`](crate::package_version::PackageVersion), any string is accepted.
+pub fn vercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let (epoch_a, version_a, release_a) = parse_evr(a);
+    let (epoch_b, version_b, release_b) = parse_evr(b);
+    rpmvercmp(epoch_a, epoch_b)
+        .then_with(|| rpmvercmp(version_a, version_b))
+        .then_with(|| match (release_a, release_b) {
+            (Some(release_a), Some(release_b)) => rpmvercmp(release_a, release_b),
+            _ => Ordering::Equal,
+        })
+}
+
+/// Split a full version into epoch, pkgver, and pkgrel, like `parseEVR` of libalpm.
+pub(crate) fn parse_evr(evr: &str) -> (&str, &str, Option<&str>) {
+    let digits = evr.bytes().take_while(u8::is_ascii_digit).count();
+    let (epoch, version) = match evr[digits..].strip_prefix(':') {
+        Some(version) if digits == 0 => ("0", version),
+        Some(version) => (&evr[..digits], version),
+        None => ("0", evr),
+    };
+    // libalpm only looks for the pkgrel after the digits of a potential epoch.
+    let offset = evr.len() - version.len();
+    match evr[digits.max(offset)..].rfind('-') {
+        Some(index) => {
+            let index = digits.max(offset) + index;
+            (epoch, &evr[offset..index], Some(&evr[index + 1..]))
+        }
+        None => (epoch, version, None),
+    }
+}
+
+/// Compare two version segments, like `rpmvercmp` of libalpm.
+pub(crate) fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut one, mut two) = (0, 0);
+
+    while one < a.len() && two < b.len() {
+        let separator_start = (one, two);
+        while one < a.len() && !a[one].is_ascii_alphanumeric() {
+            one += 1;
+        }
+        while two < b.len() && !b[two].is_ascii_alphanumeric() {
+            two += 1;
+        }
+        if one >= a.len() || two >= b.len() {
+            break;
+        }
+        // Different lengths of separators decide the comparison.
+        let separators = (one - separator_start.0, two - separator_start.1);
+        if separators.0 != separators.1 {
+            return separators.0.cmp(&separators.1);
+        }
+
+        let is_num = a[one].is_ascii_digit();
+        let class: fn(&u8) -> bool = if is_num {
+            u8::is_ascii_digit
+        } else {
+            u8::is_ascii_alphabetic
+        };
+        let end_one = one + a[one..].iter().take_while(|char| class(char)).count();
+        let end_two = two + b[two..].iter().take_while(|char| class(char)).count();
+
+        // Numeric segments are always newer than alpha segments.
+        if end_two == two {
+            return if is_num {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+        }
+
+        let (mut segment_one, mut segment_two) = (&a[one..end_one], &b[two..end_two]);
+        if is_num {
+            while let [b'0', rest @ ..] = segment_one {
+                segment_one = rest;
+            }
+            while let [b'0', rest @ ..] = segment_two {
+                segment_two = rest;
+            }
+            match segment_one.len().cmp(&segment_two.len()) {
+                Ordering::Equal => {}
+                ordering => return ordering,
+            }
+        }
+        match segment_one.cmp(segment_two) {
+            Ordering::Equal => {}
+            ordering => return ordering,
+        }
+
+        one = end_one;
+        two = end_two;
+    }
+
+    let (rest_one, rest_two) = (&a[one.min(a.len())..], &b[two.min(b.len())..]);
+    if rest_one.is_empty() && rest_two.is_empty() {
+        return Ordering::Equal;
+    }
+    // A remaining alpha segment never beats an empty string.
+    let two_is_alpha = rest_two.first().is_some_and(u8::is_ascii_alphabetic);
+    let one_is_alpha = rest_one.first().is_some_and(u8::is_ascii_alphabetic);
+    if (rest_one.is_empty() && !two_is_alpha) || one_is_alpha {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
@@ -0,0 +1,297 @@
+use pacman_repo_builder::{
+    aur::{AurClient, AurError},
+    build_plan::ResolveAurError,
+    manifest::Manifest,
+    pkgbuild_desc::PkgBuildDesc,
+    resolve::{resolve_aur_dependencies, MissingDependencyAction, ResolveError},
+    srcinfo::Srcinfo,
+    sync_db::SyncPackages,
+    template::TemplateContext,
+};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    process, thread,
+};
+
+/// Packages known to the stand-in AUR, as tuples of name, pkgbase, dependencies, and provisions.
+const PACKAGES: &[(&str, &str, &[&str], &[&str])] = &[
+    ("foo", "foo", &["glibc"], &[]),
+    ("bar-cli", "bar", &["glibc"], &[]),
+    ("bar-gui", "bar", &["glibc"], &[]),
+    ("app-deps", "app-deps", &["libfoo>=1", "glibc"], &[]),
+    ("libfoo", "libfoo", &["libbar", "libbaz.so", "glibc"], &[]),
+    ("libbar", "libbar", &["glibc"], &["libbaz.so=1-64"]),
+    ("libbaz", "libbaz", &["glibc"], &[]),
+    ("needs-ghost", "needs-ghost", &["ghost"], &[]),
+];
+
+/// Start a stand-in of the AUR RPC `info` endpoint, and get its base URL.
+///
+/// Querying a package named `broken` makes the endpoint return an error.
+fn serve() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request_line = String::new();
+            let mut reader = BufReader::new(&stream);
+            reader.read_line(&mut request_line).unwrap();
+            let mut header = String::new();
+            while reader.read_line(&mut header).unwrap() > 2 {
+                header.clear();
+            }
+            let body = respond(&request_line);
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len(),
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    format!("http://{address}/")
+}
+
+/// Create the JSON response to a request line such as `GET /rpc/v5/info?arg[]=foo HTTP/1.1`.
+fn respond(request_line: &str) -> String {
+    let target = request_line.split(' ').nth(1).unwrap();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    assert_eq!(path, "/rpc/v5/info");
+    let names: Vec<_> = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(key, _)| *key == "arg[]" || *key == "arg%5B%5D")
+        .map(|(_, name)| name)
+        .collect();
+    if names.contains(&"broken") {
+        return r#"{"type":"error","error":"Something broke","results":[]}"#.to_string();
+    }
+    // Reply in reverse order, as the AUR makes no promise about the order of the results.
+    let results: Vec<_> = PACKAGES
+        .iter()
+        .rev()
+        .filter(|(name, ..)| names.contains(name))
+        .map(|(name, base, depends, provides)| {
+            format!(
+                r#"{{"Name":"{name}","PackageBase":"{base}","Version":"1.0-1","Depends":{depends:?},"Provides":{provides:?}}}"#,
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"type":"multiinfo","results":[{}]}}"#,
+        results.join(",")
+    )
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn info_keeps_the_order_of_the_names() {
+    let aur = AurClient::new(serve());
+    let packages = aur.info(&names(&["bar-gui", "foo", "bar-cli"])).unwrap();
+    let found: Vec<_> = packages
+        .iter()
+        .map(|package| (package.name.as_str(), package.package_base.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [("bar-gui", "bar"), ("foo", "foo"), ("bar-cli", "bar")]
+    );
+    assert_eq!(packages[0].depends, ["glibc"]);
+}
+
+#[test]
+fn info_reports_missing_packages() {
+    let aur = AurClient::new(serve());
+    let error = aur.info(&names(&["foo", "nope"])).unwrap_err();
+    assert!(matches!(error, AurError::NotFound(missing) if missing == ["nope"]));
+    assert_eq!(aur.lookup(&names(&["foo", "nope"])).unwrap().len(), 1);
+}
+
+#[test]
+fn rpc_errors_are_reported() {
+    let aur = AurClient::new(serve());
+    let error = aur.info(&names(&["broken"])).unwrap_err();
+    assert!(matches!(error, AurError::Rpc(message) if message == "Something broke"));
+}
+
+#[test]
+fn git_descs_merge_split_packages() {
+    let aur = AurClient::new("https://aur.example.com/".to_string());
+    let descs = aur.git_descs(
+        [("bar-cli", "bar"), ("foo", "foo"), ("bar-gui", "bar")],
+        Some(1),
+    );
+    assert_eq!(descs.len(), 2);
+    assert_eq!(descs[0].package.base(), "bar");
+    assert_eq!(descs[0].package.names(), ["bar-cli", "bar-gui"]);
+    assert_eq!(descs[0].git_url, "https://aur.example.com/bar.git");
+    assert_eq!(descs[0].git_depth, Some(1));
+    assert_eq!(descs[1].package.base(), "foo");
+}
+
+#[test]
+fn load_and_resolve_without_network() {
+    let dir = env::temp_dir().join(format!("pacman-repo-builder-aur-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("manifest.hjson");
+    // Nothing listens on the AUR URL, so any request would fail.
+    let manifest = "{
+  container-manager: docker
+  container-file: Dockerfile
+  repo-name: repo
+  aur-url: http://127.0.0.1:9
+  sources: [
+    {
+      aur: [
+        bar-cli
+        foo
+        bar-gui
+      ]
+    }
+  ]
+}
+";
+    fs::write(&path, manifest).unwrap();
+    let mut plan = Manifest::load(&path, &TemplateContext::new(dir.clone())).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(plan.sources.is_empty());
+    assert_eq!(
+        plan.aur_names().collect::<Vec<_>>(),
+        ["bar-cli", "foo", "bar-gui"],
+    );
+
+    let mut pkgbases = BTreeMap::from([("foo".to_string(), "foo".to_string())]);
+    let error = plan.resolve_aur(&pkgbases).unwrap_err();
+    assert!(matches!(
+        error,
+        ResolveAurError::Unknown(unknown) if unknown == ["bar-cli", "bar-gui"],
+    ));
+
+    pkgbases.insert("bar-cli".to_string(), "bar".to_string());
+    pkgbases.insert("bar-gui".to_string(), "bar".to_string());
+    plan.resolve_aur(&pkgbases).unwrap();
+    assert!(plan.aur_groups.is_empty());
+    let bases: Vec<_> = plan
+        .sources
+        .iter()
+        .map(|source| match source {
+            PkgBuildDesc::Git(source) => (source.package.base(), source.git_url.as_str()),
+            source => panic!("unexpected source {source:?}"),
+        })
+        .collect();
+    assert_eq!(
+        bases,
+        [
+            ("bar", "http://127.0.0.1:9/bar.git"),
+            ("foo", "http://127.0.0.1:9/foo.git"),
+        ],
+    );
+}
+
+fn srcinfo(base: &str, depends: &[&str]) -> Srcinfo {
+    let mut text = format!("pkgbase = {base}\n\tpkgver = 1.0\n\tpkgrel = 1\n\tarch = any\n");
+    for depend in depends {
+        text.push_str(&format!("\tdepends = {depend}\n"));
+    }
+    text.push_str(&format!("\npkgname = {base}\n"));
+    Srcinfo::parse(&text).unwrap()
+}
+
+fn official() -> SyncPackages {
+    let mut official = SyncPackages::default();
+    official.provided.insert("glibc".to_string());
+    official
+}
+
+#[test]
+fn resolve_adds_dependencies_recursively() {
+    let aur = AurClient::new(serve());
+    let sources = [
+        srcinfo("app", &["app-deps", "glibc", "own"]),
+        srcinfo("own", &[]),
+    ];
+    let descs = resolve_aur_dependencies(&sources, &official(), &aur, MissingDependencyAction::Add)
+        .unwrap();
+    let bases: Vec<_> = descs
+        .iter()
+        .map(|desc| (desc.package.base(), desc.git_url.as_str()))
+        .collect();
+    // `libbaz` is not added, as `libbar` provides `libbaz.so`.
+    assert_eq!(bases.len(), 3);
+    for base in ["app-deps", "libfoo", "libbar"] {
+        assert!(
+            bases
+                .iter()
+                .any(|(found, url)| *found == base && url.ends_with(&format!("/{base}.git"))),
+            "{base} in {bases:?}",
+        );
+    }
+}
+
+#[test]
+fn resolve_without_missing_dependencies() {
+    let aur = AurClient::new(serve());
+    let sources = [srcinfo("app", &["glibc", "own>=1"]), srcinfo("own", &[])];
+    for action in [MissingDependencyAction::Add, MissingDependencyAction::Error] {
+        let descs = resolve_aur_dependencies(&sources, &official(), &aur, action).unwrap();
+        assert!(descs.is_empty());
+    }
+}
+
+#[test]
+fn resolve_reports_missing_dependencies_in_error_mode() {
+    let aur = AurClient::new(serve());
+    let sources = [srcinfo("app", &["foo", "nope>=2"])];
+    let error =
+        resolve_aur_dependencies(&sources, &official(), &aur, MissingDependencyAction::Error)
+            .unwrap_err();
+    let ResolveError::Missing(missing) = &error else {
+        panic!("unexpected error: {error}");
+    };
+    let missing: Vec<_> = missing
+        .iter()
+        .map(|missing| (missing.chain.clone(), missing.in_aur))
+        .collect();
+    assert_eq!(
+        missing,
+        [
+            (names(&["app", "foo"]), true),
+            (names(&["app", "nope"]), false)
+        ],
+    );
+    assert_eq!(
+        error.to_string(),
+        "Missing dependencies:\n  app -> foo: available in the AUR but not in the sources\n  app -> nope: found in neither the official repositories nor the AUR",
+    );
+}
+
+#[test]
+fn resolve_reports_the_chain_of_a_missing_indirect_dependency() {
+    let aur = AurClient::new(serve());
+    let sources = [srcinfo("app", &["needs-ghost"])];
+    let error = resolve_aur_dependencies(&sources, &official(), &aur, MissingDependencyAction::Add)
+        .unwrap_err();
+    match error {
+        ResolveError::Missing(missing) => {
+            assert_eq!(missing.len(), 1);
+            assert_eq!(missing[0].chain, ["app", "needs-ghost", "ghost"]);
+            assert!(!missing[0].in_aur);
+        }
+        error => panic!("unexpected error: {error}"),
+    }
+}
+
+#[test]
+fn resolve_reports_rpc_errors() {
+    let aur = AurClient::new(serve());
+    let sources = [srcinfo("app", &["broken"])];
+    let error = resolve_aur_dependencies(&sources, &official(), &aur, MissingDependencyAction::Add)
+        .unwrap_err();
+    assert!(matches!(error, ResolveError::Aur(AurError::Rpc(_))));
+}
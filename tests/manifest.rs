@@ -0,0 +1,152 @@
+use pacman_repo_builder::manifest::{Manifest, ParseManifestError, ReadManifestError};
+use std::{env, fs, path::PathBuf, process};
+
+const HEADER: &str = "{
+  container-manager: docker
+  container-file: Dockerfile
+  repo-name: repo
+  sources: [
+";
+
+/// Write a manifest with the given sources into a temporary file, read it, and return the
+/// parse error.
+fn parse_error(name: &str, sources: &str) -> ParseManifestError {
+    read_error(name, &format!("{HEADER}{sources}\n  ]\n}}\n"))
+}
+
+/// Write a manifest into a temporary file, read it, and return the parse error.
+fn read_error(name: &str, text: &str) -> ParseManifestError {
+    let dir = env::temp_dir().join(format!("pacman-repo-builder-test-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path: PathBuf = dir.join(format!("{name}.hjson"));
+    fs::write(&path, text).unwrap();
+    let error = Manifest::read(&path).unwrap_err();
+    fs::remove_file(&path).unwrap();
+    match error {
+        ReadManifestError::Parse(error) => error,
+        error => panic!("unexpected error: {error}"),
+    }
+}
+
+#[test]
+fn type_error_has_line_and_key() {
+    let error = parse_error(
+        "type",
+        "    {
+      name: foo
+      git-url: https://example.com/foo.git
+      git-depth: abc
+    }",
+    );
+    assert_eq!(error.key, "sources[0].git-depth");
+    assert_eq!((error.line, error.column), (Some(9), Some(7)));
+}
+
+#[test]
+fn unknown_key_of_group_member_is_reported() {
+    let error = parse_error(
+        "member",
+        "    {
+      git-url-template: https://example.com/{name}.git
+      members: [
+        foo
+        {
+          name: bar
+          git-urll: https://example.com/bar.git
+        }
+      ]
+    }",
+    );
+    assert_eq!(error.key, "sources[0].members[1].git-urll");
+    assert_eq!(error.line, Some(12));
+    assert!(error.message.contains("git-urll"), "{}", error.message);
+    assert!(!error.message.contains("untagged"), "{}", error.message);
+}
+
+#[test]
+fn unknown_key_of_aur_group_is_reported() {
+    let error = parse_error(
+        "aur",
+        "    {
+      aur: [\"foo\"]
+      git-dpth: 1
+    }",
+    );
+    assert_eq!(error.key, "sources[0].git-dpth");
+    assert_eq!(error.line, Some(8));
+    assert!(error.message.contains("unknown field"), "{}", error.message);
+}
+
+#[test]
+fn unknown_keys_of_every_variant_are_reported() {
+    // Quoteless strings run until the end of the line, hence one key per line.
+    let cases = [
+        ("local-desc", "name: foo\ndir: foo", ""),
+        ("split-desc", "base: foo\nnames: [\"foo-a\", \"foo-b\"]\ndir: foo", ""),
+        ("git-desc", "name: foo\ngit-url: https://example.com/foo.git", ""),
+        ("local-group", "dir-path-template: \"{name}\"\nmembers: [\"foo\"]", ""),
+        (
+            "git-group",
+            "git-url-template: \"https://example.com/{name}.git\"\nmembers: [\"foo\"]",
+            "",
+        ),
+        ("aur-group", "aur: [\"foo\"]", ""),
+        (
+            "local-member",
+            "dir-path-template: \"{name}\"\nmembers: [{\nname: foo\ndir: foo\nextra: 1\n}]",
+            ".members[0]",
+        ),
+        (
+            "git-member",
+            "git-url-template: \"https://example.com/{name}.git\"\nmembers: [{\nname: foo\ngit-ref: main\nextra: 1\n}]",
+            ".members[0]",
+        ),
+    ];
+    for (name, keys, parent) in cases {
+        let source = match parent {
+            "" => format!("{{\n{keys}\nextra: 1\n}}"),
+            _ => format!("{{\n{keys}\n}}"),
+        };
+        let error = parse_error(name, &source);
+        assert_eq!(error.key, format!("sources[0]{parent}.extra"), "{name}");
+        assert!(
+            error.message.starts_with("unknown field `extra`"),
+            "{name}: {}",
+            error.message,
+        );
+        let line = HEADER.lines().count()
+            + source.lines().position(|line| line == "extra: 1").unwrap()
+            + 1;
+        assert_eq!((error.line, error.column), (Some(line), Some(1)), "{name}");
+    }
+}
+
+#[test]
+fn line_of_key_in_braceless_manifest() {
+    let error = read_error(
+        "braceless",
+        "container-manager: docker
+container-file: Dockerfile
+repo-name: repo
+# The key of a source is quoted.
+sources: [
+  {
+    \"name\": foo
+    \"dir\": 42
+  }
+]
+",
+    );
+    assert_eq!(error.key, "sources[0].dir");
+    assert_eq!((error.line, error.column), (Some(8), Some(5)));
+    let error = read_error(
+        "braceless-type",
+        "container-manager: docker
+container-file: Dockerfile
+repo-name: 42
+sources: []
+",
+    );
+    assert_eq!(error.key, "repo-name");
+    assert_eq!((error.line, error.column), (Some(3), Some(1)));
+}
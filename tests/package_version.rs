@@ -0,0 +1,196 @@
+use pacman_repo_builder::{
+    dependency_spec::DependencySpec, package_version::PackageVersion, vercmp::vercmp,
+};
+use std::cmp::Ordering::{self, Equal, Greater, Less};
+
+/// Cases of `test/util/vercmptest.sh` of pacman.
+const VERCMP_CASES: &[(&str, &str, Ordering)] = &[
+    // all similar length, no pkgrel
+    ("1.5.0", "1.5.0", Equal),
+    ("1.5.1", "1.5.0", Greater),
+    // mixed length
+    ("1.5.1", "1.5", Greater),
+    // with pkgrel, simple
+    ("1.5.0-1", "1.5.0-1", Equal),
+    ("1.5.0-1", "1.5.0-2", Less),
+    ("1.5.0-1", "1.5.1-1", Less),
+    ("1.5.0-2", "1.5.1-1", Less),
+    // with pkgrel, mixed lengths
+    ("1.5-1", "1.5.1-1", Less),
+    ("1.5-2", "1.5.1-1", Less),
+    ("1.5-2", "1.5.1-2", Less),
+    // mixed pkgrel inclusion
+    ("1.5", "1.5-1", Equal),
+    ("1.5.1", "1.5-1", Greater),
+    ("1.1-1", "1.1", Equal),
+    ("1.0-1", "1.1", Less),
+    ("1.1-1", "1.0", Greater),
+    // alphanumeric versions
+    ("1.5b-1", "1.5-1", Less),
+    ("1.5b", "1.5", Less),
+    ("1.5b-1", "1.5", Less),
+    ("1.5b", "1.5.1", Less),
+    // from the manpage
+    ("1.0a", "1.0alpha", Less),
+    ("1.0alpha", "1.0b", Less),
+    ("1.0b", "1.0beta", Less),
+    ("1.0beta", "1.0rc", Less),
+    ("1.0rc", "1.0", Less),
+    // going crazy? alpha-dotted versions
+    ("1.5.a", "1.5", Greater),
+    ("1.5.b", "1.5.a", Greater),
+    ("1.5.1", "1.5.b", Greater),
+    // alpha dots and dashes
+    ("1.5.b-1", "1.5.b", Equal),
+    ("1.5-1", "1.5.b", Less),
+    // same/similar content, differing separators
+    ("2.0", "2_0", Equal),
+    ("2.0_a", "2_0.a", Equal),
+    ("2.0a", "2.0.a", Less),
+    ("2___a", "2_a", Greater),
+    // epoch included version comparisons
+    ("0:1.0", "0:1.0", Equal),
+    ("0:1.0", "0:1.1", Less),
+    ("1:1.0", "0:1.0", Greater),
+    ("1:1.0", "0:1.1", Greater),
+    ("1:1.0", "2:1.1", Less),
+    // epoch + sometimes present pkgrel
+    ("1:1.0", "0:1.0-1", Greater),
+    ("1:1.0-1", "0:1.1-1", Greater),
+    // epoch included on one version
+    ("0:1.0", "1.0", Equal),
+    ("0:1.0", "1.1", Less),
+    ("0:1.1", "1.0", Greater),
+    ("1:1.0", "1.0", Greater),
+    ("1:1.0", "1.1", Greater),
+    ("1:1.1", "1.1", Greater),
+];
+
+#[test]
+fn vercmp_matches_pacman() {
+    for &(a, b, expected) in VERCMP_CASES {
+        assert_eq!(vercmp(a, b), expected, "vercmp({a:?}, {b:?})");
+        assert_eq!(vercmp(b, a), expected.reverse(), "vercmp({b:?}, {a:?})");
+    }
+}
+
+#[test]
+fn package_version_vercmp_matches_pacman() {
+    for &(a, b, expected) in VERCMP_CASES {
+        let a = a.parse::<PackageVersion>().unwrap();
+        let b = b.parse::<PackageVersion>().unwrap();
+        assert_eq!(a.vercmp(&b), expected, "vercmp({a}, {b})");
+        assert_eq!(b.vercmp(&a), expected.reverse(), "vercmp({b}, {a})");
+    }
+}
+
+#[test]
+fn package_version_ord_refines_vercmp() {
+    for &(a, b, expected) in VERCMP_CASES {
+        let a = a.parse::<PackageVersion>().unwrap();
+        let b = b.parse::<PackageVersion>().unwrap();
+        if expected != Equal {
+            assert_eq!(a.cmp(&b), expected, "{a} cmp {b}");
+        }
+        assert_eq!(b.cmp(&a), a.cmp(&b).reverse(), "{b} cmp {a}");
+    }
+}
+
+#[test]
+fn package_version_ord_is_total() {
+    let version = |text: &str| text.parse::<PackageVersion>().unwrap();
+    let (bare, first, second) = (version("1.0"), version("1.0-1"), version("1.0-2"));
+    assert_eq!(bare.vercmp(&first), Equal);
+    assert_eq!(bare.vercmp(&second), Equal);
+    assert!(bare < first && first < second);
+    assert_ne!(bare, first);
+    assert_eq!(version("0:1.0-1"), first);
+
+    let mut versions = vec![version("1:0.1"), second, bare, first, version("0.9-3")];
+    versions.sort();
+    let sorted: Vec<_> = versions.iter().map(ToString::to_string).collect();
+    assert_eq!(sorted, ["0.9-3", "1.0", "1.0-1", "1.0-2", "1:0.1"]);
+}
+
+#[test]
+fn parse_splits_epoch_pkgver_and_pkgrel() {
+    let cases = [
+        ("1.0", None, "1.0", None),
+        ("1.0-2", None, "1.0", Some("2")),
+        ("3:1.0-2", Some("3"), "1.0", Some("2")),
+        ("3:1.0", Some("3"), "1.0", None),
+        ("1.0-rc1-2", None, "1.0-rc1", Some("2")),
+        ("20240101-1.1", None, "20240101", Some("1.1")),
+    ];
+    for (text, epoch, pkgver, pkgrel) in cases {
+        let version = PackageVersion::parse(text).unwrap();
+        assert_eq!(version.epoch.as_deref(), epoch, "epoch of {text:?}");
+        assert_eq!(version.pkgver, pkgver, "pkgver of {text:?}");
+        assert_eq!(version.pkgrel.as_deref(), pkgrel, "pkgrel of {text:?}");
+        assert_eq!(version.to_string(), text, "display of {text:?}");
+    }
+}
+
+#[test]
+fn parse_rejects_empty_parts() {
+    for text in ["", "1:", "1:-2", "1.0-"] {
+        assert!(PackageVersion::parse(text).is_err(), "{text:?} is accepted");
+    }
+}
+
+#[test]
+fn serde_uses_the_string_form() {
+    let version: PackageVersion = serde_json::from_str("\"2:1.0-3\"").unwrap();
+    assert_eq!(version, PackageVersion::parse("2:1.0-3").unwrap());
+    assert_eq!(serde_json::to_string(&version).unwrap(), "\"2:1.0-3\"");
+    assert!(serde_json::from_str::<PackageVersion>("\"1.0-\"").is_err());
+}
+
+#[test]
+fn constraints_compare_like_pacman() {
+    let satisfies = |dependency: &str, version: &str| {
+        DependencySpec::parse(dependency).unwrap().is_satisfied_by(
+            "foo",
+            &version.parse::<PackageVersion>().unwrap(),
+            &[],
+        )
+    };
+    assert!(satisfies("foo=1.0", "1.0-2"));
+    assert!(satisfies("foo=1.0-2", "1.0-2"));
+    assert!(!satisfies("foo=1.0-1", "1.0-2"));
+    assert!(satisfies("foo>=1.0-2", "1.0-2"));
+    assert!(!satisfies("foo>1.0", "1.0-2"));
+    assert!(satisfies("foo<=1.0", "1.0-2"));
+}
+
+#[test]
+fn provisions_satisfy_dependencies_like_pacman() {
+    let version = "2.0-1".parse::<PackageVersion>().unwrap();
+    let specs = |texts: &[&str]| -> Vec<DependencySpec> {
+        texts
+            .iter()
+            .map(|text| DependencySpec::parse(text).unwrap())
+            .collect()
+    };
+    let satisfies = |dependency: &str, provides: &[&str]| {
+        DependencySpec::parse(dependency).unwrap().is_satisfied_by(
+            "bar",
+            &version,
+            &specs(provides),
+        )
+    };
+    assert!(satisfies("foo", &["foo"]));
+    assert!(satisfies("foo", &["foo=1.0"]));
+    assert!(!satisfies("foo", &["baz"]));
+    assert!(satisfies("foo>=1.0", &["foo=1.0"]));
+    assert!(!satisfies("foo>1.0", &["foo=1.0"]));
+    assert!(satisfies("foo<2.0", &["baz=3.0", "foo=1.0"]));
+    assert!(!satisfies("foo>=1.0", &["foo"]), "unversioned provision");
+    assert!(!satisfies("foo>=1.0", &["foo>=1.0"]), "non-equal provision");
+    assert!(satisfies("bar>=2.0", &[]), "the package itself");
+    assert!(!satisfies("bar>2.0", &[]));
+    assert!(
+        satisfies("bar>2.0", &["bar=3.0"]),
+        "a provision of the own name"
+    );
+}
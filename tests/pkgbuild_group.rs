@@ -0,0 +1,252 @@
+use pacman_repo_builder::{
+    build_plan::BuildPlan,
+    manifest::{ContextLoadManifestError, LoadManifestError, Manifest},
+    pkgbuild_desc::PkgBuildDesc,
+    template::TemplateContext,
+};
+use std::{env, fs, path::PathBuf, process};
+
+/// Write a manifest with the given sources into a temporary directory, and load it.
+fn load(
+    name: &str,
+    sources: &str,
+) -> (
+    PathBuf,
+    Result<BuildPlan, ContextLoadManifestError<TemplateContext>>,
+) {
+    let dir = env::temp_dir().join(format!("pacman-repo-builder-group-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{name}.hjson"));
+    let manifest = format!(
+        "{{
+  container-manager: docker
+  container-file: Dockerfile
+  repo-name: repo
+  variables: {{
+    org: arch
+    version: \"1.2\"
+  }}
+  sources: [
+{sources}
+  ]
+}}
+"
+    );
+    fs::write(&path, manifest).unwrap();
+    let plan = Manifest::load(&path, &TemplateContext::new(dir.clone()));
+    fs::remove_file(&path).unwrap();
+    (dir, plan)
+}
+
+#[test]
+fn git_group_renders_templates_for_each_member() {
+    let (_, plan) = load(
+        "git",
+        "    {
+      git-url-template: https://example.com/{var:org}/{base}.git
+      git-ref-template: v{var:version}
+      sub-dir-template: \"{name}\"
+      git-depth: 1
+      members: [
+        foo
+        {
+          base: bar
+          names: [\"bar-cli\", \"bar-gui\"]
+        }
+        {
+          name: baz
+          git-url: https://example.org/baz.git
+          git-ref: main
+          sub-dir: pkg
+          git-depth: 5
+        }
+      ]
+    }",
+    );
+    let sources: Vec<_> = plan
+        .unwrap()
+        .sources
+        .into_iter()
+        .map(|source| match source {
+            PkgBuildDesc::Git(source) => (
+                source.package.base().to_string(),
+                source.git_url,
+                source.git_ref,
+                source.sub_dir,
+                source.git_depth,
+            ),
+            source => panic!("unexpected source {source:?}"),
+        })
+        .collect();
+    let some = |value: &str| Some(value.to_string());
+    assert_eq!(
+        sources,
+        [
+            (
+                "foo".to_string(),
+                "https://example.com/arch/foo.git".to_string(),
+                some("v1.2"),
+                some("foo"),
+                Some(1),
+            ),
+            (
+                "bar".to_string(),
+                "https://example.com/arch/bar.git".to_string(),
+                some("v1.2"),
+                some("bar-cli"),
+                Some(1),
+            ),
+            (
+                "baz".to_string(),
+                "https://example.org/baz.git".to_string(),
+                some("main"),
+                some("pkg"),
+                Some(5),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn git_group_without_optional_templates() {
+    let (_, plan) = load(
+        "git-minimal",
+        "    {
+      git-url-template: https://example.com/{name}.git
+      members: [\"foo\"]
+    }",
+    );
+    match &plan.unwrap().sources[..] {
+        [PkgBuildDesc::Git(source)] => {
+            assert_eq!(source.git_url, "https://example.com/foo.git");
+            assert_eq!(source.git_ref, None);
+            assert_eq!(source.sub_dir, None);
+            assert_eq!(source.git_depth, None);
+        }
+        sources => panic!("unexpected sources {sources:?}"),
+    }
+}
+
+#[test]
+fn local_group_renders_dir_path_template_and_overrides() {
+    let (dir, plan) = load(
+        "local",
+        "    {
+      dir-path-template: pkgs/{var:org}-{name}
+      members: [
+        foo
+        {
+          name: bar
+          dir: elsewhere/bar
+        }
+      ]
+    }",
+    );
+    let dirs: Vec<_> = plan
+        .unwrap()
+        .sources
+        .into_iter()
+        .map(|source| match source {
+            PkgBuildDesc::Local(source) => PathBuf::from(source.dir),
+            source => panic!("unexpected source {source:?}"),
+        })
+        .collect();
+    assert_eq!(dirs, [dir.join("pkgs/arch-foo"), dir.join("elsewhere/bar")],);
+}
+
+#[test]
+fn index_is_the_position_of_the_member_in_its_group() {
+    let (dir, plan) = load(
+        "index",
+        "    {
+      name: single
+      dir: single
+    }
+    {
+      dir-path-template: pkgs/{index}-{name}
+      members: [\"foo\", \"bar\", \"baz\"]
+    }",
+    );
+    let dirs: Vec<_> = plan
+        .unwrap()
+        .sources
+        .into_iter()
+        .map(|source| match source {
+            PkgBuildDesc::Local(source) => PathBuf::from(source.dir),
+            source => panic!("unexpected source {source:?}"),
+        })
+        .collect();
+    assert_eq!(
+        dirs,
+        [
+            dir.join("single"),
+            dir.join("pkgs/0-foo"),
+            dir.join("pkgs/1-bar"),
+            dir.join("pkgs/2-baz"),
+        ],
+    );
+}
+
+#[test]
+fn failure_to_render_names_the_member_and_field() {
+    let (_, plan) = load(
+        "render-error",
+        "    {
+      git-url-template: https://example.com/{var:missing}.git
+      members: [\"foo\"]
+    }",
+    );
+    match plan.unwrap_err() {
+        LoadManifestError::Template { error, .. } => {
+            assert_eq!(error.member, "foo");
+            assert_eq!(error.field, "git-url-template");
+        }
+        error => panic!("unexpected error: {error}"),
+    }
+}
+
+#[test]
+fn invalid_template_names_the_field() {
+    let (_, plan) = load(
+        "invalid",
+        "    {
+      dir-path-template: \"{nope:foo}\"
+      members: [\"foo\"]
+    }",
+    );
+    match plan.unwrap_err() {
+        LoadManifestError::InvalidTemplate {
+            field, template, ..
+        } => {
+            assert_eq!(field, "sources[0].dir-path-template");
+            assert_eq!(template, "{nope:foo}");
+        }
+        error => panic!("unexpected error: {error}"),
+    }
+}
+
+#[test]
+fn git_refs_that_look_like_options_are_rejected() {
+    let sources = [
+        "    {
+      git-url-template: https://example.com/{name}.git
+      git-ref-template: \"--upload-pack=touch /tmp/{name}\"
+      members: [\"foo\"]
+    }",
+        "    {
+      name: foo
+      git-url: https://example.com/foo.git
+      git-ref: -b
+    }",
+    ];
+    for (index, sources) in sources.into_iter().enumerate() {
+        let (_, plan) = load(&format!("git-ref-{index}"), sources);
+        match plan.unwrap_err() {
+            LoadManifestError::InvalidGitRef { base, git_ref, .. } => {
+                assert_eq!(base, "foo");
+                assert!(git_ref.starts_with('-'), "{git_ref}");
+            }
+            error => panic!("unexpected error: {error}"),
+        }
+    }
+}
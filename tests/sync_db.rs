@@ -0,0 +1,25 @@
+use pacman_repo_builder::{srcinfo::Srcinfo, sync_db::SyncPackages};
+
+fn srcinfo(base: &str, depends: &[&str]) -> Srcinfo {
+    let mut text = format!("pkgbase = {base}\n\tpkgver = 1.0\n\tpkgrel = 1\n\tarch = any\n");
+    for depend in depends {
+        text.push_str(&format!("\tdepends = {depend}\n"));
+    }
+    text.push_str(&format!(
+        "\npkgname = {base}\n\tprovides = {base}-virtual\n"
+    ));
+    Srcinfo::parse(&text).unwrap()
+}
+
+#[test]
+fn sync_databases_are_needed_by_external_dependencies() {
+    let foo = srcinfo("foo", &["bar>=1.0", "bar-virtual"]);
+    let bar = srcinfo("bar", &[]);
+    assert!(!SyncPackages::are_needed_by(&[foo.clone(), bar.clone()]));
+    assert!(SyncPackages::are_needed_by(&[foo]));
+    assert!(SyncPackages::are_needed_by(&[
+        srcinfo("baz", &["glibc"]),
+        bar
+    ]));
+    assert!(!SyncPackages::are_needed_by(&[]));
+}
@@ -0,0 +1,260 @@
+use pacman_repo_builder::{
+    pkgbuild_name::PkgBuildName,
+    repo_name::RepoName,
+    template::{
+        params::{
+            ParseTemplateParamQueryError, QueryCmd, QueryCommon, QueryEnv, QueryFile,
+            QueryTemplateParamError, TemplateParamPipeline, TemplateParamQuery,
+        },
+        render, ManifestParams, PkgbuildParams, RenderTemplateError, TemplateFilter,
+    },
+};
+use std::collections::BTreeMap;
+
+/// Context whose environment has only `BRANCH=Main`, and whose commands echo themselves
+/// unless they are `fail`.
+struct FakeContext;
+
+impl QueryCommon for FakeContext {
+    type Value = String;
+}
+
+impl QueryEnv for FakeContext {
+    type Name = String;
+    type Error = String;
+    fn env(&self, name: String) -> Result<Option<String>, String> {
+        Ok((name == "BRANCH").then(|| "Main".to_string()))
+    }
+}
+
+impl QueryCmd for FakeContext {
+    type Command = String;
+    type Error = String;
+    fn run(&self, command: String) -> Result<String, String> {
+        match command.as_str() {
+            "fail" => Err("command failed".to_string()),
+            _ => Ok(format!("  {command}  ")),
+        }
+    }
+}
+
+impl QueryFile for FakeContext {
+    type Path = String;
+    type Error = String;
+    fn file(&self, path: String) -> Result<String, String> {
+        Err(format!("cannot read {path}"))
+    }
+}
+
+type RenderError = RenderTemplateError<String, String, String>;
+
+fn render_with_fake(template: &str) -> Result<String, RenderError> {
+    let repo_name = RepoName::try_from_string("repo".to_string()).unwrap();
+    let variables = BTreeMap::from([("version".to_string(), "1.2".to_string())]);
+    let manifest = ManifestParams {
+        repo_name: &repo_name,
+        variables: &variables,
+        context: &FakeContext,
+    };
+    let package = PkgBuildName::single("foo".to_string());
+    let params = PkgbuildParams {
+        package: &package,
+        index: 2,
+        context: &manifest,
+    };
+    render(template, &params)
+}
+
+fn pipeline(raw: &str) -> TemplateParamPipeline<'_> {
+    TemplateParamPipeline::parse(raw).unwrap()
+}
+
+#[test]
+fn parse_pipeline_of_filters() {
+    let parsed = pipeline("env:BRANCH | default:master | urlencode");
+    assert!(matches!(parsed.query, TemplateParamQuery::GetEnv("BRANCH")));
+    assert_eq!(
+        parsed.filters,
+        [TemplateFilter::Default("master"), TemplateFilter::UrlEncode],
+    );
+}
+
+#[test]
+fn parse_pipeline_without_filters() {
+    let parsed = pipeline("name");
+    assert!(matches!(parsed.query, TemplateParamQuery::PkgbuildName));
+    assert!(parsed.filters.is_empty());
+}
+
+#[test]
+fn parse_index() {
+    let parsed = pipeline(" index | default: 0");
+    assert!(matches!(parsed.query, TemplateParamQuery::PkgbuildIndex));
+    assert_eq!(parsed.filters, [TemplateFilter::Default("0")]);
+}
+
+#[test]
+fn render_index() {
+    assert_eq!(render_with_fake("{name}-{index}").unwrap(), "foo-2");
+}
+
+#[test]
+fn parse_default_with_colons_in_argument() {
+    let parsed = pipeline("var:url | default: https://example.com");
+    assert_eq!(
+        parsed.filters,
+        [TemplateFilter::Default("https://example.com")],
+    );
+}
+
+#[test]
+fn parse_command_keeps_shell_pipes() {
+    let parsed = pipeline("cmd: git tag | sort -V | tail -n 1");
+    assert!(matches!(
+        parsed.query,
+        TemplateParamQuery::RunCommand("git tag | sort -V | tail -n 1"),
+    ));
+    assert!(parsed.filters.is_empty());
+}
+
+#[test]
+fn parse_command_with_trailing_filters() {
+    let parsed = pipeline("cmd: git tag | sort -V | tail -n 1 | trim | lowercase");
+    assert!(matches!(
+        parsed.query,
+        TemplateParamQuery::RunCommand("git tag | sort -V | tail -n 1"),
+    ));
+    assert_eq!(
+        parsed.filters,
+        [TemplateFilter::Trim, TemplateFilter::Lowercase],
+    );
+}
+
+#[test]
+fn parse_command_stops_at_first_non_filter_from_the_right() {
+    let parsed = pipeline("cmd: echo a | trim | tr a-z A-Z");
+    assert!(matches!(
+        parsed.query,
+        TemplateParamQuery::RunCommand("echo a | trim | tr a-z A-Z"),
+    ));
+    assert!(parsed.filters.is_empty());
+}
+
+#[test]
+fn parse_errors() {
+    let error = |raw| TemplateParamPipeline::parse(raw).unwrap_err();
+    assert!(matches!(
+        error("foo"),
+        ParseTemplateParamQueryError::MissingColon,
+    ));
+    assert!(matches!(
+        error("foo:bar"),
+        ParseTemplateParamQueryError::UnknownScheme("foo"),
+    ));
+    assert!(matches!(
+        error("env:HOME |"),
+        ParseTemplateParamQueryError::EmptyFilter,
+    ));
+    assert!(matches!(
+        error("env:HOME | capitalize"),
+        ParseTemplateParamQueryError::UnknownFilter("capitalize"),
+    ));
+    assert!(matches!(
+        error("env:HOME | default"),
+        ParseTemplateParamQueryError::MissingFilterArgument("default"),
+    ));
+    assert!(matches!(
+        error("env:HOME | trim: both"),
+        ParseTemplateParamQueryError::UnexpectedFilterArgument("trim"),
+    ));
+}
+
+#[test]
+fn apply_filters() {
+    let apply = |filter: TemplateFilter, value: &str| filter.apply(Some(value.to_string()));
+    assert_eq!(apply(TemplateFilter::Lowercase, "FoO"), Some("foo".into()));
+    assert_eq!(apply(TemplateFilter::Uppercase, "FoO"), Some("FOO".into()));
+    assert_eq!(apply(TemplateFilter::Trim, " a b\n"), Some("a b".into()));
+    assert_eq!(
+        apply(TemplateFilter::UrlEncode, "a b/c~d_é"),
+        Some("a%20b%2Fc~d_%C3%A9".into()),
+    );
+    assert_eq!(apply(TemplateFilter::Default("x"), "y"), Some("y".into()));
+    assert_eq!(TemplateFilter::Default("x").apply(None), Some("x".into()));
+    assert_eq!(TemplateFilter::Trim.apply(None), None);
+}
+
+#[test]
+fn render_default_over_missing_env() {
+    assert_eq!(
+        render_with_fake("{env:MISSING | default:master}").unwrap(),
+        "master",
+    );
+    assert_eq!(
+        render_with_fake("{env:BRANCH | default:master | lowercase}").unwrap(),
+        "main",
+    );
+}
+
+#[test]
+fn render_default_over_missing_var() {
+    assert_eq!(render_with_fake("{var:missing | default:0}").unwrap(), "0");
+    assert_eq!(
+        render_with_fake("v{var:version | default:0}").unwrap(),
+        "v1.2"
+    );
+}
+
+#[test]
+fn render_filters_before_default_keep_the_value_missing() {
+    assert_eq!(
+        render_with_fake("{env:MISSING | uppercase | default:x}").unwrap(),
+        "x",
+    );
+}
+
+#[test]
+fn render_missing_value_without_default() {
+    let error = render_with_fake("{env:MISSING | lowercase}").unwrap_err();
+    assert!(matches!(
+        error,
+        RenderTemplateError::Query {
+            error: QueryTemplateParamError::NoEnv,
+            ..
+        },
+    ));
+    let error = render_with_fake("{var:missing}").unwrap_err();
+    assert!(matches!(
+        error,
+        RenderTemplateError::Query {
+            error: QueryTemplateParamError::NoVar,
+            ..
+        },
+    ));
+}
+
+#[test]
+fn render_default_does_not_hide_failures() {
+    let error = render_with_fake("{cmd: fail | default:x}").unwrap_err();
+    assert!(matches!(
+        error,
+        RenderTemplateError::Query {
+            error: QueryTemplateParamError::Command(_),
+            ..
+        },
+    ));
+}
+
+#[test]
+fn render_command_with_filters() {
+    assert_eq!(
+        render_with_fake("{cmd: echo A | tr A B | trim | lowercase}").unwrap(),
+        "echo a | tr a b",
+    );
+}
+
+#[test]
+fn render_invalid_query() {
+    let error = render_with_fake("{env:HOME | nope}").unwrap_err();
+    assert!(matches!(error, RenderTemplateError::ParseQuery { .. }));
+}